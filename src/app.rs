@@ -1,146 +1,2059 @@
-use std::io;
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use std::{collections::HashMap, io, time::{Duration, Instant}};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Layout, Rect},
     style::Stylize,
-    widgets::{Paragraph, Widget}, 
+    widgets::{Paragraph, Tabs, Widget, Wrap}, 
     Frame
 };
 
+use tracing::Level;
+
 use crate::tui;
 use crate::config::Config;
-use crate::core::tsh::Tsh;
+use crate::core::{
+    access_request::{RequestState, ReviewDecision},
+    activity::BackendAction,
+    audit::{self, AuditAction, AuditEntry},
+    clipboard::Clipboard,
+    fanout::{self, FanoutRun},
+    guardrail::{find_guardrail, Guardrail},
+    health_check::HealthCheckRun,
+    hooks::{self, HookContext, HookStage},
+    ide_export::{self, ExportSummary},
+    pty_session::{self, PtySession, SessionOrigin},
+    query::DatabaseKind,
+    recording::Recording,
+    result_export::{self, ResultDestination},
+    schema_diff::SchemaDiffRun,
+    task::Task,
+    resources::{Application, KubeCluster, Node, Resource},
+    session_limit::{format_duration, SessionDeadline, SessionLimit, SessionOutcome},
+    snippet,
+    subshell,
+    template::{self, PreparedCommand},
+    tsh::{ConnectionArgs, Database, Tsh},
+    tunnel::Tunnel,
+    worker::{DeadlineState, HeldConnection},
+};
 use crate::widgets::{
-    connect_dialog::{ConnectDialog, Step},
+    styles,
+    access_request_view::{AccessRequestView, RequestFocus, RequestMode},
+    activity_view::ActivityView,
+    audit_view::AuditView,
+    connect_dialog::{ConnectDialog, ConnectMethod, Step},
     database_list::StatefulDatabaseList,
-    search_dialog::SearchDialog
+    export_dialog::{ExportDialog, ExportStep},
+    fanout_view::{FanoutFocus, FanoutView},
+    health_check_view::HealthCheckView,
+    ide_export_dialog::IdeExportDialog,
+    query_console::{ConsoleFocus, QueryConsole},
+    result_export_dialog::ResultExportDialog,
+    schema_diff_view::{DiffFocus, SchemaDiffView},
+    snippet_dialog::SnippetDialog,
+    details_table::DetailsTable,
+    replay_view::ReplayView,
+    request_dialog::RequestStep,
+    resource_list::StatefulResourceList,
+    review_view::ReviewView,
+    search_dialog::SearchDialog,
+    session_view::SessionView,
+    ssh_dialog::SshDialog,
+    toast::Toast,
 };
 
+// Upper bound on how long the loop blocks on input before updating state
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+// Faster redraws while a session is on screen so its output feels live
+const SESSION_TICK_RATE: Duration = Duration::from_millis(25);
+
 enum InputMode {
     Normal,
     Searching,
     Connecting,
+    Requesting,
+    Reviewing,
+    SelectingLogin,
+    Exporting,
+    ExportingIde,
+    Auditing,
+    Session,
+    Replaying,
+    Querying,
+    ExportingResults,
+    FanningOut,
+    PickingSnippet,
+    Monitoring,
+    Diffing,
+    Testing,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ResourceTab {
+    Databases,
+    Applications,
+    Nodes,
+    Kubernetes,
+}
+
+impl ResourceTab {
+    const ALL: [ResourceTab; 4] = [
+        ResourceTab::Databases,
+        ResourceTab::Applications,
+        ResourceTab::Nodes,
+        ResourceTab::Kubernetes,
+    ];
+
+    fn to_string(self) -> &'static str {
+        match self {
+            ResourceTab::Databases => "Databases",
+            ResourceTab::Applications => "Applications",
+            ResourceTab::Nodes => "SSH Nodes",
+            ResourceTab::Kubernetes => "Kubernetes",
+        }
+    }
+
+    fn to_index(self) -> usize {
+        ResourceTab::ALL.iter().position(|tab| *tab == self).unwrap_or(0)
+    }
+
+    fn next(self) -> ResourceTab {
+        ResourceTab::ALL[(self.to_index() + 1) % ResourceTab::ALL.len()]
+    }
+
+    fn previous(self) -> ResourceTab {
+        let count = ResourceTab::ALL.len();
+        ResourceTab::ALL[(self.to_index() + count - 1) % count]
+    }
+}
+
+// A confirmed in-place session, run once the TUI can hand over the terminal
+struct PendingSession {
+    args: ConnectionArgs,
+    method: ConnectMethod,
+    prepared: Option<PreparedCommand>,
+    reason: Option<String>,
+    limit: Option<SessionLimit>,
+}
+
+// A confirmed connection waiting on its pre-connect hooks, which run off the render loop
+struct PreConnect {
+    task: Task<Result<(), String>>,
+    session: PendingSession,
+    kind: Option<DatabaseKind>,
+}
+
+pub struct App {
+    pending_session: Option<PendingSession>,
+    pre_connect: Option<PreConnect>,
+    teleport: Tsh,
+    config: Config,
+    current_tab: ResourceTab,
+    database_list: StatefulDatabaseList,
+    app_list: StatefulResourceList<Application>,
+    node_list: StatefulResourceList<Node>,
+    kube_list: StatefulResourceList<KubeCluster>,
+    ssh_dialog: SshDialog,
+    export_dialog: ExportDialog,
+    ide_export_dialog: IdeExportDialog,
+    ide_export_task: Option<Task<io::Result<ExportSummary>>>,
+    template_task: Option<Task<Result<PreparedCommand, String>>>,
+    toast: Toast,
+    clipboard: Clipboard,
+    search_dialog: SearchDialog,
+    connect_dialog: ConnectDialog,
+    access_request_view: AccessRequestView,
+    review_view: ReviewView,
+    audit_view: AuditView,
+    session_view: SessionView,
+    replay_view: ReplayView,
+    query_console: QueryConsole,
+    result_export_dialog: ResultExportDialog,
+    fanout_view: FanoutView,
+    snippet_dialog: SnippetDialog,
+    activity_view: ActivityView,
+    schema_diff_view: SchemaDiffView,
+    health_check_view: HealthCheckView,
+    input_mode: InputMode,
+    last_search: String,
+    logged_in: bool,
+    exit: bool,
+    show_search: bool,
+    show_connect: bool,
+    show_requests: bool,
+    show_reviews: bool,
+    show_audit: bool,
+    show_sessions: bool,
+    show_replays: bool,
+    show_query: bool,
+    show_fanout: bool,
+    show_activity: bool,
+    show_diff: bool,
+    last_area: Rect,
 }
 
-pub struct App {
-    pub initiate_connection: bool,
-    teleport: Tsh,
-    config: Config,
-    database_list: StatefulDatabaseList,
-    search_dialog: SearchDialog,
-    connect_dialog: ConnectDialog,
-    input_mode: InputMode,
-    logged_in: bool,
-    exit: bool,
-    show_search: bool,
-    show_connect: bool,
-}
+impl App {
+    pub fn new(config: Config) -> App {
+        let mut app = App {
+            teleport: Tsh::new(),
+            clipboard: Clipboard::new(&config.clipboard),
+            config,
+            current_tab: ResourceTab::Databases,
+            database_list: StatefulDatabaseList::new(),
+            app_list: StatefulResourceList::new("Applications"),
+            node_list: StatefulResourceList::new("SSH Nodes"),
+            kube_list: StatefulResourceList::new("Kubernetes Clusters"),
+            ssh_dialog: SshDialog::new(),
+            export_dialog: ExportDialog::new(),
+            ide_export_dialog: IdeExportDialog::new(),
+            ide_export_task: None,
+            template_task: None,
+            toast: Toast::new(),
+            search_dialog: SearchDialog::new(),
+            connect_dialog: ConnectDialog::new(),
+            access_request_view: AccessRequestView::new(),
+            review_view: ReviewView::new(),
+            audit_view: AuditView::new(),
+            session_view: SessionView::new(),
+            replay_view: ReplayView::new(),
+            query_console: QueryConsole::new(),
+            result_export_dialog: ResultExportDialog::new(),
+            fanout_view: FanoutView::new(),
+            snippet_dialog: SnippetDialog::new(),
+            activity_view: ActivityView::new(),
+            schema_diff_view: SchemaDiffView::new(),
+            health_check_view: HealthCheckView::new(),
+            input_mode: InputMode::Normal,
+            last_search: String::new(),
+            logged_in: false,
+            exit: false,
+            show_search: false,
+            show_connect: false,
+            show_requests: false,
+            show_reviews: false,
+            show_audit: false,
+            show_sessions: false,
+            show_replays: false,
+            show_query: false,
+            show_fanout: false,
+            show_activity: false,
+            show_diff: false,
+            last_area: Rect::default(),
+            pending_session: None,
+            pre_connect: None,
+        };
+        app.connect_dialog.with_templates(app.config.templates.clone());
+        app.connect_dialog.with_guardrails(app.config.guardrails.clone());
+        app.connect_dialog.with_default_rules(app.config.defaults.clone());
+        app.connect_dialog.with_session_limits(app.config.session_limits.clone());
+        app.database_list.with_guardrails(app.config.guardrails.clone());
+        if let Some(err) = app.config.load_error.clone() {
+            app.toast.error(&err);
+        }
+        app
+    }
+
+    pub fn run(&mut self, terminal: &mut tui::Tui) -> io::Result<()>{
+        while !self.exit {
+            // Sessions follow the terminal's size before each draw
+            self.last_area = terminal.size()?;
+            self.session_view.resize_all(self.main_area());
+
+            // Render new state
+            terminal.draw(|frame| self.render_frame(frame))?;
+
+            // Take input
+            self.handle_events()?;
+
+            if let Some(session) = self.pending_session.take() {
+                self.run_session(terminal, session)?;
+            }
+
+            // Update state
+            self.set_database_list_state();
+            self.set_resource_list_state();
+            self.set_selected_database_state();
+            self.set_user_list_state();
+            self.connect_dialog.set_database_name_state();
+            if self.show_requests {
+                self.access_request_view.poll_requests();
+            }
+            if self.show_reviews {
+                self.review_view.poll_requests();
+            }
+            self.poll_ide_export();
+            self.poll_template_preview();
+            self.poll_pre_connect();
+            self.poll_sessions();
+            self.poll_query_console();
+            self.poll_fanout();
+            self.poll_activity();
+            self.poll_schema_diff();
+            self.poll_health_check();
+            if self.show_replays {
+                self.replay_view.tick();
+            }
+        }
+        self.close_sessions();
+        Ok(())
+    }
+
+    fn main_area(&self) -> Rect {
+        let (_, main_area, _) = get_high_level_areas(self.last_area);
+        main_area
+    }
+
+    fn open_session(
+        &mut self,
+        title: &str,
+        origin: SessionOrigin,
+        command: portable_pty::CommandBuilder,
+        deadline: Option<SessionDeadline>,
+        labels: Option<&HashMap<String, String>>,
+    ) {
+        let (rows, cols) = SessionView::pane_size(self.main_area());
+        let recording = self.start_recording(title, labels, cols, rows);
+        match PtySession::spawn(title, origin, command, rows, cols, deadline, recording) {
+            Ok(session) => {
+                self.session_view.add(session);
+                self.open_sessions();
+            },
+            Err(err) => {
+                tracing::event!(Level::ERROR, "failed to spawn session {}: {}", title, err);
+                self.toast.error(&format!("Failed to open session: {}", err));
+            },
+        }
+    }
+
+    // A recording that fails to start is reported, the session itself still goes ahead
+    fn start_recording(
+        &mut self,
+        name: &str,
+        labels: Option<&HashMap<String, String>>,
+        cols: u16,
+        rows: u16,
+    ) -> Option<Recording> {
+        if !self.config.recording.applies_to(labels) {
+            return None;
+        }
+        match Recording::create(name, cols, rows) {
+            Ok(recording) => Some(recording),
+            Err(err) => {
+                tracing::event!(Level::ERROR, "failed to start recording {}: {}", name, err);
+                self.toast.error(&format!("Failed to start recording: {}", err));
+                None
+            },
+        }
+    }
+
+    // Warns before limits run out, enforces them and reaps sessions whose process ended
+    fn poll_sessions(&mut self) {
+        for session in &mut self.session_view.sessions {
+            let Some(deadline) = &session.deadline else {
+                continue;
+            };
+            if deadline.is_expired() && !session.expired {
+                session.expired = true;
+                session.terminate();
+            } else if deadline.in_warning() && !session.warned {
+                session.warned = true;
+                self.toast.error(&format!(
+                    "Session {} ends in {}",
+                    session.title,
+                    format_duration(deadline.remaining()),
+                ));
+            }
+        }
+
+        for (session, exit_code) in self.session_view.take_exited() {
+            self.finish_session(session, exit_code);
+        }
+        if self.show_sessions && self.session_view.is_empty() {
+            self.exit_sessions();
+        }
+    }
+
+    fn finish_session(&mut self, session: PtySession, exit_code: u32) {
+        let exit_status = Some(exit_code as i32);
+        tracing::event!(Level::INFO, "session {} exited with status: {}", session.title, exit_code);
+
+        let mut hook_error = None;
+        if let SessionOrigin::Database { args, method, reason } = &session.origin {
+            let mut entry = AuditEntry::for_connection(AuditAction::Connect, args, method)
+                .with_duration(session.started.elapsed());
+            entry.exit_status = exit_status;
+            entry.reason = reason.clone();
+            entry.recording = session.recording_path();
+            self.audit(entry);
+
+            if session.expired {
+                let entry = AuditEntry::for_connection(AuditAction::SessionExpired, args, method)
+                    .with_duration(session.started.elapsed());
+                self.audit(entry);
+            }
+            // A failed hook is only reported, the session is torn down either way
+            if let Err(err) = self.run_connect_hooks(HookStage::Post, args, method, exit_status) {
+                tracing::event!(Level::ERROR, "post-connect hook failed for session {}: {}", session.title, err);
+                hook_error = Some(err);
+            }
+            self.teleport.read_status();
+        }
+
+        let ending = match (&session.deadline, session.expired) {
+            (Some(deadline), true) => Some(format!(
+                "Session {} terminated after reaching its {} limit",
+                session.title,
+                format_duration(deadline.max_duration),
+            )),
+            _ if exit_code != 0 => Some(format!("Session {} exited with status {}", session.title, exit_code)),
+            _ => None,
+        };
+        // A failed post-connect hook is reported along with however the session ended
+        match (ending, hook_error) {
+            (Some(ending), Some(err)) => self.toast.error(&format!("{}, post-connect {}", ending, err)),
+            (Some(ending), None) => self.toast.error(&ending),
+            (None, Some(err)) => self.toast.error(&format!("Post-connect {}", err)),
+            (None, None) => self.toast.info(&format!("Session {} closed", session.title)),
+        }
+    }
+
+    // Quitting ends every open session, they are still audited and run their post hooks
+    fn close_sessions(&mut self) {
+        let mut sessions: Vec<PtySession> = self.session_view.sessions.drain(..).collect();
+        for session in &mut sessions {
+            session.terminate();
+        }
+        for mut session in sessions {
+            let exit_code = session.wait_exit_code();
+            self.finish_session(session, exit_code);
+        }
+    }
+
+    // Warns once when a held connection's deadline comes close, returns whether it passed
+    fn check_held_deadline(&mut self, state: Option<DeadlineState>, label: &str) -> bool {
+        match state {
+            Some(DeadlineState::Expired) => true,
+            Some(DeadlineState::Ending(remaining)) => {
+                self.toast.error(&format!("{} ends in {}", label, format_duration(remaining)));
+                false
+            },
+            Some(DeadlineState::Open) | None => false,
+        }
+    }
+
+    // Consoles and monitors count as one connection in the audit log, recorded when they close
+    fn close_held_connection(&mut self, session: HeldConnection, method: &str, label: &str, expired: bool) {
+        let mut entry = AuditEntry::for_connection(AuditAction::Connect, &session.args, method)
+            .with_duration(session.started.elapsed());
+        entry.reason = session.reason.clone();
+        self.audit(entry);
+
+        if expired {
+            let entry = AuditEntry::for_connection(AuditAction::SessionExpired, &session.args, method)
+                .with_duration(session.started.elapsed());
+            self.audit(entry);
+            if let Some(deadline) = &session.deadline {
+                self.toast.error(&format!(
+                    "{} closed after reaching its {} limit",
+                    label,
+                    format_duration(deadline.max_duration),
+                ));
+            }
+        }
+        if let Err(err) = self.run_connect_hooks(HookStage::Post, &session.args, method, None) {
+            self.toast.error(&format!("Post-connect {}", err));
+        }
+    }
+
+    fn poll_query_console(&mut self) {
+        if !self.query_console.is_open() {
+            return;
+        }
+        self.query_console.poll();
+        match self.query_console.take_exported() {
+            Some(Ok(exported)) => {
+                self.toast.info(&format!("Exported {} rows to {}", exported.rows, exported.path.display()));
+            },
+            Some(Err(err)) => {
+                tracing::event!(Level::ERROR, "failed to export query results: {}", err);
+                self.toast.error(&format!("Failed to export results: {}", err));
+            },
+            None => {},
+        }
+
+        let state = self.query_console.session.as_mut().map(HeldConnection::check_deadline);
+        if self.check_held_deadline(state, "Query console") {
+            self.close_query_console(true);
+        }
+    }
+
+    // Closing drops the client and its tunnel
+    fn close_query_console(&mut self, expired: bool) {
+        let Some(session) = self.query_console.session.take() else {
+            return;
+        };
+        self.query_console.cancel();
+        self.close_held_connection(session, &ConnectMethod::Query.name(), "Query console", expired);
+
+        self.query_console.reset();
+        self.show_query = false;
+        if let InputMode::Querying | InputMode::ExportingResults | InputMode::PickingSnippet = self.input_mode {
+            self.result_export_dialog.reset();
+            self.snippet_dialog.reset();
+            self.input_mode = InputMode::Normal;
+        }
+    }
+
+    fn handle_query_key_event(&mut self, key_event: KeyEvent) {
+        let console = &mut self.query_console;
+        let alt = key_event.modifiers.contains(KeyModifiers::ALT);
+        let control = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match key_event.code {
+            KeyCode::Esc => self.close_query_console(false),
+            KeyCode::F(5) => console.run(),
+            KeyCode::Enter if alt => console.run(),
+            KeyCode::Char('c') if control => console.cancel(),
+            KeyCode::Tab => console.toggle_focus(),
+            KeyCode::F(2) => console.toggle_schema(),
+            KeyCode::F(3) => self.open_snippets(),
+            KeyCode::F(6) => self.open_result_export(),
+            code if console.focus == ConsoleFocus::Schema => match code {
+                KeyCode::Down => console.schema.select_next(),
+                KeyCode::Up => console.schema.select_previous(),
+                KeyCode::Enter => console.expand_schema(true),
+                KeyCode::Right => console.expand_schema(false),
+                KeyCode::Left => console.schema.collapse_selected(),
+                KeyCode::Char('i') => console.insert_schema_name(),
+                KeyCode::Char('r') => console.refresh_schema(),
+                KeyCode::Char('R') => console.reload_schema(),
+                _ => {},
+            },
+            code if console.focus == ConsoleFocus::Results => match code {
+                KeyCode::Down => console.results.select_next(),
+                KeyCode::Up => console.results.select_previous(),
+                KeyCode::PageDown => console.results.page_down(),
+                KeyCode::PageUp => console.results.page_up(),
+                KeyCode::Right => console.results.scroll_right(),
+                KeyCode::Left => console.results.scroll_left(),
+                _ => {},
+            },
+            KeyCode::Char(to_enter) => console.editor.enter_char(to_enter),
+            KeyCode::Enter => console.editor.new_line(),
+            KeyCode::Backspace => console.editor.delete_char(),
+            KeyCode::Delete => console.editor.delete_forward(),
+            KeyCode::Left => console.editor.move_cursor_left(),
+            KeyCode::Right => console.editor.move_cursor_right(),
+            KeyCode::Up => console.editor.move_cursor_up(),
+            KeyCode::Down => console.editor.move_cursor_down(),
+            KeyCode::Home => console.editor.move_cursor_home(),
+            KeyCode::End => console.editor.move_cursor_end(),
+            _ => {},
+        }
+    }
+
+    // Signals sent from the activity view are audited one by one
+    fn poll_activity(&mut self) {
+        if !self.activity_view.is_open() {
+            return;
+        }
+        for outcome in self.activity_view.poll() {
+            let Some(session) = &self.activity_view.session else {
+                break;
+            };
+            let (args, reason) = (session.args.clone(), session.reason.clone());
+            let action = match outcome.action {
+                BackendAction::Cancel => AuditAction::BackendCancel,
+                BackendAction::Terminate => AuditAction::BackendTerminate,
+            };
+            let method = format!("{}:{}", ConnectMethod::Activity.name(), outcome.pid);
+            let mut entry = AuditEntry::for_connection(action, &args, &method);
+            entry.exit_status = Some(if matches!(outcome.result, Ok(true)) { 0 } else { 1 });
+            entry.reason = reason;
+            self.audit(entry);
+
+            match outcome.result {
+                Ok(true) => self.toast.info(&format!("Sent {} to backend {}", outcome.action.to_string(), outcome.pid)),
+                Ok(false) => self.toast.error(&format!("Backend {} could not be signalled, it may have exited", outcome.pid)),
+                Err(err) => {
+                    tracing::event!(Level::ERROR, "failed to {} backend {}: {}", outcome.action.to_string(), outcome.pid, err);
+                    self.toast.error(&format!("Failed to {} backend {}: {}", outcome.action.to_string(), outcome.pid, err));
+                },
+            }
+        }
+
+        let state = self.activity_view.session.as_mut().map(HeldConnection::check_deadline);
+        if self.check_held_deadline(state, "Activity monitor") {
+            self.close_activity(true);
+        }
+    }
+
+    fn close_activity(&mut self, expired: bool) {
+        let Some(session) = self.activity_view.session.take() else {
+            return;
+        };
+        self.close_held_connection(session, &ConnectMethod::Activity.name(), "Activity monitor", expired);
+
+        self.activity_view.reset();
+        self.show_activity = false;
+        if let InputMode::Monitoring = self.input_mode {
+            self.input_mode = InputMode::Normal;
+        }
+    }
+
+    fn handle_activity_key_event(&mut self, key_event: KeyEvent) {
+        let view = &mut self.activity_view;
+        if view.pending.is_some() {
+            match key_event.code {
+                KeyCode::Char('y') => {
+                    let sent = view.confirm();
+                    if !sent {
+                        self.toast.error("The activity connection is closed");
+                    }
+                },
+                KeyCode::Char('n') | KeyCode::Esc => view.abort(),
+                _ => {},
+            }
+            return;
+        }
+        match key_event.code {
+            KeyCode::Esc => self.close_activity(false),
+            KeyCode::Down => view.select_next(),
+            KeyCode::Up => view.select_previous(),
+            KeyCode::PageDown => view.page_down(),
+            KeyCode::PageUp => view.page_up(),
+            KeyCode::Char('c') => view.request(BackendAction::Cancel),
+            KeyCode::Char('k') => view.request(BackendAction::Terminate),
+            KeyCode::Char('a') => view.toggle_idle(),
+            KeyCode::Char('p') => view.toggle_paused(),
+            KeyCode::Char('r') => view.refresh_now(),
+            _ => {},
+        }
+    }
+
+    fn handle_toggle_marked(&mut self) {
+        if self.current_tab == ResourceTab::Databases {
+            self.database_list.toggle_marked();
+        }
+    }
+
+    fn open_fanout(&mut self) {
+        if self.current_tab != ResourceTab::Databases || self.connections_blocked() {
+            return;
+        }
+        if self.database_list.marked.is_empty() {
+            self.toast.info("Mark databases with <space> to run a query on them");
+            return;
+        }
+
+        let instances = self.database_list.marked.clone();
+        let guarded: Vec<(String, Guardrail)> = instances
+            .iter()
+            .filter_map(|instance| self.find_database(instance))
+            .filter_map(|database| {
+                find_guardrail(&self.config.guardrails, database)
+                    .map(|guardrail| (database.metadata.name.clone(), guardrail.clone()))
+            })
+            .collect();
+        let require_reason = guarded.iter().any(|(_, guardrail)| guardrail.require_reason);
+
+        self.fanout_view.open(instances, guarded, require_reason);
+        self.show_fanout = true;
+        self.input_mode = InputMode::FanningOut;
+    }
+
+    // Dropping the run cancels the instances still queued or running,
+    // they are audited here since their outcomes never arrive
+    fn exit_fanout(&mut self) {
+        self.poll_fanout();
+        let reason = self.fanout_reason();
+        for (args, elapsed) in self.fanout_view.unfinished() {
+            let mut entry = AuditEntry::for_connection(AuditAction::Connect, &args, "fanout")
+                .with_duration(elapsed);
+            entry.exit_status = Some(1);
+            entry.reason = reason.clone();
+            self.audit(entry);
+        }
+        self.fanout_view.reset();
+        self.show_fanout = false;
+        self.input_mode = InputMode::Normal;
+    }
+
+    // Guarded instances need a reason when a rule asks for one and their names typed
+    fn run_fanout(&mut self) {
+        let view = &mut self.fanout_view;
+        if view.is_running() || view.editor.is_empty() {
+            return;
+        }
+        if view.require_reason && view.reason_input.value.trim().is_empty() {
+            view.focus = FanoutFocus::Reason;
+            self.toast.error("A reason is required for guarded databases");
+            return;
+        }
+        let guarded = view.guarded.clone();
+        if !view.confirmation.start(guarded) {
+            self.start_fanout();
+        }
+    }
+
+    fn start_fanout(&mut self) {
+        let view = &mut self.fanout_view;
+        let mut targets = vec![];
+        let mut failures = vec![];
+        for instance in view.instance_names() {
+            let target = match self.teleport.databases.iter().find(|database| database.metadata.name == instance) {
+                Some(database) => fanout::resolve_target(
+                    database,
+                    view.user_input.value.trim(),
+                    view.name_input.value.trim(),
+                    &self.config.defaults,
+                ),
+                None => Err("no longer listed".to_string()),
+            };
+            match target {
+                Ok(target) => targets.push(target),
+                Err(err) => failures.push((instance, err)),
+            }
+        }
+
+        tracing::event!(Level::INFO, "running a fan-out query on {} databases", targets.len());
+        let args = targets.iter().map(|target| target.args.clone()).collect();
+        let run = FanoutRun::start(targets, &view.editor.value(), &self.config.fanout, self.config.hooks.clone());
+        view.start(run, args, failures);
+    }
+
+    fn fanout_reason(&self) -> Option<String> {
+        Some(self.fanout_view.reason_input.value.trim().to_string()).filter(|reason| !reason.is_empty())
+    }
+
+    fn poll_fanout(&mut self) {
+        if !self.show_fanout {
+            return;
+        }
+        let reason = self.fanout_reason();
+        for outcome in self.fanout_view.poll() {
+            let mut entry = AuditEntry::for_connection(AuditAction::Connect, &outcome.args, "fanout")
+                .with_duration(outcome.elapsed);
+            entry.exit_status = Some(if outcome.result.is_ok() { 0 } else { 1 });
+            entry.reason = reason.clone();
+            self.audit(entry);
+        }
+    }
+
+    fn handle_fanout_key_event(&mut self, key_event: KeyEvent) {
+        let view = &mut self.fanout_view;
+        let alt = key_event.modifiers.contains(KeyModifiers::ALT);
+        let control = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        if view.confirmation.is_active() {
+            let confirmation = &mut view.confirmation;
+            match key_event.code {
+                KeyCode::Esc => confirmation.reset(),
+                KeyCode::Enter if confirmation.submit() => self.start_fanout(),
+                KeyCode::Char(to_enter) => confirmation.input.enter_char(to_enter),
+                KeyCode::Backspace => confirmation.input.delete_char(),
+                KeyCode::Left => confirmation.input.move_cursor_left(),
+                KeyCode::Right => confirmation.input.move_cursor_right(),
+                _ => {},
+            }
+            return;
+        }
+        match key_event.code {
+            KeyCode::Esc => self.exit_fanout(),
+            KeyCode::F(5) => self.run_fanout(),
+            KeyCode::Enter if alt => self.run_fanout(),
+            KeyCode::Char('c') if control => view.cancel(),
+            KeyCode::Tab => view.next_focus(),
+            code if view.focus == FanoutFocus::Instances => match code {
+                KeyCode::Down => view.select_next(),
+                KeyCode::Up => view.select_previous(),
+                _ => {},
+            },
+            code if view.focus == FanoutFocus::Results => match code {
+                KeyCode::Down => view.results.select_next(),
+                KeyCode::Up => view.results.select_previous(),
+                KeyCode::PageDown => view.results.page_down(),
+                KeyCode::PageUp => view.results.page_up(),
+                KeyCode::Right => view.results.scroll_right(),
+                KeyCode::Left => view.results.scroll_left(),
+                _ => {},
+            },
+            code if view.focus == FanoutFocus::Editor => match code {
+                KeyCode::Char(to_enter) => view.editor.enter_char(to_enter),
+                KeyCode::Enter => view.editor.new_line(),
+                KeyCode::Backspace => view.editor.delete_char(),
+                KeyCode::Delete => view.editor.delete_forward(),
+                KeyCode::Left => view.editor.move_cursor_left(),
+                KeyCode::Right => view.editor.move_cursor_right(),
+                KeyCode::Up => view.editor.move_cursor_up(),
+                KeyCode::Down => view.editor.move_cursor_down(),
+                KeyCode::Home => view.editor.move_cursor_home(),
+                KeyCode::End => view.editor.move_cursor_end(),
+                _ => {},
+            },
+            KeyCode::Enter => view.next_focus(),
+            code => {
+                let Some(input) = view.focused_input() else {
+                    return;
+                };
+                match code {
+                    KeyCode::Char(to_enter) => input.enter_char(to_enter),
+                    KeyCode::Backspace => input.delete_char(),
+                    KeyCode::Left => input.move_cursor_left(),
+                    KeyCode::Right => input.move_cursor_right(),
+                    _ => {},
+                }
+            },
+        }
+    }
+
+    // Two marked databases are compared, a single one is compared with the highlighted database
+    fn open_schema_diff(&mut self) {
+        if self.current_tab != ResourceTab::Databases || self.connections_blocked() {
+            return;
+        }
+        let highlighted = self.database_list.selected_item().map(|database| database.metadata.name.clone());
+        let (left, right) = match (self.database_list.marked.as_slice(), highlighted) {
+            ([left, right], _) => (left.clone(), right.clone()),
+            ([left], Some(highlighted)) => (left.clone(), highlighted),
+            _ => {
+                self.toast.info("Mark two databases with <space>, or one to compare with the highlighted database");
+                return;
+            },
+        };
+
+        let mut guarded: Vec<(String, Guardrail)> = vec![];
+        for database in [&left, &right].into_iter().filter_map(|instance| self.find_database(instance)) {
+            let Some(guardrail) = find_guardrail(&self.config.guardrails, database) else {
+                continue;
+            };
+            // A database compared with itself is only confirmed once
+            if !guarded.iter().any(|(instance, _)| *instance == database.metadata.name) {
+                guarded.push((database.metadata.name.clone(), guardrail.clone()));
+            }
+        }
+
+        self.schema_diff_view.open(&left, &right, guarded);
+        self.show_diff = true;
+        self.input_mode = InputMode::Diffing;
+    }
+
+    fn exit_schema_diff(&mut self) {
+        self.schema_diff_view.reset();
+        self.show_diff = false;
+        self.input_mode = InputMode::Normal;
+    }
+
+    // Guarded instances need a reason when a rule asks for one and their names typed
+    fn run_schema_diff(&mut self) {
+        let view = &mut self.schema_diff_view;
+        if view.is_running() {
+            return;
+        }
+        if view.require_reason && view.reason_input.value.trim().is_empty() {
+            view.focus = DiffFocus::Reason;
+            self.toast.error("A reason is required for guarded databases");
+            return;
+        }
+        let guarded = view.guarded.clone();
+        if !view.confirmation.start(guarded) {
+            self.start_schema_diff();
+        }
+    }
+
+    // Empty users and database names fall back to each instance's defaults, like fan-out queries
+    fn start_schema_diff(&mut self) {
+        let view = &mut self.schema_diff_view;
+        let sides = [
+            (&view.left_instance, &view.left_user, &view.left_name),
+            (&view.right_instance, &view.right_user, &view.right_name),
+        ];
+        let targets = sides.map(|(instance, user, name)| {
+            match self.teleport.databases.iter().find(|database| database.metadata.name == *instance) {
+                Some(database) => fanout::resolve_target(
+                    database,
+                    user.value.trim(),
+                    name.value.trim(),
+                    &self.config.defaults,
+                ),
+                None => Err("no longer listed".to_string()),
+            }
+        });
+        let labels = targets.each_ref().map(|target| {
+            target.as_ref().ok().map(|target| {
+                format!("{}@{}/{}", target.args.db_user, target.args.instance, target.args.db_name)
+            })
+        });
+
+        match targets {
+            [Ok(left), Ok(right)] if left.kind != right.kind => {
+                let failure = format!("cannot compare {} with {}", left.kind.to_string(), right.kind.to_string());
+                view.start(None, labels, [Some(failure.clone()), Some(failure)]);
+            },
+            [Ok(left), Ok(right)] => {
+                tracing::event!(Level::INFO, "comparing the schemas of {} and {}", left.args.instance, right.args.instance);
+                let run = SchemaDiffRun::start(left, right, self.config.hooks.clone());
+                view.start(Some(run), labels, [None, None]);
+            },
+            [left, right] => view.start(None, labels, [left.err(), right.err()]),
+        }
+    }
+
+    fn poll_schema_diff(&mut self) {
+        if !self.show_diff {
+            return;
+        }
+        let reason = Some(self.schema_diff_view.reason_input.value.trim().to_string()).filter(|reason| !reason.is_empty());
+        for read in self.schema_diff_view.poll() {
+            let mut entry = AuditEntry::for_connection(AuditAction::Connect, &read.args, "schema_diff")
+                .with_duration(read.elapsed);
+            entry.exit_status = Some(if read.succeeded { 0 } else { 1 });
+            entry.reason = reason.clone();
+            self.audit(entry);
+        }
+    }
+
+    fn handle_schema_diff_key_event(&mut self, key_event: KeyEvent) {
+        let view = &mut self.schema_diff_view;
+        if view.confirmation.is_active() {
+            let confirmation = &mut view.confirmation;
+            match key_event.code {
+                KeyCode::Esc => confirmation.reset(),
+                KeyCode::Enter if confirmation.submit() => self.start_schema_diff(),
+                KeyCode::Char(to_enter) => confirmation.input.enter_char(to_enter),
+                KeyCode::Backspace => confirmation.input.delete_char(),
+                KeyCode::Left => confirmation.input.move_cursor_left(),
+                KeyCode::Right => confirmation.input.move_cursor_right(),
+                _ => {},
+            }
+            return;
+        }
+        if view.exporting {
+            match key_event.code {
+                KeyCode::Esc => view.close_export(),
+                KeyCode::Enter => match view.write_report() {
+                    Ok(Some(path)) => self.toast.info(&format!("Wrote the schema diff to {}", path.display())),
+                    Ok(None) => {},
+                    Err(err) => {
+                        tracing::event!(Level::ERROR, "failed to write the schema diff: {}", err);
+                        self.toast.error(&format!("Failed to write the schema diff: {}", err));
+                    },
+                },
+                KeyCode::Char(to_enter) => {
+                    view.path_input.enter_char(to_enter);
+                    view.overwrite = None;
+                },
+                KeyCode::Backspace => {
+                    view.path_input.delete_char();
+                    view.overwrite = None;
+                },
+                KeyCode::Left => view.path_input.move_cursor_left(),
+                KeyCode::Right => view.path_input.move_cursor_right(),
+                _ => {},
+            }
+            return;
+        }
+        match key_event.code {
+            KeyCode::Esc => self.exit_schema_diff(),
+            KeyCode::F(5) => self.run_schema_diff(),
+            KeyCode::F(6) => {
+                if !view.open_export() {
+                    self.toast.info("Compare the schemas before exporting the diff");
+                }
+            },
+            KeyCode::Tab => view.next_focus(),
+            code if view.focus == DiffFocus::Diff => match code {
+                KeyCode::Down => view.select_next(),
+                KeyCode::Up => view.select_previous(),
+                KeyCode::PageDown => view.page_down(),
+                KeyCode::PageUp => view.page_up(),
+                KeyCode::Char('a') => view.toggle_only_differences(),
+                KeyCode::Char('y') => {
+                    if let Some(report) = view.report() {
+                        self.copy_to_clipboard("schema diff", &report);
+                    }
+                },
+                _ => {},
+            },
+            KeyCode::Enter => view.next_focus(),
+            code => {
+                let Some(input) = view.focused_input() else {
+                    return;
+                };
+                match code {
+                    KeyCode::Char(to_enter) => input.enter_char(to_enter),
+                    KeyCode::Backspace => input.delete_char(),
+                    KeyCode::Left => input.move_cursor_left(),
+                    KeyCode::Right => input.move_cursor_right(),
+                    _ => {},
+                }
+            },
+        }
+    }
+
+    // Shows the highlighted database's last connection test, tests are started from the connect dialog
+    fn open_health_check(&mut self) {
+        if self.current_tab != ResourceTab::Databases {
+            return;
+        }
+        let Some(database) = self.database_list.selected_item().cloned() else {
+            return;
+        };
+        if !self.health_check_view.is_running() {
+            let Some(result) = self.database_list.health.get(&database.metadata.name).cloned() else {
+                self.toast.info("No connection test yet, pick `Connection test` in the connect dialog (<c>)");
+                return;
+            };
+            self.health_check_view.show(result, database);
+        }
+        self.input_mode = InputMode::Testing;
+    }
+
+    fn start_health_check(&mut self, args: ConnectionArgs, database: Database, reason: Option<String>) {
+        if self.health_check_view.is_running() {
+            self.toast.info("A connection test is already running");
+            return;
+        }
+        tracing::event!(Level::INFO, "testing the connection to {} as {}", args.instance, args.db_user);
+        self.database_list.testing = Some(args.instance.clone());
+        let run = HealthCheckRun::start(args.clone(), database.clone(), self.config.hooks.clone());
+        self.health_check_view.start(run, args, database, reason);
+        self.input_mode = InputMode::Testing;
+    }
+
+    // Results are cached even when the popup was closed before the test finished
+    fn poll_health_check(&mut self) {
+        let Some(result) = self.health_check_view.poll() else {
+            return;
+        };
+        let mut entry = AuditEntry::for_connection(AuditAction::Connect, &result.args, &ConnectMethod::HealthCheck.name())
+            .with_duration(result.elapsed);
+        entry.exit_status = Some(if result.is_healthy() { 0 } else { 1 });
+        entry.reason = self.health_check_view.reason.clone();
+        self.audit(entry);
+
+        if !matches!(self.input_mode, InputMode::Testing) {
+            let message = format!("{}: {}", result.args.instance, result.summary());
+            if result.is_healthy() {
+                self.toast.info(&message);
+            } else {
+                self.toast.error(&message);
+            }
+        }
+        self.database_list.testing = None;
+        self.database_list.health.insert(result.args.instance.clone(), result);
+    }
+
+    fn handle_health_check_key_event(&mut self, key_event: KeyEvent) {
+        let view = &self.health_check_view;
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.input_mode = InputMode::Normal,
+            KeyCode::Char('y') => {
+                if let Some(report) = view.result.as_ref().map(|result| result.report()) {
+                    self.copy_to_clipboard("connection test report", &report);
+                }
+            },
+            KeyCode::Char('r') => {
+                let (Some(args), Some(database)) = (view.args.clone(), view.database.clone()) else {
+                    return;
+                };
+                if view.is_running() {
+                    return;
+                }
+                // Guarded databases go through the connect dialog's confirmation again
+                if !view.confirmed && find_guardrail(&self.config.guardrails, &database).is_some() {
+                    self.toast.info("Guarded databases are tested from the connect dialog (<c>)");
+                    return;
+                }
+                let reason = view.reason.clone();
+                self.start_health_check(args, database, reason);
+            },
+            _ => {},
+        }
+    }
+
+    // The library is read on every open so edits show up without a restart
+    fn open_snippets(&mut self) {
+        let Some(instance) = self.query_console.session.as_ref().map(|session| session.args.instance.clone()) else {
+            return;
+        };
+        let snippets = match snippet::load_snippets() {
+            Ok(snippets) => snippets,
+            Err(err) => {
+                tracing::event!(Level::ERROR, "failed to load snippets: {}", err);
+                self.toast.error(&format!("Failed to load snippets: {}", err));
+                return;
+            },
+        };
+        let (available, kind): (Vec<_>, _) = match self.find_database(&instance) {
+            Some(database) => (
+                snippet::snippets_for(&snippets, database).into_iter().cloned().collect(),
+                DatabaseKind::from_protocol(&database.spec.protocol),
+            ),
+            None => (vec![], None),
+        };
+        let Some(kind) = kind.filter(|_| !available.is_empty()) else {
+            self.toast.info(&format!("No snippets apply to {}", instance));
+            return;
+        };
+        self.snippet_dialog.open(available, kind);
+        self.input_mode = InputMode::PickingSnippet;
+    }
+
+    fn exit_snippets(&mut self) {
+        self.snippet_dialog.reset();
+        self.input_mode = if self.query_console.is_open() { InputMode::Querying } else { InputMode::Normal };
+    }
+
+    fn handle_snippet_next(&mut self) {
+        match self.snippet_dialog.next_step() {
+            Some(Ok(sql)) => {
+                self.query_console.editor.insert_text(&sql);
+                self.query_console.focus = ConsoleFocus::Editor;
+                self.exit_snippets();
+            },
+            Some(Err(err)) => {
+                self.toast.error(&format!("Failed to render snippet: {}", err));
+                self.exit_snippets();
+            },
+            None => {},
+        }
+    }
+
+    fn open_result_export(&mut self) {
+        let Some(instance) = self.query_console.session.as_ref().map(|session| session.args.instance.clone()) else {
+            return;
+        };
+        if self.query_console.results.result.is_none() {
+            self.toast.info("Run a query before exporting its result");
+            return;
+        }
+        self.result_export_dialog.open(&instance);
+        self.input_mode = InputMode::ExportingResults;
+    }
+
+    fn exit_result_export(&mut self) {
+        self.result_export_dialog.reset();
+        self.input_mode = InputMode::Querying;
+    }
+
+    // Files are streamed by the console's worker, the clipboard gets the rows already shown
+    fn handle_result_export_next(&mut self) {
+        if !self.result_export_dialog.next_step() {
+            return;
+        }
+
+        let format = self.result_export_dialog.selected_format();
+        match self.result_export_dialog.selected_destination() {
+            ResultDestination::Clipboard => {
+                if let Some(result) = &self.query_console.results.result {
+                    let label = if result.total_rows > result.rows.len() {
+                        format!("{} of {} rows", result.rows.len(), result.total_rows)
+                    } else {
+                        format!("{} rows", result.rows.len())
+                    };
+                    let text = result_export::render(format, result);
+                    self.copy_to_clipboard(&label, &text);
+                }
+            },
+            ResultDestination::File => {
+                let path = self.result_export_dialog.path_input.value.clone();
+                if self.result_export_dialog.overwrite.is_none() {
+                    if let Some(conflict) = result_export::existing_file(&path) {
+                        self.result_export_dialog.overwrite = Some(conflict);
+                        return;
+                    }
+                }
+                let overwrite = self.result_export_dialog.overwrite.is_some();
+                match self.query_console.export_to_file(format, &path, overwrite) {
+                    Ok(()) => self.toast.info(&format!("Exporting results to {}...", path)),
+                    Err(err) => self.toast.error(&err),
+                }
+            },
+        }
+        self.exit_result_export();
+    }
+
+    fn open_sessions(&mut self) {
+        if self.session_view.is_empty() {
+            self.toast.info("No open sessions");
+            return;
+        }
+        self.input_mode = InputMode::Session;
+        self.show_sessions = true;
+    }
+
+    fn exit_sessions(&mut self) {
+        self.input_mode = InputMode::Normal;
+        self.show_sessions = false;
+    }
+
+    fn handle_session_key_event(&mut self, key_event: KeyEvent) {
+        let control = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = key_event.modifiers.contains(KeyModifiers::SHIFT);
+        let main_area = self.main_area();
+        match key_event.code {
+            // Ctrl-] is reported as Ctrl-5 by most terminals
+            KeyCode::Char(']') | KeyCode::Char('5') if control => self.exit_sessions(),
+            KeyCode::F(7) => self.session_view.previous(),
+            KeyCode::F(8) => self.session_view.next(),
+            KeyCode::PageUp if shift => self.session_view.scroll(true, main_area),
+            KeyCode::PageDown if shift => self.session_view.scroll(false, main_area),
+            _ => self.session_view.send_key(key_event),
+        }
+    }
+
+    fn run_session(&mut self, terminal: &mut tui::Tui, session: PendingSession) -> io::Result<()> {
+        let started = Instant::now();
+        let deadline = session.limit.as_ref().map(SessionDeadline::start);
+        let labels = self.find_database(&session.args.instance).map(|database| database.metadata.labels.clone());
+        let recording = self.start_recording(
+            &session.args.instance,
+            labels.as_ref(),
+            self.last_area.width,
+            self.last_area.height,
+        );
+        let recording_path = recording.as_ref().map(|recording| recording.path.display().to_string());
+        let outcome = match &session.prepared {
+            Some(prepared) => self.run_template(terminal, &session.args, &session.method, prepared, deadline, recording)?,
+            None => self.run_subshell(terminal, &session.args, deadline, recording)?,
+        };
+        let exit_status = outcome.as_ref().and_then(|outcome| outcome.status.code());
+
+        let mut entry = AuditEntry::for_connection(AuditAction::Connect, &session.args, &session.method.name())
+            .with_duration(started.elapsed());
+        entry.exit_status = exit_status;
+        entry.reason = session.reason;
+        entry.recording = recording_path;
+        self.audit(entry);
+
+        if let (Some(outcome), Some(deadline)) = (&outcome, &deadline) {
+            if outcome.expired {
+                let entry = AuditEntry::for_connection(AuditAction::SessionExpired, &session.args, &session.method.name())
+                    .with_duration(started.elapsed());
+                self.audit(entry);
+                self.toast.error(&format!(
+                    "Session terminated after reaching its {} limit",
+                    format_duration(deadline.max_duration),
+                ));
+            }
+        }
+
+        if let Err(err) = self.run_connect_hooks(HookStage::Post, &session.args, &session.method.name(), exit_status) {
+            self.toast.error(&format!("Post-connect {}", err));
+        }
+        self.teleport.read_status();
+        Ok(())
+    }
+
+    // Suspends the TUI while the subshell owns the terminal
+    fn run_subshell(
+        &mut self,
+        terminal: &mut tui::Tui,
+        args: &ConnectionArgs,
+        deadline: Option<SessionDeadline>,
+        recording: Option<Recording>,
+    ) -> io::Result<Option<SessionOutcome>> {
+        let protocol = self.find_database(&args.instance)
+            .map(|database| database.spec.protocol.clone())
+            .unwrap_or_default();
+        let login = self.implicit_login_entry("subshell");
+        let Some(variables) = self.teleport.read_db_env(args, &protocol, &login) else {
+            self.toast.error("Failed to read the database environment");
+            return Ok(None);
+        };
+
+        tui::restore()?;
+        let outcome = subshell::spawn_subshell(&args.instance, &variables, deadline, recording);
+        tui::resume(terminal)?;
+
+        match outcome {
+            Ok(outcome) => {
+                tracing::event!(Level::INFO, "subshell exited with status: {}", outcome.status);
+                Ok(Some(outcome))
+            },
+            Err(err) => {
+                tracing::event!(Level::ERROR, "failed to spawn subshell: {}", err);
+                self.toast.error(&format!("Failed to spawn subshell: {}", err));
+                Ok(None)
+            },
+        }
+    }
+
+    // Same hand-off as the subshell, with a tunnel kept alive for the command's lifetime
+    fn run_template(
+        &mut self,
+        terminal: &mut tui::Tui,
+        args: &ConnectionArgs,
+        method: &ConnectMethod,
+        prepared: &PreparedCommand,
+        deadline: Option<SessionDeadline>,
+        recording: Option<Recording>,
+    ) -> io::Result<Option<SessionOutcome>> {
+        tui::restore()?;
+        let tunnel_started = Instant::now();
+        let tunnel = match prepared.tunnel_port {
+            Some(port) => match Tunnel::start(args, port) {
+                Ok(tunnel) => Some(tunnel),
+                Err(err) => {
+                    tui::resume(terminal)?;
+                    tracing::event!(Level::ERROR, "failed to start tunnel: {}", err);
+                    self.toast.error(&format!("Failed to start tunnel: {}", err));
+                    return Ok(None);
+                },
+            },
+            None => None,
+        };
+        let outcome = template::run_command(prepared, deadline, recording);
+        if let Some(tunnel) = tunnel {
+            drop(tunnel);
+            let entry = AuditEntry::for_connection(AuditAction::Tunnel, args, &method.name())
+                .with_duration(tunnel_started.elapsed());
+            self.audit(entry);
+        }
+        tui::resume(terminal)?;
+
+        match outcome {
+            Ok(outcome) if outcome.status.success() || outcome.expired => {
+                tracing::event!(Level::INFO, "command template exited with status: {}", outcome.status);
+                Ok(Some(outcome))
+            },
+            Ok(outcome) => {
+                tracing::event!(Level::ERROR, "command template exited with status: {}", outcome.status);
+                self.toast.error(&format!("Command exited with {}", outcome.status));
+                Ok(Some(outcome))
+            },
+            Err(err) => {
+                tracing::event!(Level::ERROR, "failed to run command template: {}", err);
+                self.toast.error(&format!("Failed to run command: {}", err));
+                Ok(None)
+            },
+        }
+    }
+
+    // Stamps the entry with the Teleport identity from the last status read
+    fn audit(&self, entry: AuditEntry) {
+        audit::record(&self.with_identity(entry));
+    }
+
+    fn with_identity(&self, mut entry: AuditEntry) -> AuditEntry {
+        entry.teleport_user = self.teleport.username.clone();
+        entry.cluster = self.cluster();
+        entry
+    }
+
+    // The cluster `tsh status` reports, KITH_TSH_CLUSTER only covers a missing profile
+    fn cluster(&self) -> String {
+        if self.teleport.cluster.is_empty() {
+            self.config.tsh_cluster.clone()
+        } else {
+            self.teleport.cluster.clone()
+        }
+    }
+
+    // Recorded for the `tsh db login` a method runs on its own to get certificates
+    fn implicit_login_entry(&self, method: &str) -> AuditEntry {
+        let mut entry = AuditEntry::new(AuditAction::DbLogin, "");
+        entry.method = method.to_string();
+        self.with_identity(entry)
+    }
+
+    fn run_connect_hooks(
+        &self,
+        stage: HookStage,
+        args: &ConnectionArgs,
+        method: &str,
+        exit_status: Option<i32>,
+    ) -> Result<(), String> {
+        let context = HookContext {
+            args,
+            database: self.find_database(&args.instance),
+            method,
+            exit_status,
+        };
+        hooks::run_hooks(&self.config.hooks, stage, &context)
+    }
+
+    // A config that failed to load has no guardrails, hooks or session limits to apply,
+    // so no database connection starts until it is fixed
+    fn connections_blocked(&mut self) -> bool {
+        let Some(err) = &self.config.load_error else {
+            return false;
+        };
+        self.toast.error(&format!("{}, fix it to connect to databases", err));
+        true
+    }
+
+    fn find_database(&self, instance: &str) -> Option<&Database> {
+        self.teleport.databases
+            .iter()
+            .find(|database| database.metadata.name == instance)
+    }
+
+    fn render_frame(&self, frame: &mut Frame){
+        let frame_size = frame.size();
+        frame.render_widget(self, frame_size);
+
+        self.enable_cursor(frame, frame_size);
+    }
+
+    fn handle_events(&mut self) -> io::Result<()>{
+        let tick_rate = if self.show_sessions || self.replay_view.is_playing() {
+            SESSION_TICK_RATE
+        } else {
+            TICK_RATE
+        };
+        if !event::poll(tick_rate)? {
+            return Ok(());
+        }
+        match event::read()? {
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                self.handle_key_event(key_event)
+            }
+            _ => {}
+        };
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) {
+        match self.input_mode {
+            InputMode::Normal => match key_event.code {
+                KeyCode::Char('q') => self.exit(),
+                KeyCode::Char('l') => self.handle_login(),
+                KeyCode::Char('s') => self.toggle_search(),
+                KeyCode::Char('c') => self.handle_primary_action(),
+                KeyCode::Char('i') => self.handle_db_login(),
+                KeyCode::Char('o') => self.handle_db_logout(),
+                KeyCode::Char('r') => self.open_requests(),
+                KeyCode::Char('v') => self.open_reviews(),
+                KeyCode::Char('y') => self.handle_copy(),
+                KeyCode::Char('Y') => self.handle_copy_connect_command(),
+                KeyCode::Char('e') => self.open_export(),
+                KeyCode::Char('E') => self.open_ide_export(),
+                KeyCode::Char('a') => self.open_audit(),
+                KeyCode::Char('t') => self.open_sessions(),
+                KeyCode::Char('p') => self.open_replays(),
+                KeyCode::Char(' ') => self.handle_toggle_marked(),
+                KeyCode::Char('f') => self.open_fanout(),
+                KeyCode::Char('d') => self.open_schema_diff(),
+                KeyCode::Char('h') => self.open_health_check(),
+                KeyCode::Tab => self.toggle_details_focus(),
+                KeyCode::Right => self.switch_tab(self.current_tab.next()),
+                KeyCode::Left => self.switch_tab(self.current_tab.previous()),
+                KeyCode::Down => self.handle_resource_list_next(),
+                KeyCode::Up => self.handle_resource_list_previous(),
+                _ => {},
+            },
+            InputMode::Searching => match key_event.code {
+                KeyCode::Esc => self.exit_search(),
+                KeyCode::Enter => self.handle_search(),
+                KeyCode::Char(to_enter) => self.search_dialog.enter_char(to_enter),
+                KeyCode::Backspace => self.search_dialog.delete_char(),
+                KeyCode::Left => self.search_dialog.move_cursor_left(),
+                KeyCode::Right => self.search_dialog.move_cursor_right(),
+                _ => {},
+            },
+            InputMode::Connecting => match key_event.code {
+                KeyCode::Esc => self.exit_connect(),
+                KeyCode::Enter => self.handle_connect(),
+                KeyCode::Down => self.handle_connect_down(),
+                KeyCode::Up => self.handle_connect_up(),
+                KeyCode::Char(to_enter) => self.handle_connect_char_input(to_enter),
+                KeyCode::Backspace => self.handle_connect_backspace(),
+                KeyCode::Left => self.handle_connect_left(),
+                KeyCode::Right => self.handle_connect_right(),
+                _ => {},
+            },
+            InputMode::Requesting => self.handle_request_key_event(key_event),
+            InputMode::Reviewing => self.handle_review_key_event(key_event),
+            InputMode::SelectingLogin => match key_event.code {
+                KeyCode::Esc => self.exit_ssh(),
+                KeyCode::Enter => self.handle_ssh(),
+                KeyCode::Down => self.ssh_dialog.login_list.state.select_next(),
+                KeyCode::Up => self.ssh_dialog.login_list.state.select_previous(),
+                _ => {},
+            },
+            InputMode::Exporting => self.handle_export_key_event(key_event),
+            InputMode::Auditing => match key_event.code {
+                KeyCode::Esc => self.exit_audit(),
+                KeyCode::Down => self.audit_view.select_next(),
+                KeyCode::Up => self.audit_view.select_previous(),
+                KeyCode::Char(to_enter) => self.audit_view.enter_char(to_enter),
+                KeyCode::Backspace => self.audit_view.delete_char(),
+                KeyCode::Left => self.audit_view.filter_input.move_cursor_left(),
+                KeyCode::Right => self.audit_view.filter_input.move_cursor_right(),
+                _ => {},
+            },
+            InputMode::Session => self.handle_session_key_event(key_event),
+            InputMode::Replaying => self.handle_replay_key_event(key_event),
+            InputMode::Querying => self.handle_query_key_event(key_event),
+            InputMode::FanningOut => self.handle_fanout_key_event(key_event),
+            InputMode::Monitoring => self.handle_activity_key_event(key_event),
+            InputMode::Diffing => self.handle_schema_diff_key_event(key_event),
+            InputMode::Testing => self.handle_health_check_key_event(key_event),
+            InputMode::PickingSnippet => match key_event.code {
+                KeyCode::Esc => self.exit_snippets(),
+                KeyCode::Enter => self.handle_snippet_next(),
+                KeyCode::Down => self.snippet_dialog.select_next(),
+                KeyCode::Up => self.snippet_dialog.select_previous(),
+                KeyCode::Char(to_enter) => self.snippet_dialog.input.enter_char(to_enter),
+                KeyCode::Backspace => self.snippet_dialog.input.delete_char(),
+                KeyCode::Left => self.snippet_dialog.input.move_cursor_left(),
+                KeyCode::Right => self.snippet_dialog.input.move_cursor_right(),
+                _ => {},
+            },
+            InputMode::ExportingResults => match key_event.code {
+                KeyCode::Esc => self.exit_result_export(),
+                KeyCode::Enter => self.handle_result_export_next(),
+                KeyCode::Down => self.result_export_dialog.select_next(),
+                KeyCode::Up => self.result_export_dialog.select_previous(),
+                KeyCode::Char(to_enter) => {
+                    self.result_export_dialog.path_input.enter_char(to_enter);
+                    self.result_export_dialog.overwrite = None;
+                },
+                KeyCode::Backspace => {
+                    self.result_export_dialog.path_input.delete_char();
+                    self.result_export_dialog.overwrite = None;
+                },
+                KeyCode::Left => self.result_export_dialog.path_input.move_cursor_left(),
+                KeyCode::Right => self.result_export_dialog.path_input.move_cursor_right(),
+                _ => {},
+            },
+            InputMode::ExportingIde => match key_event.code {
+                KeyCode::Esc => self.exit_ide_export(),
+                KeyCode::Enter => self.handle_ide_export_next(),
+                KeyCode::Down => self.ide_export_dialog.select_next(),
+                KeyCode::Up => self.ide_export_dialog.select_previous(),
+                KeyCode::Char(to_enter) => self.ide_export_dialog.path_input.enter_char(to_enter),
+                KeyCode::Backspace => self.ide_export_dialog.path_input.delete_char(),
+                KeyCode::Left => self.ide_export_dialog.path_input.move_cursor_left(),
+                KeyCode::Right => self.ide_export_dialog.path_input.move_cursor_right(),
+                _ => {},
+            },
+        } 
+    }
+
+    fn handle_replay_key_event(&mut self, key_event: KeyEvent) {
+        let view = &mut self.replay_view;
+        if view.is_open() {
+            match key_event.code {
+                KeyCode::Esc => view.close_player(),
+                KeyCode::Char(' ') => view.toggle_pause(),
+                KeyCode::Left => view.seek(-5.0),
+                KeyCode::Right => view.seek(5.0),
+                KeyCode::Char('+') | KeyCode::Char('=') => view.change_speed(true),
+                KeyCode::Char('-') => view.change_speed(false),
+                KeyCode::Home => view.restart(),
+                _ => {},
+            }
+            return;
+        }
+
+        match key_event.code {
+            KeyCode::Esc => self.exit_replays(),
+            KeyCode::Enter => {
+                if let Err(err) = view.open_selected() {
+                    tracing::event!(Level::ERROR, "failed to open recording: {}", err);
+                    self.toast.error(&err);
+                }
+            },
+            KeyCode::Down => view.select_next(),
+            KeyCode::Up => view.select_previous(),
+            KeyCode::Char('r') => view.reload(),
+            _ => {},
+        }
+    }
+
+    fn handle_export_key_event(&mut self, key_event: KeyEvent) {
+        let dialog = &mut self.export_dialog;
+        match key_event.code {
+            KeyCode::Esc => self.exit_export(),
+            KeyCode::Enter => self.handle_export_next(),
+            KeyCode::Down => dialog.select_next(),
+            KeyCode::Up => dialog.select_previous(),
+            KeyCode::Char(to_enter) => {
+                dialog.overwrite = None;
+                if let Some(input) = dialog.current_input() {
+                    input.enter_char(to_enter);
+                }
+            },
+            KeyCode::Backspace => {
+                dialog.overwrite = None;
+                if let Some(input) = dialog.current_input() {
+                    input.delete_char();
+                }
+            },
+            KeyCode::Left => {
+                if let Some(input) = dialog.current_input() {
+                    input.move_cursor_left();
+                }
+            },
+            KeyCode::Right => {
+                if let Some(input) = dialog.current_input() {
+                    input.move_cursor_right();
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn handle_review_key_event(&mut self, key_event: KeyEvent) {
+        let view = &mut self.review_view;
+        if view.decision.is_some() {
+            match key_event.code {
+                KeyCode::Esc => view.cancel_review(),
+                KeyCode::Enter => self.handle_submit_review(),
+                KeyCode::Char(to_enter) => view.comment_input.enter_char(to_enter),
+                KeyCode::Backspace => view.comment_input.delete_char(),
+                KeyCode::Left => view.comment_input.move_cursor_left(),
+                KeyCode::Right => view.comment_input.move_cursor_right(),
+                _ => {},
+            }
+            return;
+        }
+
+        match key_event.code {
+            KeyCode::Esc => self.exit_reviews(),
+            KeyCode::Down => view.state.select_next(),
+            KeyCode::Up => view.state.select_previous(),
+            KeyCode::Char('a') => view.start_review(ReviewDecision::Approve),
+            KeyCode::Char('d') => view.start_review(ReviewDecision::Deny),
+            KeyCode::Char('r') => view.refresh_now(),
+            _ => {},
+        }
+    }
+
+    fn handle_request_key_event(&mut self, key_event: KeyEvent) {
+        let view = &mut self.access_request_view;
+        match view.mode {
+            RequestMode::Browsing => match key_event.code {
+                KeyCode::Esc => self.exit_requests(),
+                KeyCode::Tab => view.toggle_focus(),
+                KeyCode::Down => view.select_next(),
+                KeyCode::Up => view.select_previous(),
+                KeyCode::Char('s') => view.start_search(),
+                KeyCode::Char('n') | KeyCode::Enter if view.focus == RequestFocus::Requestable => {
+                    view.start_compose()
+                },
+                KeyCode::Char('a') => self.handle_assume_request(),
+                KeyCode::Char('r') => view.refresh_now(),
+                _ => {},
+            },
+            RequestMode::Searching => match key_event.code {
+                KeyCode::Esc => view.exit_mode(),
+                KeyCode::Enter => {
+                    self.handle_login();
+                    self.access_request_view.search();
+                },
+                KeyCode::Char(to_enter) => view.search_input.enter_char(to_enter),
+                KeyCode::Backspace => view.search_input.delete_char(),
+                KeyCode::Left => view.search_input.move_cursor_left(),
+                KeyCode::Right => view.search_input.move_cursor_right(),
+                _ => {},
+            },
+            RequestMode::Composing => {
+                let dialog = &mut view.request_dialog;
+                match key_event.code {
+                    KeyCode::Esc => view.exit_mode(),
+                    KeyCode::Enter => self.handle_submit_request(),
+                    KeyCode::Up | KeyCode::Down => {
+                        if let RequestStep::Confirmation = dialog.current_step {
+                            dialog.confirmation_toggle.toggle();
+                        }
+                    },
+                    KeyCode::Char(to_enter) => {
+                        if let Some(input) = dialog.current_input() {
+                            input.enter_char(to_enter);
+                        }
+                    },
+                    KeyCode::Backspace => {
+                        if let Some(input) = dialog.current_input() {
+                            input.delete_char();
+                        }
+                    },
+                    KeyCode::Left => {
+                        if let Some(input) = dialog.current_input() {
+                            input.move_cursor_left();
+                        }
+                    },
+                    KeyCode::Right => {
+                        if let Some(input) = dialog.current_input() {
+                            input.move_cursor_right();
+                        }
+                    },
+                    _ => {},
+                }
+            },
+        }
+    }
+
+    fn handle_login(&mut self) {
+        self.teleport.login(&self.config.tsh_proxy, &self.config.tsh_cluster);
+        self.logged_in = true;
+    }
+
+    fn toggle_search(&mut self) {
+        self.input_mode = InputMode::Searching;
+        self.show_search = !self.show_search;
+    }
+
+    fn handle_search(&mut self) {
+        self.handle_login();
+        let search = self.search_dialog.search.clone();
+        match self.current_tab {
+            ResourceTab::Databases => {
+                if let Err(err) = self.teleport.read_databases(&search) {
+                    self.toast.error(&format!("Failed to list databases, {}", err));
+                }
+                self.last_search = search;
+            },
+            ResourceTab::Applications => self.teleport.read_apps(&search),
+            ResourceTab::Nodes => self.teleport.read_nodes(&search),
+            ResourceTab::Kubernetes => self.teleport.read_kube_clusters(&search),
+        }
+        self.teleport.read_status();
+        self.exit_search();
+    }
+
+    fn handle_primary_action(&mut self) {
+        match self.current_tab {
+            ResourceTab::Databases => self.toggle_connect(),
+            ResourceTab::Applications => self.handle_app_login(),
+            ResourceTab::Nodes => self.open_ssh(),
+            ResourceTab::Kubernetes => self.handle_kube_login(),
+        }
+    }
+
+    fn handle_app_login(&mut self) {
+        let Some(app) = self.app_list.selected_item() else {
+            return;
+        };
+        let (name, url) = (app.name().to_string(), app.url());
+
+        let mut entry = AuditEntry::new(AuditAction::AppLogin, &name);
+        entry.exit_status = self.teleport.app_login(&name);
+        let exit_status = entry.exit_status;
+        self.audit(entry);
+        match tsh_failure(exit_status) {
+            Some(failure) => self.toast.error(&format!("Failed to log into {}, {}", name, failure)),
+            None => self.toast.info(&format!("Logged into {}, open {}", name, url)),
+        }
+    }
+
+    fn handle_kube_login(&mut self) {
+        let Some(kube_cluster) = self.kube_list.selected_item() else {
+            return;
+        };
+        let name = kube_cluster.name().to_string();
+
+        let mut entry = AuditEntry::new(AuditAction::KubeLogin, &name);
+        entry.exit_status = self.teleport.kube_login(&name);
+        let exit_status = entry.exit_status;
+        self.audit(entry);
+        match tsh_failure(exit_status) {
+            Some(failure) => self.toast.error(&format!("Failed to log into {}, {}", name, failure)),
+            None => self.toast.info(&format!("Switched the kubeconfig context to {}", name)),
+        }
+    }
+
+    fn open_ssh(&mut self) {
+        if let Some(node) = self.node_list.selected_item() {
+            self.ssh_dialog.open(node.clone(), self.teleport.logins.clone());
+            self.input_mode = InputMode::SelectingLogin;
+        }
+    }
+
+    fn handle_ssh(&mut self) {
+        if let Some(args) = self.ssh_dialog.to_ssh_args() {
+            let title = format!("{}@{}", args.login, args.host);
+            let command = pty_session::ssh_command(&args);
+            self.exit_ssh();
+            self.open_session(&title, SessionOrigin::Ssh(args), command, None, None);
+        }
+    }
 
-impl App {
-    pub fn new(config: Config) -> App {
-        App {
-            teleport: Tsh::new(),
-            config,
-            database_list: StatefulDatabaseList::new(),
-            search_dialog: SearchDialog::new(),
-            connect_dialog: ConnectDialog::new(),
-            input_mode: InputMode::Normal,
-            logged_in: false,
-            exit: false,
-            show_search: false,
-            show_connect: false,
-            initiate_connection: false,
+    fn exit_ssh(&mut self) {
+        self.ssh_dialog.reset();
+        self.input_mode = InputMode::Normal;
+    }
+
+    fn open_export(&mut self) {
+        if self.current_tab != ResourceTab::Databases {
+            return;
+        }
+        if let Some(database) = self.database_list.selected_item() {
+            self.export_dialog.open(database.clone());
+            self.input_mode = InputMode::Exporting;
         }
     }
 
-    pub fn run(&mut self, terminal: &mut tui::Tui) -> io::Result<()>{
-        while !self.exit {
-            // Render new state
-            terminal.draw(|frame| self.render_frame(frame))?;
+    fn exit_export(&mut self) {
+        self.export_dialog.reset();
+        self.input_mode = InputMode::Normal;
+    }
 
-            // Take input
-            self.handle_events()?;
+    fn handle_export_next(&mut self) {
+        let dialog = &mut self.export_dialog;
+        match dialog.current_step {
+            ExportStep::UserSelection => {
+                if dialog.selected_user().is_some() {
+                    dialog.current_step = ExportStep::DatabaseInput;
+                }
+            },
+            ExportStep::DatabaseInput => {
+                if dialog.database_name_input.value.is_empty() {
+                    return;
+                }
+                let Some(args) = dialog.to_connection_args() else {
+                    return;
+                };
+                let login = self.implicit_login_entry("config_export");
+                match self.teleport.read_db_config(&args, &login) {
+                    Some(db_config) => {
+                        self.export_dialog.navigate_to_format(db_config);
+                        self.teleport.read_status();
+                    },
+                    None => self.toast.error("Failed to read tsh db config"),
+                }
+            },
+            ExportStep::FormatSelection => dialog.navigate_to_path(),
+            ExportStep::PathInput => {
+                let Some(content) = dialog.rendered_config() else {
+                    return;
+                };
+                let format = dialog.selected_format();
+                if dialog.overwrite.is_none() {
+                    if let Some(conflict) = format.conflict(&dialog.path_input.value, &content) {
+                        dialog.overwrite = Some(conflict);
+                        return;
+                    }
+                }
+                match format.write(&dialog.path_input.value, &content, dialog.overwrite.is_some()) {
+                    Ok(path) => {
+                        self.toast.info(&format!("Exported {} to {}", format.to_string(), path.display()));
+                        self.exit_export();
+                    },
+                    Err(err) => {
+                        tracing::event!(Level::ERROR, "failed to export connection config: {}", err);
+                        self.toast.error(&format!("Failed to export: {}", err));
+                    },
+                }
+            },
+        }
+    }
 
-            // Update state
-            self.set_database_list_state();
-            self.set_selected_database_state();
-            self.set_user_list_state();
-            self.connect_dialog.set_database_name_state();
+    fn open_ide_export(&mut self) {
+        if self.current_tab != ResourceTab::Databases || self.teleport.databases.is_empty() {
+            return;
         }
-        Ok(())
+        if self.ide_export_task.is_some() {
+            self.toast.info("An IDE export is already running");
+            return;
+        }
+        self.ide_export_dialog.open();
+        self.input_mode = InputMode::ExportingIde;
     }
 
-    pub fn connect_to_database(&self) {
-        let args = self.connect_dialog.to_connection_args();
-        self.teleport.connect(args);
+    fn exit_ide_export(&mut self) {
+        self.ide_export_dialog.reset();
+        self.input_mode = InputMode::Normal;
     }
 
-    fn render_frame(&self, frame: &mut Frame){
-        let frame_size = frame.size();
-        frame.render_widget(self, frame_size);
+    // Resolving every database can take a while, so it runs in the background
+    fn handle_ide_export_next(&mut self) {
+        if !self.ide_export_dialog.next_step() {
+            return;
+        }
 
-        self.enable_cursor(frame, frame_size);
+        let target = self.ide_export_dialog.selected_target();
+        let path = self.ide_export_dialog.path_input.value.clone();
+        let databases = self.teleport.databases.clone();
+        let active = self.teleport.active_databases.clone();
+        let login = self.implicit_login_entry("ide_export");
+
+        self.ide_export_task = Some(Task::spawn(move || {
+            let (sources, skipped) = Tsh::build_data_sources(&databases, &active, &login);
+            ide_export::export(target, &path, &sources, skipped)
+        }));
+        self.toast.info(&format!("Exporting data sources for {} databases...", self.teleport.databases.len()));
+        self.exit_ide_export();
     }
 
-    fn handle_events(&mut self) -> io::Result<()>{
-        match event::read()? {
-            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
-                self.handle_key_event(key_event)
-            }
-            _ => {}
+    fn poll_ide_export(&mut self) {
+        let Some(result) = self.ide_export_task.as_ref().and_then(|task| task.try_take()) else {
+            return;
         };
-        Ok(())
-    }
+        self.ide_export_task = None;
 
-    fn handle_key_event(&mut self, key_event: KeyEvent) {
-        match self.input_mode {
-            InputMode::Normal => match key_event.code {
-                KeyCode::Char('q') => self.exit(),
-                KeyCode::Char('l') => self.handle_login(),
-                KeyCode::Char('s') => self.toggle_search(),
-                KeyCode::Char('c') => self.toggle_connect(),
-                KeyCode::Down => self.handle_database_list_next(),
-                KeyCode::Up => self.handle_database_list_previous(),
-                _ => {},
+        match result {
+            Ok(Ok(summary)) => {
+                let mut message = format!("Exported {} data sources to {}", summary.exported, summary.path.display());
+                if summary.skipped > 0 {
+                    message.push_str(&format!(" ({} skipped)", summary.skipped));
+                }
+                self.toast.info(&message);
+                self.teleport.read_status();
             },
-            InputMode::Searching => match key_event.code {
-                KeyCode::Esc => self.exit_search(),
-                KeyCode::Enter => self.handle_search(),
-                KeyCode::Char(to_enter) => self.search_dialog.enter_char(to_enter),
-                KeyCode::Backspace => self.search_dialog.delete_char(),
-                KeyCode::Left => self.search_dialog.move_cursor_left(),
-                KeyCode::Right => self.search_dialog.move_cursor_right(),
-                _ => {},
+            Ok(Err(err)) => {
+                tracing::event!(Level::ERROR, "failed to export data sources: {}", err);
+                self.toast.error(&format!("Failed to export data sources: {}", err));
             },
-            InputMode::Connecting => match key_event.code {
-                KeyCode::Esc => self.exit_connect(),
-                KeyCode::Enter => self.handle_connect(),
-                KeyCode::Down => self.handle_connect_down(),
-                KeyCode::Up => self.handle_connect_up(),
-                KeyCode::Char(to_enter) => self.handle_connect_char_input(to_enter),
-                KeyCode::Backspace => self.handle_connect_backspace(),
-                KeyCode::Left => self.handle_connect_left(),
-                KeyCode::Right => self.handle_connect_right(),
-                _ => {},
+            Err(()) => self.toast.error("Data source export crashed"),
+        }
+    }
+
+    fn handle_db_login(&mut self) {
+        if self.current_tab != ResourceTab::Databases {
+            return;
+        }
+        if let Some(database) = self.database_list.selected_item() {
+            let instance = database.metadata.name.clone();
+            let mut entry = AuditEntry::new(AuditAction::DbLogin, &instance);
+            entry.exit_status = self.teleport.db_login(&instance);
+            self.audit(entry);
+            self.teleport.read_status();
+        }
+    }
+
+    fn handle_db_logout(&mut self) {
+        if self.current_tab != ResourceTab::Databases {
+            return;
+        }
+        if let Some(database) = self.database_list.selected_item() {
+            let instance = database.metadata.name.clone();
+            let mut entry = AuditEntry::new(AuditAction::DbLogout, &instance);
+            entry.exit_status = self.teleport.db_logout(&instance);
+            self.audit(entry);
+            self.teleport.read_status();
+        }
+    }
+
+    fn open_requests(&mut self) {
+        self.input_mode = InputMode::Requesting;
+        self.show_requests = true;
+        self.access_request_view.refresh_now();
+    }
+
+    fn exit_requests(&mut self) {
+        self.access_request_view.exit_mode();
+        self.input_mode = InputMode::Normal;
+        self.show_requests = false;
+    }
+
+    fn handle_submit_request(&mut self) {
+        let cluster = self.cluster();
+        let dialog = &mut self.access_request_view.request_dialog;
+        dialog.next_step();
+        match dialog.ready_to_submit {
+            Some(true) => {
+                let args = dialog.to_access_request_args(&cluster);
+                let mut entry = AuditEntry::new(AuditAction::AccessRequestCreate, &args.resource_ids.join(","));
+                entry.method = format!("roles={}", args.roles.join(","));
+                entry.reason = Some(args.reason.clone()).filter(|reason| !reason.is_empty());
+                entry.exit_status = self.teleport.create_access_request(args);
+                let exit_status = entry.exit_status;
+                self.audit(entry);
+                match tsh_failure(exit_status) {
+                    Some(failure) => self.toast.error(&format!("Failed to create the access request, {}", failure)),
+                    None => self.toast.info("Access request created"),
+                }
+                self.access_request_view.exit_mode();
+                self.access_request_view.focus = RequestFocus::Requests;
+                self.access_request_view.refresh_now();
             },
-        } 
+            Some(false) => self.access_request_view.exit_mode(),
+            None => {},
+        }
     }
 
-    fn handle_login(&mut self) {
-        self.teleport.login(&self.config.tsh_proxy, &self.config.tsh_cluster);
+    // Logs back in with the approved request so its resources join the inventory
+    fn handle_assume_request(&mut self) {
+        let request_id = match self.access_request_view.selected_request() {
+            Some(request) if request.spec.state == RequestState::Approved => request.id().to_string(),
+            _ => return,
+        };
+
+        let exit_status = self.teleport.assume_access_request(
+            &self.config.tsh_proxy,
+            &self.cluster(),
+            &request_id,
+        );
+        let mut entry = AuditEntry::new(AuditAction::AccessRequestAssume, &request_id);
+        entry.exit_status = exit_status;
+        self.audit(entry);
+
+        if let Some(failure) = tsh_failure(exit_status) {
+            self.toast.error(&format!("Failed to assume the access request, {}", failure));
+            return;
+        }
         self.logged_in = true;
+        let listed = self.teleport.read_databases(&self.last_search);
+        self.teleport.read_status();
+        self.exit_requests();
+        match listed {
+            Ok(()) => self.toast.info("Access request assumed"),
+            Err(err) => self.toast.error(&format!("Access request assumed, but listing databases failed: {}", err)),
+        }
     }
 
-    fn toggle_search(&mut self) {
-        self.input_mode = InputMode::Searching;
-        self.show_search = !self.show_search;
+    fn open_audit(&mut self) {
+        self.input_mode = InputMode::Auditing;
+        self.show_audit = true;
+        self.audit_view.reload();
     }
 
-    fn handle_search(&mut self) {
-        self.handle_login();
-        self.teleport.read_databases(&self.search_dialog.search);
-        self.exit_search();
+    fn exit_audit(&mut self) {
+        self.audit_view.reset();
+        self.input_mode = InputMode::Normal;
+        self.show_audit = false;
+    }
+
+    fn open_replays(&mut self) {
+        self.input_mode = InputMode::Replaying;
+        self.show_replays = true;
+        self.replay_view.reload();
+    }
+
+    fn exit_replays(&mut self) {
+        self.replay_view.reset();
+        self.input_mode = InputMode::Normal;
+        self.show_replays = false;
+    }
+
+    fn open_reviews(&mut self) {
+        self.input_mode = InputMode::Reviewing;
+        self.show_reviews = true;
+        self.review_view.refresh_now();
+    }
+
+    fn exit_reviews(&mut self) {
+        self.review_view.cancel_review();
+        self.input_mode = InputMode::Normal;
+        self.show_reviews = false;
+    }
+
+    fn handle_submit_review(&mut self) {
+        let view = &mut self.review_view;
+        let request_id = match view.selected_request() {
+            Some(request) => request.id().to_string(),
+            None => return,
+        };
+        if let Some(decision) = &view.decision {
+            let comment = view.comment_input.value.trim().to_string();
+            let mut entry = AuditEntry::new(AuditAction::AccessRequestReview, &request_id);
+            entry.method = decision.to_string().to_lowercase();
+            entry.reason = Some(comment.clone()).filter(|comment| !comment.is_empty());
+            entry.exit_status = self.teleport.review_access_request(&request_id, decision, &comment);
+            let exit_status = entry.exit_status;
+            self.audit(entry);
+            match tsh_failure(exit_status) {
+                Some(failure) => self.toast.error(&format!("Failed to review the access request, {}", failure)),
+                None => self.toast.info(&format!("Access request {} reviewed", request_id)),
+            }
+        }
+        let view = &mut self.review_view;
+        view.cancel_review();
+        view.refresh_now();
     }
 
     fn enable_cursor(&self, frame: &mut Frame, area: Rect) {
@@ -151,12 +2064,40 @@ impl App {
                 self.search_dialog.set_cursor(frame, main_area);
             },
             InputMode::Connecting => {
-                match self.connect_dialog.current_step {
-                    Step::DatabaseInput => {
-                        self.connect_dialog.database_name_input.set_cursor(frame, main_area);
-                    },
-                    _ => {},
-                }
+                self.connect_dialog.set_cursor(frame, main_area);
+            },
+            InputMode::Requesting => {
+                self.access_request_view.set_cursor(frame, main_area);
+            },
+            InputMode::Reviewing => {
+                self.review_view.set_cursor(frame, main_area);
+            },
+            InputMode::Exporting => {
+                self.export_dialog.set_cursor(frame, main_area);
+            },
+            InputMode::ExportingIde => {
+                self.ide_export_dialog.set_cursor(frame, main_area);
+            },
+            InputMode::Auditing => {
+                self.audit_view.set_cursor(frame, main_area);
+            },
+            InputMode::Session => {
+                self.session_view.set_cursor(frame, main_area);
+            },
+            InputMode::Querying => {
+                self.query_console.set_cursor(frame, main_area);
+            },
+            InputMode::ExportingResults => {
+                self.result_export_dialog.set_cursor(frame, main_area);
+            },
+            InputMode::FanningOut => {
+                self.fanout_view.set_cursor(frame, main_area);
+            },
+            InputMode::PickingSnippet => {
+                self.snippet_dialog.set_cursor(frame, main_area);
+            },
+            InputMode::Diffing => {
+                self.schema_diff_view.set_cursor(frame, main_area);
             },
             _ => {},
         }
@@ -169,34 +2110,213 @@ impl App {
     }
 
     fn toggle_connect(&mut self) {
+        if !self.show_connect && self.connections_blocked() {
+            return;
+        }
         self.input_mode = InputMode::Connecting;
         self.show_connect = !self.show_connect;
+        if self.show_connect {
+            self.set_selected_database_state();
+            self.connect_dialog.apply_defaults();
+        }
     }
 
     fn handle_connect(&mut self) {
+        let confirming = matches!(self.connect_dialog.current_step, Step::Confirmation);
         self.connect_dialog.next_step();
-        match self.connect_dialog.ready_to_connect {
-            Some(flag) => {
-                match flag {
-                    true => self.breakout_and_connect(),
-                    false => self.exit_connect(),
-                }
+        if !confirming && matches!(self.connect_dialog.current_step, Step::Confirmation) {
+            self.prepare_template_preview();
+        }
+        if let Some(flag) = self.connect_dialog.ready_to_connect {
+            match (flag, self.connect_dialog.selected_method()) {
+                (true, method @ (ConnectMethod::Session | ConnectMethod::Query | ConnectMethod::Activity)) => {
+                    let kind = self.connect_dialog.selected_entry
+                        .as_ref()
+                        .and_then(|database| DatabaseKind::from_protocol(&database.spec.protocol));
+                    let session = PendingSession {
+                        args: self.connect_dialog.to_connection_args(),
+                        method,
+                        prepared: None,
+                        reason: self.connect_dialog.reason(),
+                        limit: self.connect_dialog.session_limit.clone(),
+                    };
+                    self.exit_connect();
+                    if matches!(session.method, ConnectMethod::Query) && kind.is_none() {
+                        return;
+                    }
+                    self.start_pre_connect(session, kind);
+                },
+                (true, ConnectMethod::HealthCheck) => {
+                    let args = self.connect_dialog.to_connection_args();
+                    let reason = self.connect_dialog.reason();
+                    let database = self.connect_dialog.selected_entry.clone();
+                    self.exit_connect();
+                    if let Some(database) = database {
+                        self.start_health_check(args, database, reason);
+                    }
+                },
+                (true, method @ ConnectMethod::Subshell) => {
+                    let session = PendingSession {
+                        args: self.connect_dialog.to_connection_args(),
+                        method,
+                        prepared: None,
+                        reason: self.connect_dialog.reason(),
+                        limit: self.connect_dialog.session_limit.clone(),
+                    };
+                    self.exit_connect();
+                    self.start_pre_connect(session, None);
+                },
+                (true, method @ ConnectMethod::Template(_)) => {
+                    if let Some(Ok(prepared)) = self.connect_dialog.template_preview.take() {
+                        let session = PendingSession {
+                            args: self.connect_dialog.to_connection_args(),
+                            method,
+                            prepared: Some(prepared),
+                            reason: self.connect_dialog.reason(),
+                            limit: self.connect_dialog.session_limit.clone(),
+                        };
+                        self.start_pre_connect(session, None);
+                    }
+                    self.exit_connect();
+                },
+                (false, _) => self.exit_connect(),
+            }
+        }
+    }
+
+    fn start_pre_connect(&mut self, session: PendingSession, kind: Option<DatabaseKind>) {
+        if self.pre_connect.is_some() {
+            self.toast.error("Another connection is still running its pre-connect hooks");
+            return;
+        }
+        let hooks = self.config.hooks.clone();
+        let database = self.find_database(&session.args.instance).cloned();
+        let (args, method) = (session.args.clone(), session.method.name());
+        if hooks.iter().any(|hook| hook.stage == HookStage::Pre) {
+            self.toast.info(&format!("Running pre-connect hooks for {}...", args.instance));
+        }
+        let task = Task::spawn(move || {
+            let context = HookContext {
+                args: &args,
+                database: database.as_ref(),
+                method: &method,
+                exit_status: None,
+            };
+            hooks::run_hooks(&hooks, HookStage::Pre, &context)
+        });
+        self.pre_connect = Some(PreConnect { task, session, kind });
+    }
+
+    fn poll_pre_connect(&mut self) {
+        let Some(result) = self.pre_connect.as_ref().and_then(|pending| pending.task.try_take()) else {
+            return;
+        };
+        let Some(PreConnect { session, kind, .. }) = self.pre_connect.take() else {
+            return;
+        };
+        match result {
+            Ok(Ok(())) => self.open_connection(session, kind),
+            Ok(Err(err)) => self.toast.error(&format!("Connection aborted, {}", err)),
+            Err(()) => self.toast.error("Pre-connect hooks crashed, connection aborted"),
+        }
+    }
+
+    // Session limits start counting once the hooks let the connection through
+    fn open_connection(&mut self, session: PendingSession, kind: Option<DatabaseKind>) {
+        let deadline = session.limit.as_ref().map(SessionDeadline::start);
+        match session.method {
+            ConnectMethod::Session => {
+                let PendingSession { args, method, reason, .. } = session;
+                let title = args.instance.clone();
+                let labels = self.find_database(&title).map(|database| database.metadata.labels.clone());
+                let command = pty_session::database_command(&args);
+                let origin = SessionOrigin::Database { args, method: method.name(), reason };
+                self.open_session(&title, origin, command, deadline, labels.as_ref());
             },
-            None => {},
+            ConnectMethod::Query => {
+                let Some(kind) = kind else {
+                    return;
+                };
+                self.close_query_console(false);
+                self.query_console.open(session.args, kind, session.reason, deadline);
+                self.input_mode = InputMode::Querying;
+                self.show_query = true;
+            },
+            ConnectMethod::Activity => {
+                self.close_activity(false);
+                self.activity_view.open(session.args, session.reason, deadline);
+                self.input_mode = InputMode::Monitoring;
+                self.show_activity = true;
+            },
+            // In-place sessions wait for the render loop to hand over the terminal
+            _ => self.pending_session = Some(session),
         }
     }
 
+    // Resolved before confirming so the exact command is reviewed, not the raw template
+    fn prepare_template_preview(&mut self) {
+        let ConnectMethod::Template(template) = self.connect_dialog.selected_method() else {
+            return;
+        };
+        let Some(database) = &self.connect_dialog.selected_entry else {
+            return;
+        };
+
+        let args = self.connect_dialog.to_connection_args();
+        let protocol = database.spec.protocol.clone();
+        let logged_in = self.teleport.active_databases.contains(&args.instance);
+        let login = (!logged_in).then(|| self.implicit_login_entry(&self.connect_dialog.selected_method().name()));
+        // Certificate paths may need a `tsh db login`, which runs in the background
+        self.connect_dialog.template_preview = None;
+        self.template_task = Some(Task::spawn(move || {
+            let prepared = Tsh::prepare_command(&template, &args, &protocol, login.as_ref());
+            if let Err(err) = &prepared {
+                tracing::event!(Level::ERROR, "failed to prepare command template {}: {}", template.name, err);
+            }
+            prepared
+        }));
+    }
+
+    fn poll_template_preview(&mut self) {
+        let Some(result) = self.template_task.as_ref().and_then(|task| task.try_take()) else {
+            return;
+        };
+        self.template_task = None;
+        self.connect_dialog.template_preview = Some(result.unwrap_or(Err("template preparation crashed".to_string())));
+    }
+
     fn handle_connect_char_input(&mut self, to_enter: char) {
         match self.connect_dialog.current_step {
+            Step::InstanceConfirmation | Step::Reason => {
+                if let Some(input) = self.connect_dialog.current_input() {
+                    input.enter_char(to_enter);
+                }
+            },
             Step::DatabaseInput => {
                 self.connect_dialog.database_name_input.enter_char(to_enter);
             },
+            Step::Confirmation => match to_enter {
+                'y' => {
+                    let command = self.connect_dialog.to_connection_args().connect_command();
+                    self.copy_to_clipboard("connect command", &command);
+                },
+                'Y' => {
+                    let command = self.connect_dialog.to_connection_args().tunnel_command();
+                    self.copy_to_clipboard("tunnel command", &command);
+                },
+                _ => {},
+            },
             _ => {},
         }
     }
 
     fn handle_connect_backspace(&mut self) {
         match self.connect_dialog.current_step {
+            Step::InstanceConfirmation | Step::Reason => {
+                if let Some(input) = self.connect_dialog.current_input() {
+                    input.delete_char();
+                }
+            },
             Step::DatabaseInput => {
                 self.connect_dialog.database_name_input.delete_char();
             },
@@ -209,6 +2329,9 @@ impl App {
             Step::UserSelection => {
                 self.connect_dialog.user_list.state.select_next();
             },
+            Step::MethodSelection => {
+                self.connect_dialog.method_state.select_next();
+            },
             Step::Confirmation => {
                 self.connect_dialog.confirmation_toggle.toggle();
             },
@@ -221,6 +2344,9 @@ impl App {
             Step::UserSelection => {
                 self.connect_dialog.user_list.state.select_previous();
             },
+            Step::MethodSelection => {
+                self.connect_dialog.method_state.select_previous();
+            },
             Step::Confirmation => {
                 self.connect_dialog.confirmation_toggle.toggle();
             },
@@ -230,6 +2356,11 @@ impl App {
 
     fn handle_connect_left(&mut self) {
         match self.connect_dialog.current_step {
+            Step::InstanceConfirmation | Step::Reason => {
+                if let Some(input) = self.connect_dialog.current_input() {
+                    input.move_cursor_left();
+                }
+            },
             Step::DatabaseInput => {
                 self.connect_dialog.database_name_input.move_cursor_left();
             },
@@ -239,6 +2370,11 @@ impl App {
 
     fn handle_connect_right(&mut self) {
         match self.connect_dialog.current_step {
+            Step::InstanceConfirmation | Step::Reason => {
+                if let Some(input) = self.connect_dialog.current_input() {
+                    input.move_cursor_right();
+                }
+            },
             Step::DatabaseInput => {
                 self.connect_dialog.database_name_input.move_cursor_right();
             },
@@ -248,44 +2384,139 @@ impl App {
 
     fn exit_connect(&mut self) {
         self.connect_dialog.reset();
+        self.template_task = None;
         self.input_mode = InputMode::Normal;
         self.show_connect = false;
     }
 
     fn set_database_list_state(&mut self) {
         self.database_list.with_items(self.teleport.databases.to_vec());
+        self.database_list.with_active(self.teleport.active_databases.to_vec());
     }
 
-    fn handle_database_list_next(&mut self) {
-        self.database_list.state.select_next();
+    fn set_resource_list_state(&mut self) {
+        self.app_list.with_items(self.teleport.apps.to_vec());
+        self.app_list.with_active(self.teleport.active_apps.to_vec());
+        self.node_list.with_items(self.teleport.nodes.to_vec());
+        self.kube_list.with_items(self.teleport.kube_clusters.to_vec());
+        self.kube_list.with_active(
+            self.teleport.kube_clusters
+                .iter()
+                .filter(|kube_cluster| kube_cluster.selected)
+                .map(|kube_cluster| kube_cluster.kube_cluster_name.clone())
+                .collect()
+        );
     }
 
-    fn handle_database_list_previous(&mut self) {
-        self.database_list.state.select_previous();
+    fn handle_resource_list_next(&mut self) {
+        if self.current_details().focused {
+            self.current_details().select_next();
+            return;
+        }
+        match self.current_tab {
+            ResourceTab::Databases => self.database_list.select_next(),
+            ResourceTab::Applications => self.app_list.select_next(),
+            ResourceTab::Nodes => self.node_list.select_next(),
+            ResourceTab::Kubernetes => self.kube_list.select_next(),
+        }
     }
 
-    fn set_user_list_state(&mut self) {
-        match &self.connect_dialog.selected_entry {
-            Some(entry) => {
-                self.connect_dialog.user_list.with_items(entry.users.allowed.clone());
-            },
-            None => {},
+    fn handle_resource_list_previous(&mut self) {
+        if self.current_details().focused {
+            self.current_details().select_previous();
+            return;
+        }
+        match self.current_tab {
+            ResourceTab::Databases => self.database_list.select_previous(),
+            ResourceTab::Applications => self.app_list.select_previous(),
+            ResourceTab::Nodes => self.node_list.select_previous(),
+            ResourceTab::Kubernetes => self.kube_list.select_previous(),
         }
     }
 
-    fn set_selected_database_state(&mut self) {
-        match &self.database_list.state.selected() {
-            Some(index) => {
-                let database = &self.database_list.items[*index];
-                self.connect_dialog.selected_entry = Some(database.clone());
+    fn current_details(&mut self) -> &mut DetailsTable {
+        match self.current_tab {
+            ResourceTab::Databases => &mut self.database_list.details,
+            ResourceTab::Applications => &mut self.app_list.details,
+            ResourceTab::Nodes => &mut self.node_list.details,
+            ResourceTab::Kubernetes => &mut self.kube_list.details,
+        }
+    }
+
+    fn toggle_details_focus(&mut self) {
+        let details = self.current_details();
+        details.focused = !details.focused;
+    }
+
+    fn switch_tab(&mut self, tab: ResourceTab) {
+        self.current_details().focused = false;
+        self.current_tab = tab;
+    }
+
+    fn copy_to_clipboard(&mut self, label: &str, text: &str) {
+        match self.clipboard.copy(text) {
+            Ok(()) => {
+                tracing::event!(Level::INFO, "copied {} to the clipboard", label);
+                self.toast.info(&format!("Copied {}", label));
+            },
+            Err(err) => {
+                tracing::event!(Level::ERROR, "failed to copy {}: {}", label, err);
+                self.toast.error(&format!("Failed to copy {}", label));
             },
-            None => {},
         }
     }
 
-    fn breakout_and_connect(&mut self) {
-        self.initiate_connection = true;
-        self.exit();
+    // Copies the highlighted detail when the pane is focused, otherwise the selected entry's address
+    fn handle_copy(&mut self) {
+        let details = self.current_details();
+        if details.focused {
+            if let Some((key, value)) = details.selected_field() {
+                let (label, value) = (key.to_string(), value.to_string());
+                self.copy_to_clipboard(&label, &value);
+            }
+            return;
+        }
+
+        let selected = match self.current_tab {
+            ResourceTab::Databases => self.database_list
+                .selected_item()
+                .map(|database| ("database URI", database.spec.uri.clone())),
+            ResourceTab::Applications => self.app_list
+                .selected_item()
+                .map(|app| ("application URL", app.url())),
+            ResourceTab::Nodes => self.node_list
+                .selected_item()
+                .map(|node| ("hostname", node.spec.hostname.clone())),
+            ResourceTab::Kubernetes => self.kube_list
+                .selected_item()
+                .map(|kube_cluster| ("cluster name", kube_cluster.kube_cluster_name.clone())),
+        };
+        if let Some((label, value)) = selected {
+            self.copy_to_clipboard(label, &value);
+        }
+    }
+
+    fn handle_copy_connect_command(&mut self) {
+        if self.current_tab != ResourceTab::Databases {
+            return;
+        }
+        if let Some(database) = self.database_list.selected_item() {
+            let command = format!("tsh db connect {}", database.metadata.name);
+            self.copy_to_clipboard("connect command", &command);
+        }
+    }
+
+    fn set_user_list_state(&mut self) {
+        if let Some(entry) = &self.connect_dialog.selected_entry {
+            self.connect_dialog.user_list.with_items(entry.users.allowed.clone());
+        }
+    }
+
+    fn set_selected_database_state(&mut self) {
+        if let Some(index) = &self.database_list.state.selected() {
+            let database = &self.database_list.items[*index];
+            self.connect_dialog.selected_entry = Some(database.clone());
+        }
     }
 
     fn exit(&mut self) {
@@ -297,11 +2528,49 @@ impl Widget for &App {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let (header_area, main_area, footer_area) = get_high_level_areas(area);
 
-        render_header(header_area, buf);
+        render_header(header_area, buf, self.current_tab);
 
-        if !self.teleport.databases.is_empty() {
-            self.database_list.render(main_area, buf);
+        if self.show_sessions {
+            self.session_view.render(main_area, buf);
+        } else if self.show_query {
+            self.query_console.render(main_area, buf);
+        } else if self.show_activity {
+            self.activity_view.render(main_area, buf);
+        } else if self.show_fanout {
+            self.fanout_view.render(main_area, buf);
+        } else if self.show_diff {
+            self.schema_diff_view.render(main_area, buf);
+        } else if self.show_replays {
+            self.replay_view.render(main_area, buf);
+        } else if self.show_requests {
+            self.access_request_view.render(main_area, buf);
+        } else if self.show_reviews {
+            self.review_view.render(main_area, buf);
+        } else if self.show_audit {
+            self.audit_view.render(main_area, buf);
+        } else {
+            match self.current_tab {
+                ResourceTab::Databases => {
+                    if !self.teleport.databases.is_empty() {
+                        self.database_list.render(main_area, buf);
+                    }
+                },
+                ResourceTab::Applications => self.app_list.render(main_area, buf),
+                ResourceTab::Nodes => self.node_list.render(main_area, buf),
+                ResourceTab::Kubernetes => self.kube_list.render(main_area, buf),
+            }
+        }
+        match self.input_mode {
+            InputMode::SelectingLogin => self.ssh_dialog.render(main_area, buf),
+            InputMode::Exporting => self.export_dialog.render(main_area, buf),
+            InputMode::ExportingIde => self.ide_export_dialog.render(main_area, buf),
+            InputMode::ExportingResults => self.result_export_dialog.render(main_area, buf),
+            InputMode::PickingSnippet => self.snippet_dialog.render(main_area, buf),
+            InputMode::Testing => self.health_check_view.render(main_area, buf),
+            _ => {},
         }
+
+        self.toast.render(main_area, buf);
         if self.show_search {
             self.search_dialog.render(main_area, buf);
         }
@@ -309,7 +2578,16 @@ impl Widget for &App {
             self.connect_dialog.render(main_area, buf);
         }
 
-        render_footer(footer_area, buf)
+        render_footer(footer_area, buf, &self.input_mode)
+    }
+}
+
+// Describes a failed tsh command from its exit status, the details are in the log
+fn tsh_failure(exit_status: Option<i32>) -> Option<String> {
+    match exit_status {
+        Some(0) => None,
+        Some(code) => Some(format!("tsh exited with status {}", code)),
+        None => Some("tsh could not be run".to_string()),
     }
 }
 
@@ -317,22 +2595,57 @@ fn get_high_level_areas(area: Rect) -> (Rect, Rect, Rect) {
     let vertical = Layout::vertical([
         Constraint::Length(2),
         Constraint::Min(0),
-        Constraint::Length(2),
+        Constraint::Length(3),
     ]);
     let [header_area, main_area, footer_area] = vertical.areas(area);
 
-    return (header_area, main_area, footer_area);
+    (header_area, main_area, footer_area)
 }
 
-fn render_header(area: Rect, buf: &mut Buffer) {
+fn render_header(area: Rect, buf: &mut Buffer, current_tab: ResourceTab) {
+    let vertical = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ]);
+    let [title_area, tabs_area] = vertical.areas(area);
+
     Paragraph::new("Kith")
         .bold()
         .centered()
-        .render(area, buf);
+        .render(title_area, buf);
+
+    let titles = ResourceTab::ALL.map(|tab| tab.to_string());
+    let tabs = Tabs::new(titles)
+        .select(current_tab.to_index())
+        .highlight_style(styles::SELECTED_STYLE);
+
+    // Center the tab bar within the header
+    let tabs_width = titles.iter().map(|title| title.len() as u16 + 3).sum::<u16>();
+    let horizontal = Layout::horizontal([Constraint::Length(tabs_width)])
+        .flex(ratatui::layout::Flex::Center);
+    let [tabs_area] = horizontal.areas(tabs_area);
+    tabs.render(tabs_area, buf);
 }
 
-fn render_footer(area: Rect, buf: &mut Buffer) {
-    Paragraph::new("\n<s> Search, ↓↑ Move, <c> Connect, <esc> Escape Dialog, <q> Quit")
+fn render_footer(area: Rect, buf: &mut Buffer, input_mode: &InputMode) {
+    let hints = match input_mode {
+        InputMode::Requesting => "\n<s> Search, ↓↑ Move, <tab> Switch Pane, <n> New Request, <a> Assume, <r> Refresh, <esc> Back",
+        InputMode::Reviewing => "\n↓↑ Move, <a> Approve, <d> Deny, <r> Refresh, <enter> Submit Review, <esc> Back",
+        InputMode::SelectingLogin => "\n↓↑ Move, <enter> SSH, <esc> Escape Dialog",
+        InputMode::Exporting | InputMode::ExportingIde | InputMode::ExportingResults | InputMode::PickingSnippet => "\n↓↑ Move, <enter> Next, <esc> Escape Dialog",
+        InputMode::Auditing => "\nType to Filter, ↓↑ Move, <esc> Back",
+        InputMode::Querying => "\n<F5>/<alt-enter> Run, <ctrl-c> Cancel Query, <F3> Snippets, <F6> Export Results, <tab> Switch Pane, ↓↑←→ Scroll Results, <F2> Schema Browser, <enter> Expand, <i> Insert Name, <r>/<R> Refresh Node/Tree, <esc> Close Console",
+        InputMode::Monitoring => "\n↓↑ Move, <c> Cancel Query, <k> Terminate Backend, <a> Show/Hide Idle, <p> Pause/Resume Refresh, <r> Refresh, <esc> Close",
+        InputMode::Testing => "\n<r> Test Again, <y> Copy Report, <esc> Close",
+        InputMode::Diffing => "\n<F5> Compare, <tab> Switch Field, ↓↑ Move, <a> All Tables/Differences, <y> Copy Report, <F6> Export Report, <esc> Close",
+        InputMode::FanningOut => "\n<F5>/<alt-enter> Run on Marked Databases, <ctrl-c> Cancel, <tab> Switch Field, ↓↑←→ Move/Scroll, <esc> Close",
+        InputMode::Replaying => "\n↓↑ Move, <enter> Play, <space> Pause/Resume, ←→ Seek, <+>/<-> Speed, <home> Restart, <r> Refresh, <esc> Back",
+        InputMode::Session => "\n<ctrl-]> Back to Inventory, <F7>/<F8> Previous/Next Session, <shift-pgup>/<shift-pgdn> Scroll",
+        InputMode::Connecting => "\n↓↑ Move, <enter> Next, <y>/<Y> Copy Connect/Tunnel Command (confirmation), <esc> Escape Dialog",
+        _ => "\n←→ Switch Tab, <tab> Focus Details, <y>/<Y> Copy Value/Command, <s> Search, ↓↑ Move, <c> Connect, <e>/<E> Export Config/IDE Data Sources, <i>/<o> DB Login/Logout, <r> Access Requests, <v> Reviews, <a> Audit Log, <t> Sessions, <p> Recordings, <space> Mark, <f> Fan-out Query, <d> Schema Diff, <h> Connection Test, <esc> Escape Dialog, <q> Quit",
+    };
+    Paragraph::new(hints)
+        .wrap(Wrap { trim: false })
         .centered()
         .render(area, buf);
 }
@@ -0,0 +1,296 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, IsTerminal, Write},
+    sync::atomic::AtomicBool,
+    time::Instant,
+};
+
+use crate::{
+    config::Config,
+    core::{
+        audit::{self, AuditAction, AuditEntry},
+        fanout,
+        guardrail::find_guardrail,
+        hooks::{self, HookContext, HookStage},
+        query::QueryResult,
+        result_export::{ResultFormat, ResultWriter},
+        session_limit::find_session_limit,
+        snippet::{self, Snippet},
+        tsh::Tsh,
+    },
+};
+
+const AUDIT_USAGE: &str = "usage: kith audit [--instance NAME] [--action ACTION] [--filter TEXT] [--limit N] [--json]";
+
+const QUERY_USAGE: &str = "usage: kith query --snippet NAME [--db-user USER] [--db-name NAME] [--param KEY=VALUE]... [--format csv|ndjson|json|markdown] [--reason TEXT] INSTANCE";
+
+// Subcommands run without the TUI, returns false when the arguments are not a subcommand
+pub fn run(args: &[String]) -> io::Result<bool> {
+    match args.first().map(String::as_str) {
+        Some("audit") => {
+            run_audit(&args[1..])?;
+            Ok(true)
+        },
+        Some("query") => {
+            run_query(&args[1..])?;
+            Ok(true)
+        },
+        _ => Ok(false),
+    }
+}
+
+struct AuditOptions {
+    instance: Option<String>,
+    action: Option<String>,
+    filter: String,
+    limit: Option<usize>,
+    json: bool,
+}
+
+fn parse_audit_options(args: &[String]) -> Result<AuditOptions, String> {
+    let mut options = AuditOptions {
+        instance: None,
+        action: None,
+        filter: String::new(),
+        limit: None,
+        json: false,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--instance" => options.instance = Some(value()?),
+            "--action" => options.action = Some(value()?),
+            "--filter" => options.filter = value()?,
+            "--limit" => {
+                let limit = value()?;
+                options.limit = Some(limit.parse().map_err(|_| format!("invalid limit `{}`", limit))?);
+            },
+            "--json" => options.json = true,
+            _ => return Err(format!("unknown argument `{}`", arg)),
+        }
+    }
+    Ok(options)
+}
+
+fn run_audit(args: &[String]) -> io::Result<()> {
+    let options = match parse_audit_options(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("kith: {}\n{}", err, AUDIT_USAGE);
+            std::process::exit(2);
+        },
+    };
+
+    let entries: Vec<AuditEntry> = audit::read_entries()?
+        .into_iter()
+        .filter(|entry| options.instance.as_ref().is_none_or(|instance| entry.instance == *instance))
+        .filter(|entry| options.action.as_ref().is_none_or(|action| entry.action.to_string() == action))
+        .filter(|entry| entry.matches(&options.filter))
+        .collect();
+
+    // The limit keeps the most recent entries
+    let skip = options.limit
+        .map(|limit| entries.len().saturating_sub(limit))
+        .unwrap_or(0);
+
+    for entry in &entries[skip..] {
+        if options.json {
+            println!("{}", serde_json::to_string(entry)?);
+        } else {
+            println!(
+                "{}  {:<21} {:<24} {:<12} {:<12} {:<18} {:>4} {:>8}  {}",
+                entry.timestamp,
+                entry.action.to_string(),
+                entry.instance,
+                entry.db_user,
+                entry.db_name,
+                entry.method,
+                entry.exit_status_label(),
+                entry.duration_label(),
+                entry.teleport_user,
+            );
+        }
+    }
+    Ok(())
+}
+
+struct QueryOptions {
+    snippet: String,
+    instance: String,
+    db_user: String,
+    db_name: String,
+    params: HashMap<String, String>,
+    format: ResultFormat,
+    reason: Option<String>,
+}
+
+fn parse_query_options(args: &[String]) -> Result<QueryOptions, String> {
+    let mut snippet = None;
+    let mut instance = None;
+    let mut options = QueryOptions {
+        snippet: String::new(),
+        instance: String::new(),
+        db_user: String::new(),
+        db_name: String::new(),
+        params: HashMap::new(),
+        format: ResultFormat::Markdown,
+        reason: None,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--snippet" => snippet = Some(value()?),
+            "--db-user" => options.db_user = value()?,
+            "--db-name" => options.db_name = value()?,
+            "--param" => {
+                let param = value()?;
+                let (key, param_value) = param
+                    .split_once('=')
+                    .ok_or(format!("invalid parameter `{}`, expected KEY=VALUE", param))?;
+                options.params.insert(key.to_string(), param_value.to_string());
+            },
+            "--format" => {
+                let format = value()?;
+                options.format = ResultFormat::from_name(&format).ok_or(format!("unknown format `{}`", format))?;
+            },
+            "--reason" => options.reason = Some(value()?),
+            _ if arg.starts_with("--") => return Err(format!("unknown argument `{}`", arg)),
+            _ if instance.is_none() => instance = Some(arg.clone()),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+
+    options.snippet = snippet.ok_or("missing --snippet")?;
+    options.instance = instance.ok_or("missing instance")?;
+    Ok(options)
+}
+
+fn run_query(args: &[String]) -> io::Result<()> {
+    let options = match parse_query_options(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("kith: {}\n{}", err, QUERY_USAGE);
+            std::process::exit(2);
+        },
+    };
+    if let Err(err) = run_snippet(&options) {
+        eprintln!("kith: {}", err);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// Applies the snippet scope, guardrail reason, defaults, session limit, hooks and the audit log.
+// A guarded instance is not asked for by name, passing it as an argument stands in for typing it
+fn run_snippet(options: &QueryOptions) -> Result<(), String> {
+    let mut config = Config::new();
+    config.load();
+    // Without the config there are no guardrails, hooks or limits to apply
+    if let Some(err) = &config.load_error {
+        return Err(err.clone());
+    }
+
+    let snippets = snippet::load_snippets()?;
+    let snippet = snippets
+        .iter()
+        .find(|snippet| snippet.name == options.snippet)
+        .ok_or(format!("no snippet named `{}`", options.snippet))?;
+
+    let mut tsh = Tsh::new();
+    tsh.read_status();
+    tsh.read_databases(&options.instance)?;
+    let database = tsh.databases
+        .iter()
+        .find(|database| database.metadata.name == options.instance)
+        .ok_or(format!("no database instance named `{}`", options.instance))?;
+    if !snippet.applies_to(database) {
+        return Err(format!("snippet `{}` does not apply to {} ({})", snippet.name, options.instance, snippet.scope()));
+    }
+    if find_guardrail(&config.guardrails, database).is_some_and(|guardrail| guardrail.require_reason) && options.reason.is_none() {
+        return Err(format!("{} is guarded, a --reason is required", options.instance));
+    }
+
+    let target = fanout::resolve_target(database, &options.db_user, &options.db_name, &config.defaults)?;
+    let sql = snippet.render(&parameter_values(snippet, &options.params)?, target.kind)?;
+    // The query console closes at the instance's session limit, without one the fan-out timeout applies
+    let timeout = find_session_limit(&config.session_limits, database)
+        .map(|limit| limit.max_duration())
+        .unwrap_or_else(|| config.fanout.timeout());
+    let method = format!("snippet:{}", snippet.name);
+    let mut context = HookContext {
+        args: &target.args,
+        database: Some(database),
+        method: &method,
+        exit_status: None,
+    };
+    hooks::run_hooks(&config.hooks, HookStage::Pre, &context)
+        .map_err(|err| format!("pre-connect hook failed: {}", err))?;
+
+    let started = Instant::now();
+    // Every row is kept, the display cap is only for the console
+    let result = fanout::query_target(&target, &sql, usize::MAX, timeout, &AtomicBool::new(false));
+    let exit_status = if result.is_ok() { 0 } else { 1 };
+
+    context.exit_status = Some(exit_status);
+    if let Err(err) = hooks::run_hooks(&config.hooks, HookStage::Post, &context) {
+        eprintln!("kith: post-connect hook failed: {}", err);
+    }
+    let mut entry = AuditEntry::for_connection(AuditAction::Connect, &target.args, &method)
+        .with_duration(started.elapsed());
+    entry.teleport_user = tsh.username.clone();
+    entry.cluster = if tsh.cluster.is_empty() { config.tsh_cluster.clone() } else { tsh.cluster.clone() };
+    entry.exit_status = Some(exit_status);
+    entry.reason = options.reason.clone();
+    audit::record(&entry);
+
+    print_result(&result?, options.format).map_err(|err| err.to_string())
+}
+
+// Values passed with --param win, the rest are asked for on a terminal or fall back to their default
+fn parameter_values(snippet: &Snippet, params: &HashMap<String, String>) -> Result<HashMap<String, String>, String> {
+    let mut values = params.clone();
+    let interactive = io::stdin().is_terminal();
+    for parameter in snippet.parameters() {
+        if values.contains_key(&parameter.name) {
+            continue;
+        }
+        if !interactive {
+            match parameter.default {
+                Some(default) => values.insert(parameter.name, default),
+                None => return Err(format!("missing --param {}=VALUE", parameter.name)),
+            };
+            continue;
+        }
+
+        match &parameter.default {
+            Some(default) => eprint!("{} [{}]: ", parameter.name, default),
+            None => eprint!("{}: ", parameter.name),
+        }
+        let _ = io::stderr().flush();
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line).map_err(|err| err.to_string())?;
+        let value = line.trim_end_matches(['\n', '\r']).to_string();
+        let value = match (value.is_empty(), parameter.default) {
+            (true, Some(default)) => default,
+            _ => value,
+        };
+        values.insert(parameter.name, value);
+    }
+    Ok(values)
+}
+
+fn print_result(result: &QueryResult, format: ResultFormat) -> io::Result<()> {
+    if result.columns.is_empty() {
+        eprintln!("{} rows affected", result.affected.unwrap_or_default());
+        return Ok(());
+    }
+    let mut writer = ResultWriter::new(io::stdout().lock(), format, result.columns.clone())?;
+    for row in &result.rows {
+        writer.write_row(row)?;
+    }
+    writer.finish().map(|_| ())
+}
@@ -1,23 +1,73 @@
-use std::env;
+use std::{env, fs, io, path::PathBuf};
+use serde::Deserialize;
 use tracing::{event, Level};
 
+use crate::core::{
+    defaults::ConnectionDefault,
+    fanout::FanoutConfig,
+    guardrail::Guardrail,
+    hooks::Hook,
+    recording::RecordingConfig,
+    session_limit::SessionLimit,
+    template::CommandTemplate,
+};
+
 #[derive(Debug, Default)]
 pub struct Config {
     pub tsh_proxy: String,
     pub tsh_cluster: String,
+    pub clipboard: String,
+    pub templates: Vec<CommandTemplate>,
+    pub hooks: Vec<Hook>,
+    pub guardrails: Vec<Guardrail>,
+    pub defaults: Vec<ConnectionDefault>,
+    pub session_limits: Vec<SessionLimit>,
+    pub recording: RecordingConfig,
+    pub fanout: FanoutConfig,
+    pub load_error: Option<String>,
+}
+
+// Optional settings read from `config.toml`, environment variables stay the source for Teleport values
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    templates: Vec<CommandTemplate>,
+    #[serde(default)]
+    hooks: Vec<Hook>,
+    #[serde(default)]
+    guardrails: Vec<Guardrail>,
+    #[serde(default)]
+    defaults: Vec<ConnectionDefault>,
+    #[serde(default)]
+    session_limits: Vec<SessionLimit>,
+    #[serde(default)]
+    recording: RecordingConfig,
+    #[serde(default)]
+    fanout: FanoutConfig,
 }
 
 impl Config {
     pub fn new() -> Config {
-        return Config {
+        Config {
             tsh_proxy: String::new(), 
             tsh_cluster: String::new(),
+            clipboard: String::new(),
+            templates: vec![],
+            hooks: vec![],
+            guardrails: vec![],
+            defaults: vec![],
+            session_limits: vec![],
+            recording: RecordingConfig::default(),
+            fanout: FanoutConfig::default(),
+            load_error: None,
         }
     }
 
     pub fn load(&mut self) {
         self.get_tsh_proxy();
         self.get_tsh_cluster();
+        self.get_clipboard();
+        self.get_config_file();
     }
 
     fn get_tsh_proxy(&mut self) {
@@ -38,4 +88,132 @@ impl Config {
             event!(Level::DEBUG, "The KITH_TSH_CLUSTER environment variable is not set.");
         }
     }
+
+    fn get_clipboard(&mut self) {
+        if let Ok(kith_clipboard) = env::var("KITH_CLIPBOARD") {
+            event!(Level::DEBUG, "Your clipboard backend is: {}", kith_clipboard);
+            self.clipboard = kith_clipboard;
+        } else {
+            event!(Level::DEBUG, "The KITH_CLIPBOARD environment variable is not set, detecting backend.");
+        }
+    }
+
+    fn get_config_file(&mut self) {
+        let Some(path) = config_file_path() else {
+            event!(Level::DEBUG, "No config directory available, skipping config file.");
+            return;
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                event!(Level::DEBUG, "No config file found at {}", path.display());
+                return;
+            },
+            Err(err) => {
+                event!(Level::ERROR, "failed to read config file {}: {}", path.display(), err);
+                self.load_error = Some(format!("Failed to read {}: {}", path.display(), err));
+                return;
+            },
+        };
+
+        match toml::from_str::<ConfigFile>(&contents) {
+            Ok(file) => {
+                event!(Level::DEBUG, "loaded config file {}", path.display());
+                self.set_templates(file.templates);
+                self.set_hooks(file.hooks);
+                self.set_guardrails(file.guardrails);
+                self.set_defaults(file.defaults);
+                self.set_session_limits(file.session_limits);
+                self.recording = file.recording;
+                self.set_fanout(file.fanout);
+            },
+            Err(err) => {
+                event!(Level::ERROR, "failed to parse config file {}: {}", path.display(), err);
+                self.load_error = Some(format!("Invalid config file {}: {}", path.display(), err.message()));
+            },
+        }
+    }
+
+    // Invalid templates are dropped up front so they never show up as connect methods
+    fn set_templates(&mut self, templates: Vec<CommandTemplate>) {
+        for template in templates {
+            match template.validate() {
+                Ok(()) => self.templates.push(template),
+                Err(err) => {
+                    event!(Level::ERROR, "invalid command template {}: {}", template.name, err);
+                    self.load_error = Some(format!("Invalid template `{}`: {}", template.name, err));
+                },
+            }
+        }
+    }
+
+    fn set_hooks(&mut self, hooks: Vec<Hook>) {
+        for hook in hooks {
+            match hook.validate() {
+                Ok(()) => self.hooks.push(hook),
+                Err(err) => {
+                    event!(Level::ERROR, "invalid hook {}: {}", hook.name, err);
+                    self.load_error = Some(format!("Invalid hook `{}`: {}", hook.name, err));
+                },
+            }
+        }
+    }
+
+    // An invalid guardrail still applies with the default color, dropping it would be unsafe
+    fn set_guardrails(&mut self, guardrails: Vec<Guardrail>) {
+        for guardrail in guardrails {
+            if let Err(err) = guardrail.validate() {
+                event!(Level::ERROR, "invalid guardrail {}: {}", guardrail.selector.to_string(), err);
+                self.load_error = Some(format!("Invalid guardrail `{}`: {}", guardrail.selector.to_string(), err));
+            }
+            self.guardrails.push(guardrail);
+        }
+    }
+
+    fn set_defaults(&mut self, defaults: Vec<ConnectionDefault>) {
+        for default in defaults {
+            match default.validate() {
+                Ok(()) => self.defaults.push(default),
+                Err(err) => {
+                    event!(Level::ERROR, "invalid default rule {}: {}", default.selector.to_string(), err);
+                    self.load_error = Some(format!("Invalid default rule `{}`: {}", default.selector.to_string(), err));
+                },
+            }
+        }
+    }
+
+    // A limit without a valid duration cannot be enforced, it is reported at startup instead
+    fn set_session_limits(&mut self, limits: Vec<SessionLimit>) {
+        for limit in limits {
+            match limit.validate() {
+                Ok(()) => self.session_limits.push(limit),
+                Err(err) => {
+                    event!(Level::ERROR, "invalid session limit {}: {}", limit.selector.to_string(), err);
+                    self.load_error = Some(format!("Invalid session limit `{}`: {}", limit.selector.to_string(), err));
+                },
+            }
+        }
+    }
+
+    // Invalid limits fall back to the defaults rather than running unbounded
+    fn set_fanout(&mut self, fanout: FanoutConfig) {
+        match fanout.validate() {
+            Ok(()) => self.fanout = fanout,
+            Err(err) => {
+                event!(Level::ERROR, "invalid fan-out settings: {}", err);
+                self.load_error = Some(format!("Invalid fan-out settings: {}", err));
+            },
+        }
+    }
+}
+
+// `KITH_CONFIG` overrides the default `<config dir>/kith/config.toml`
+pub fn config_file_path() -> Option<PathBuf> {
+    if let Ok(path) = env::var("KITH_CONFIG") {
+        if !path.is_empty() {
+            return Some(PathBuf::from(path));
+        }
+    }
+    dirs::config_dir().map(|dir| dir.join("kith").join("config.toml"))
 }
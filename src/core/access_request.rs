@@ -0,0 +1,278 @@
+use std::{collections::HashMap, process::{Command, Stdio}};
+use serde::{Deserialize, Deserializer};
+use tracing::{event, Level};
+
+use super::tsh::{command_exit_code, read_json_output, Tsh};
+
+pub struct AccessRequestArgs {
+    pub resource_ids: Vec<String>,
+    pub roles: Vec<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RequestableResource {
+    #[serde(default)]
+    pub kind: String,
+    pub metadata: ResourceMetadata,
+    #[serde(default)]
+    spec: RequestableSpec,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ResourceMetadata {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct RequestableSpec {
+    // Database servers wrap the database they proxy
+    database: Option<Box<RequestableResource>>,
+}
+
+impl RequestableResource {
+    pub fn name(&self) -> &str {
+        match &self.spec.database {
+            Some(database) => database.name(),
+            None => &self.metadata.name,
+        }
+    }
+
+    pub fn resource_id(&self, cluster: &str) -> String {
+        format!("/{}/db/{}", cluster, self.name())
+    }
+}
+
+pub enum ReviewDecision {
+    Approve,
+    Deny,
+}
+
+impl ReviewDecision {
+    pub fn to_string(&self) -> &str {
+        match self {
+            ReviewDecision::Approve => "Approve",
+            ReviewDecision::Deny => "Deny",
+        }
+    }
+
+    fn to_flag(&self) -> &str {
+        match self {
+            ReviewDecision::Approve => "--approve",
+            ReviewDecision::Deny => "--deny",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccessRequest {
+    pub metadata: ResourceMetadata,
+    pub spec: AccessRequestSpec,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccessRequestSpec {
+    pub user: String,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(deserialize_with = "deserialize_state")]
+    pub state: RequestState,
+    #[serde(default)]
+    pub created: String,
+    #[serde(default)]
+    pub expires: String,
+    #[serde(default)]
+    pub request_reason: String,
+    #[serde(default)]
+    pub resolve_reason: String,
+    #[serde(default)]
+    pub requested_resource_ids: Vec<ResourceId>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResourceId {
+    #[serde(default)]
+    pub cluster: String,
+    #[serde(default)]
+    pub kind: String,
+    #[serde(default)]
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestState {
+    Pending,
+    Approved,
+    Denied,
+    Promoted,
+    Unknown,
+}
+
+impl RequestState {
+    pub fn to_string(&self) -> &str {
+        match self {
+            RequestState::Pending => "PENDING",
+            RequestState::Approved => "APPROVED",
+            RequestState::Denied => "DENIED",
+            RequestState::Promoted => "PROMOTED",
+            RequestState::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+// tsh serializes the request state either as the protobuf enum value or its name
+fn deserialize_state<'de, D>(deserializer: D) -> Result<RequestState, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawState {
+        Number(i64),
+        Name(String),
+    }
+
+    let state = match RawState::deserialize(deserializer)? {
+        RawState::Number(1) => RequestState::Pending,
+        RawState::Number(2) => RequestState::Approved,
+        RawState::Number(3) => RequestState::Denied,
+        RawState::Number(4) => RequestState::Promoted,
+        RawState::Number(_) => RequestState::Unknown,
+        RawState::Name(name) => match name.to_uppercase().as_str() {
+            "PENDING" => RequestState::Pending,
+            "APPROVED" => RequestState::Approved,
+            "DENIED" => RequestState::Denied,
+            "PROMOTED" => RequestState::Promoted,
+            _ => RequestState::Unknown,
+        },
+    };
+    Ok(state)
+}
+
+impl AccessRequest {
+    pub fn id(&self) -> &str {
+        &self.metadata.name
+    }
+
+    pub fn resource_names(&self) -> Vec<String> {
+        self.spec.requested_resource_ids
+            .iter()
+            .map(|resource| format!("{}/{}", resource.kind, resource.name))
+            .collect()
+    }
+}
+
+// Trims RFC 3339 timestamps down to the minute for display
+pub fn format_timestamp(timestamp: &str) -> String {
+    timestamp
+        .replace('T', " ")
+        .chars()
+        .take(16)
+        .collect()
+}
+
+impl Tsh {
+    pub fn search_requestable_databases(search: &str) -> Vec<RequestableResource> {
+        event!(Level::DEBUG, "searching requestable databases");
+
+        let search = format!("--search={}", search);
+
+        let teleport_cmd = Command::new("tsh")
+            .args(["request", "search", "--kind=db", &search, "--format=json"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        read_json_output("teleport request search", teleport_cmd)
+    }
+
+    pub fn read_access_requests() -> Vec<AccessRequest> {
+        event!(Level::DEBUG, "reading access requests");
+
+        let teleport_cmd = Command::new("tsh")
+            .args(["request", "ls", "--my-requests", "--format=json"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        read_json_output("teleport request ls", teleport_cmd)
+    }
+
+    pub fn read_reviewable_requests() -> Vec<AccessRequest> {
+        event!(Level::DEBUG, "reading reviewable access requests");
+
+        let teleport_cmd = Command::new("tsh")
+            .args(["request", "ls", "--reviewable", "--format=json"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        read_json_output("teleport request ls --reviewable", teleport_cmd)
+    }
+
+    pub fn review_access_request(&self, request_id: &str, decision: &ReviewDecision, comment: &str) -> Option<i32> {
+        event!(Level::INFO, "reviewing access request {}: {}", request_id, decision.to_string());
+
+        let mut cmd_args = vec!["request", "review", decision.to_flag()];
+        let reason = format!("--reason={}", comment);
+        if !comment.is_empty() {
+            cmd_args.push(&reason);
+        }
+        cmd_args.push(request_id);
+
+        let teleport_cmd = Command::new("tsh")
+            .args(cmd_args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        command_exit_code("teleport request review", teleport_cmd)
+    }
+
+    pub fn create_access_request(&self, args: AccessRequestArgs) -> Option<i32> {
+        event!(Level::INFO, "creating access request for: {:?}", args.resource_ids);
+
+        // Without --nowait tsh blocks until the request is resolved
+        let mut cmd_args = vec![
+            "request".to_string(),
+            "create".to_string(),
+            "--nowait".to_string(),
+        ];
+        for resource_id in &args.resource_ids {
+            cmd_args.push(format!("--resource={}", resource_id));
+        }
+        if !args.roles.is_empty() {
+            cmd_args.push(format!("--roles={}", args.roles.join(",")));
+        }
+        if !args.reason.is_empty() {
+            cmd_args.push(format!("--reason={}", args.reason));
+        }
+
+        let teleport_cmd = Command::new("tsh")
+            .args(cmd_args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        command_exit_code("teleport request create", teleport_cmd)
+    }
+
+    pub fn assume_access_request(&self, proxy_name: &str, cluster: &str, request_id: &str) -> Option<i32> {
+        event!(Level::INFO, "assuming access request: {}", request_id);
+
+        let proxy = format!("--proxy={}", proxy_name);
+        let request = format!("--request-id={}", request_id);
+
+        let teleport_cmd = Command::new("tsh")
+            .args(["login", &proxy, cluster, &request])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        command_exit_code("teleport login with access request", teleport_cmd)
+    }
+}
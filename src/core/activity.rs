@@ -0,0 +1,234 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+use tracing::{event, Level};
+
+use super::{
+    query::{connect_postgres, postgres_error},
+    schema::postgres_rows,
+    tsh::ConnectionArgs,
+    worker::Worker,
+};
+
+// Every backend except the monitor's own connection, longest running first
+const ACTIVITY_SQL: &str = "SELECT pid::text, usename::text, datname::text, application_name, client_addr::text, \
+    backend_type, state, wait_event_type || ':' || wait_event, \
+    extract(epoch FROM clock_timestamp() - query_start)::text, \
+    extract(epoch FROM clock_timestamp() - xact_start)::text, \
+    array_to_string(pg_blocking_pids(pid), ','), query \
+    FROM pg_stat_activity WHERE pid <> pg_backend_pid() ORDER BY query_start NULLS LAST, pid";
+
+const LOCKS_SQL: &str = "SELECT pid::text, granted::text, mode, locktype, \
+    coalesce(relation::regclass::text, transactionid::text, virtualxid, '') \
+    FROM pg_locks WHERE pid IS NOT NULL AND pid <> pg_backend_pid()";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum BackendAction {
+    Cancel,
+    Terminate,
+}
+
+impl BackendAction {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            BackendAction::Cancel => "cancel",
+            BackendAction::Terminate => "terminate",
+        }
+    }
+
+    fn function(self) -> &'static str {
+        match self {
+            BackendAction::Cancel => "pg_cancel_backend",
+            BackendAction::Terminate => "pg_terminate_backend",
+        }
+    }
+}
+
+// One row of `pg_stat_activity` with the locks it holds or waits for
+#[derive(Clone)]
+pub struct Backend {
+    pub pid: i32,
+    pub user: Option<String>,
+    pub database: Option<String>,
+    pub application: Option<String>,
+    pub client: Option<String>,
+    pub backend_type: Option<String>,
+    pub state: Option<String>,
+    pub wait_event: Option<String>,
+    pub query_age: Option<Duration>,
+    pub transaction_age: Option<Duration>,
+    pub query: Option<String>,
+    pub blocked_by: Vec<i32>,
+    pub locks_held: usize,
+    // First lock not granted yet, eg. `RowExclusiveLock on relation orders`
+    pub waiting_for: Option<String>,
+}
+
+impl Backend {
+    // Background processes have no state, they are listed along idle sessions
+    pub fn is_idle(&self) -> bool {
+        matches!(self.state.as_deref(), None | Some("idle"))
+    }
+}
+
+pub struct ActivitySnapshot {
+    pub backends: Vec<Backend>,
+    pub taken_at: Instant,
+    pub elapsed: Duration,
+}
+
+impl ActivitySnapshot {
+    pub fn find(&self, pid: i32) -> Option<&Backend> {
+        self.backends.iter().find(|backend| backend.pid == pid)
+    }
+
+    pub fn is_blocking(&self, pid: i32) -> bool {
+        self.backends.iter().any(|backend| backend.blocked_by.contains(&pid))
+    }
+
+    pub fn waiting_count(&self) -> usize {
+        self.backends.iter().filter(|backend| !backend.blocked_by.is_empty()).count()
+    }
+
+    // Blockers that wait on nobody come first with the backends they hold up below them,
+    // as `(depth, pid)` pairs. Backends in a lock cycle are started from the lowest pid.
+    pub fn blocking_chains(&self) -> Vec<(usize, i32)> {
+        let mut blockers: Vec<&Backend> = self.backends
+            .iter()
+            .filter(|backend| self.is_blocking(backend.pid))
+            .collect();
+        blockers.sort_by_key(|backend| (!backend.blocked_by.is_empty(), backend.pid));
+
+        let mut chains = vec![];
+        let mut visited = HashSet::new();
+        for blocker in blockers {
+            self.push_chain(blocker.pid, 0, &mut visited, &mut chains);
+        }
+        chains
+    }
+
+    fn push_chain(&self, pid: i32, depth: usize, visited: &mut HashSet<i32>, chains: &mut Vec<(usize, i32)>) {
+        if !visited.insert(pid) {
+            return;
+        }
+        chains.push((depth, pid));
+        for backend in self.backends.iter().filter(|backend| backend.blocked_by.contains(&pid)) {
+            self.push_chain(backend.pid, depth + 1, visited, chains);
+        }
+    }
+}
+
+pub enum ActivityEvent {
+    Connected,
+    Snapshot(Result<ActivitySnapshot, String>),
+    Signalled { pid: i32, action: BackendAction, result: Result<bool, String> },
+    // The worker stopped, the connection can no longer be used
+    Closed(String),
+}
+
+enum ActivityRequest {
+    Refresh,
+    Signal(i32, BackendAction),
+}
+
+// Refreshes and signals run in order on a worker owning the tunnel and the client,
+// dropping the monitor stops both
+pub struct ActivityMonitor {
+    worker: Worker<ActivityRequest, ActivityEvent>,
+}
+
+impl ActivityMonitor {
+    pub fn open(args: ConnectionArgs) -> ActivityMonitor {
+        let worker = Worker::spawn(
+            "activity",
+            args,
+            |args| Ok((connect_postgres(args)?, ActivityEvent::Connected)),
+            |(client, _tunnel), args, request| match request {
+                ActivityRequest::Refresh => ActivityEvent::Snapshot(read_snapshot(client)),
+                ActivityRequest::Signal(pid, action) => {
+                    event!(Level::INFO, "sending {} to backend {} on {}", action.function(), pid, args.instance);
+                    let result = signal_backend(client, pid, action);
+                    ActivityEvent::Signalled { pid, action, result }
+                },
+            },
+            ActivityEvent::Closed,
+        );
+        ActivityMonitor { worker }
+    }
+
+    pub fn refresh(&self) -> bool {
+        self.worker.send(ActivityRequest::Refresh)
+    }
+
+    pub fn signal(&self, pid: i32, action: BackendAction) -> bool {
+        self.worker.send(ActivityRequest::Signal(pid, action))
+    }
+
+    pub fn try_event(&self) -> Option<ActivityEvent> {
+        self.worker.try_event()
+    }
+}
+
+fn read_snapshot(client: &mut postgres::Client) -> Result<ActivitySnapshot, String> {
+    let started = Instant::now();
+    let mut backends: Vec<Backend> = postgres_rows(client, ACTIVITY_SQL, &[])?
+        .into_iter()
+        .filter_map(|row| {
+            let mut values = row.into_iter();
+            let mut next = || values.next().flatten();
+            Some(Backend {
+                pid: next()?.parse().ok()?,
+                user: next(),
+                database: next(),
+                application: next().filter(|application| !application.is_empty()),
+                client: next(),
+                backend_type: next(),
+                state: next(),
+                wait_event: next(),
+                query_age: next().and_then(|seconds| parse_age(&seconds)),
+                transaction_age: next().and_then(|seconds| parse_age(&seconds)),
+                blocked_by: next()
+                    .map(|pids| pids.split(',').filter_map(|pid| pid.parse().ok()).collect())
+                    .unwrap_or_default(),
+                query: next().filter(|query| !query.is_empty()),
+                locks_held: 0,
+                waiting_for: None,
+            })
+        })
+        .collect();
+
+    for row in postgres_rows(client, LOCKS_SQL, &[])? {
+        let text = |index: usize| row.get(index).cloned().flatten().unwrap_or_default();
+        let Ok(pid) = text(0).parse::<i32>() else {
+            continue;
+        };
+        let Some(backend) = backends.iter_mut().find(|backend| backend.pid == pid) else {
+            continue;
+        };
+        if text(1) == "true" {
+            backend.locks_held += 1;
+        } else if backend.waiting_for.is_none() {
+            backend.waiting_for = Some(format!("{} on {} {}", text(2), text(3), text(4)).trim_end().to_string());
+        }
+    }
+
+    Ok(ActivitySnapshot {
+        backends,
+        taken_at: Instant::now(),
+        elapsed: started.elapsed(),
+    })
+}
+
+// Ages are read against the clock, a statement that started after it is shown as just started
+fn parse_age(seconds: &str) -> Option<Duration> {
+    seconds.parse::<f64>().ok().map(|seconds| Duration::from_secs_f64(seconds.max(0.0)))
+}
+
+// `false` means the backend was already gone or could not be signalled
+fn signal_backend(client: &mut postgres::Client, pid: i32, action: BackendAction) -> Result<bool, String> {
+    client
+        .query_one(&format!("SELECT {}($1)", action.function()), &[&pid])
+        .map(|row| row.get::<_, bool>(0))
+        .map_err(|err| postgres_error(&err))
+}
@@ -0,0 +1,196 @@
+use std::{
+    fs::{self, OpenOptions, Permissions},
+    io::{self, BufRead, BufReader, Write},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    path::PathBuf,
+    time::Duration,
+};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+use super::tsh::ConnectionArgs;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Connect,
+    Tunnel,
+    DbLogin,
+    DbLogout,
+    AppLogin,
+    KubeLogin,
+    AccessRequestCreate,
+    AccessRequestReview,
+    AccessRequestAssume,
+    SessionExpired,
+    BackendCancel,
+    BackendTerminate,
+}
+
+impl AuditAction {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            AuditAction::Connect => "connect",
+            AuditAction::Tunnel => "tunnel",
+            AuditAction::DbLogin => "db_login",
+            AuditAction::DbLogout => "db_logout",
+            AuditAction::AppLogin => "app_login",
+            AuditAction::KubeLogin => "kube_login",
+            AuditAction::AccessRequestCreate => "access_request_create",
+            AuditAction::AccessRequestReview => "access_request_review",
+            AuditAction::AccessRequestAssume => "access_request_assume",
+            AuditAction::SessionExpired => "session_expired",
+            AuditAction::BackendCancel => "backend_cancel",
+            AuditAction::BackendTerminate => "backend_terminate",
+        }
+    }
+}
+
+// One line of the local JSONL audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: String,
+    #[serde(default)]
+    pub teleport_user: String,
+    #[serde(default)]
+    pub cluster: String,
+    pub action: AuditAction,
+    #[serde(default)]
+    pub instance: String,
+    #[serde(default)]
+    pub db_user: String,
+    #[serde(default)]
+    pub db_name: String,
+    #[serde(default)]
+    pub method: String,
+    pub exit_status: Option<i32>,
+    pub duration_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recording: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(action: AuditAction, instance: &str) -> AuditEntry {
+        AuditEntry {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            teleport_user: String::new(),
+            cluster: String::new(),
+            action,
+            instance: instance.to_string(),
+            db_user: String::new(),
+            db_name: String::new(),
+            method: String::new(),
+            exit_status: None,
+            duration_ms: None,
+            reason: None,
+            recording: None,
+        }
+    }
+
+    pub fn for_connection(action: AuditAction, args: &ConnectionArgs, method: &str) -> AuditEntry {
+        let mut entry = AuditEntry::new(action, &args.instance);
+        entry.db_user = args.db_user.clone();
+        entry.db_name = args.db_name.clone();
+        entry.method = method.to_string();
+        entry
+    }
+
+    pub fn with_duration(mut self, duration: Duration) -> AuditEntry {
+        self.duration_ms = Some(duration.as_millis() as u64);
+        self
+    }
+
+    // Case insensitive match against every text field
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.trim().to_lowercase();
+        if filter.is_empty() {
+            return true;
+        }
+        [
+            self.timestamp.as_str(),
+            self.teleport_user.as_str(),
+            self.cluster.as_str(),
+            self.action.to_string(),
+            self.instance.as_str(),
+            self.db_user.as_str(),
+            self.db_name.as_str(),
+            self.method.as_str(),
+            self.reason.as_deref().unwrap_or_default(),
+        ]
+        .iter()
+        .any(|field| field.to_lowercase().contains(&filter))
+    }
+
+    pub fn exit_status_label(&self) -> String {
+        self.exit_status
+            .map(|code| code.to_string())
+            .unwrap_or("-".to_string())
+    }
+
+    pub fn duration_label(&self) -> String {
+        match self.duration_ms {
+            Some(ms) if ms >= 60_000 => format!("{}m{:02}s", ms / 60_000, (ms % 60_000) / 1000),
+            Some(ms) => format!("{:.1}s", ms as f64 / 1000.0),
+            None => "-".to_string(),
+        }
+    }
+}
+
+pub fn audit_log_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("kith").join("audit.jsonl"))
+}
+
+// Failures are logged rather than surfaced, the audit trail never blocks a connection
+pub fn record(entry: &AuditEntry) {
+    if let Err(err) = append(entry) {
+        event!(Level::ERROR, "failed to append to the audit log: {}", err);
+    }
+}
+
+// Oldest first, lines that fail to parse are skipped
+pub fn read_entries() -> io::Result<Vec<AuditEntry>> {
+    let path = audit_log_path()
+        .ok_or_else(|| io::Error::other("no data directory available"))?;
+
+    let file = match fs::File::open(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+
+    let mut entries = vec![];
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<AuditEntry>(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(err) => event!(Level::ERROR, "skipping audit log line {}: {}", index + 1, err),
+        }
+    }
+    Ok(entries)
+}
+
+fn append(entry: &AuditEntry) -> io::Result<()> {
+    let path = audit_log_path()
+        .ok_or_else(|| io::Error::other("no data directory available"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+
+    // Only the user may read the log, one created with looser permissions is tightened too
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .mode(0o600)
+        .open(path)?;
+    file.set_permissions(Permissions::from_mode(0o600))?;
+    file.write_all(line.as_bytes())
+}
@@ -0,0 +1,137 @@
+use std::{
+    env,
+    io::{self, stdout, Write},
+    process::{Command, Stdio},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use tracing::{event, Level};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClipboardBackend {
+    Osc52,
+    WlCopy,
+    Xclip,
+    Pbcopy,
+}
+
+impl ClipboardBackend {
+    pub fn from_name(name: &str) -> Option<ClipboardBackend> {
+        match name.to_lowercase().as_str() {
+            "osc52" => Some(ClipboardBackend::Osc52),
+            "wl-copy" => Some(ClipboardBackend::WlCopy),
+            "xclip" => Some(ClipboardBackend::Xclip),
+            "pbcopy" => Some(ClipboardBackend::Pbcopy),
+            _ => None,
+        }
+    }
+
+    pub fn to_string(&self) -> &str {
+        match self {
+            ClipboardBackend::Osc52 => "osc52",
+            ClipboardBackend::WlCopy => "wl-copy",
+            ClipboardBackend::Xclip => "xclip",
+            ClipboardBackend::Pbcopy => "pbcopy",
+        }
+    }
+
+    // Remote sessions can only reach the local clipboard through the terminal,
+    // otherwise prefer the native tool of the current display server.
+    pub fn detect() -> ClipboardBackend {
+        let is_remote = env::var("SSH_TTY").is_ok() || env::var("SSH_CONNECTION").is_ok();
+        if is_remote {
+            return ClipboardBackend::Osc52;
+        }
+
+        if cfg!(target_os = "macos") {
+            return ClipboardBackend::Pbcopy;
+        }
+        if env::var("WAYLAND_DISPLAY").is_ok() && command_exists("wl-copy") {
+            return ClipboardBackend::WlCopy;
+        }
+        if env::var("DISPLAY").is_ok() && command_exists("xclip") {
+            return ClipboardBackend::Xclip;
+        }
+        ClipboardBackend::Osc52
+    }
+
+    fn command(&self) -> Option<Command> {
+        match self {
+            ClipboardBackend::Osc52 => None,
+            ClipboardBackend::WlCopy => Some(Command::new("wl-copy")),
+            ClipboardBackend::Xclip => {
+                let mut command = Command::new("xclip");
+                command.args(["-selection", "clipboard"]);
+                Some(command)
+            },
+            ClipboardBackend::Pbcopy => Some(Command::new("pbcopy")),
+        }
+    }
+}
+
+pub struct Clipboard {
+    pub backend: ClipboardBackend,
+}
+
+impl Clipboard {
+    pub fn new(backend_name: &str) -> Clipboard {
+        let backend = match ClipboardBackend::from_name(backend_name) {
+            Some(backend) => backend,
+            None => ClipboardBackend::detect(),
+        };
+        event!(Level::DEBUG, "using clipboard backend: {}", backend.to_string());
+
+        Clipboard { backend }
+    }
+
+    // Native tools fall back to OSC 52 when they fail to run
+    pub fn copy(&self, text: &str) -> io::Result<()> {
+        event!(Level::DEBUG, "copying {} bytes to the clipboard", text.len());
+
+        if let Some(command) = self.backend.command() {
+            match copy_with_command(command, text) {
+                Ok(()) => return Ok(()),
+                Err(err) => {
+                    event!(Level::ERROR, "{} failed, falling back to osc52: {}", self.backend.to_string(), err);
+                },
+            }
+        }
+        copy_with_osc52(text)
+    }
+}
+
+fn copy_with_command(mut command: Command, text: &str) -> io::Result<()> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("exited with status {}", status)));
+    }
+    Ok(())
+}
+
+// Copies through the terminal with an OSC 52 escape sequence,
+// which also reaches the local clipboard over SSH sessions.
+fn copy_with_osc52(text: &str) -> io::Result<()> {
+    let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+
+    let mut out = stdout();
+    out.write_all(sequence.as_bytes())?;
+    out.flush()
+}
+
+fn command_exists(name: &str) -> bool {
+    Command::new("which")
+        .arg(name)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
@@ -0,0 +1,289 @@
+use std::{
+    fs,
+    io,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+use serde::Deserialize;
+use tracing::{event, Level};
+
+use super::{
+    audit::{self, AuditEntry},
+    tsh::{command_exit_code, ConnectionArgs, Tsh},
+};
+
+// Output of `tsh db config --format=json`
+#[derive(Debug, Clone, Deserialize)]
+pub struct DbConfig {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub ca: String,
+    pub cert: String,
+    pub key: String,
+    #[serde(default)]
+    pub user: String,
+    #[serde(default)]
+    pub database: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Libpq,
+    Env,
+    PgService,
+    MysqlOptionFile,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 4] = [
+        ExportFormat::Libpq,
+        ExportFormat::Env,
+        ExportFormat::PgService,
+        ExportFormat::MysqlOptionFile,
+    ];
+
+    pub fn to_string(self) -> &'static str {
+        match self {
+            ExportFormat::Libpq => "libpq connection string",
+            ExportFormat::Env => ".env snippet",
+            ExportFormat::PgService => "pg_service.conf entry",
+            ExportFormat::MysqlOptionFile => "MySQL option file group",
+        }
+    }
+
+    pub fn default_path(self, instance: &str) -> String {
+        match self {
+            ExportFormat::Libpq => format!("{}.conninfo", instance),
+            ExportFormat::Env => format!("{}.env", instance),
+            ExportFormat::PgService => "~/.pg_service.conf".to_string(),
+            ExportFormat::MysqlOptionFile => "~/.my.cnf".to_string(),
+        }
+    }
+
+    pub fn render(self, config: &DbConfig) -> String {
+        let quote = |value: &str| self.quote(value);
+        let (host, user, database) = (quote(&config.host), quote(&config.user), quote(&config.database));
+        let (ca, cert, key) = (quote(&config.ca), quote(&config.cert), quote(&config.key));
+        match self {
+            ExportFormat::Libpq => format!(
+                "host={} port={} user={} dbname={} sslmode=verify-full sslrootcert={} sslcert={} sslkey={}\n",
+                host, config.port, user, database, ca, cert, key,
+            ),
+            ExportFormat::Env => format!(
+                "PGHOST={}\nPGPORT={}\nPGUSER={}\nPGDATABASE={}\nPGSSLMODE=verify-full\nPGSSLROOTCERT={}\nPGSSLCERT={}\nPGSSLKEY={}\n",
+                host, config.port, user, database, ca, cert, key,
+            ),
+            ExportFormat::PgService => format!(
+                "[{}]\nhost={}\nport={}\nuser={}\ndbname={}\nsslmode=verify-full\nsslrootcert={}\nsslcert={}\nsslkey={}\n",
+                section_name(config), host, config.port, user, database, ca, cert, key,
+            ),
+            ExportFormat::MysqlOptionFile => format!(
+                "[{}]\nhost={}\nport={}\nuser={}\ndatabase={}\nssl-mode=VERIFY_IDENTITY\nssl-ca={}\nssl-cert={}\nssl-key={}\n",
+                section_name(config), host, config.port, user, database, ca, cert, key,
+            ),
+        }
+    }
+
+    // Plain values are written as is, the others are quoted the way each format reads them back
+    fn quote(self, value: &str) -> String {
+        let plain = !value.is_empty() && value
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || "-_./:@+,".contains(character));
+        if plain {
+            return value.to_string();
+        }
+        match self {
+            // libpq reads single quoted values with backslash escapes
+            ExportFormat::Libpq => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'")),
+            // Single quotes are literal for shells and dotenv loaders alike
+            ExportFormat::Env if !value.contains('\'') && !value.contains('\n') => format!("'{}'", value),
+            ExportFormat::Env => format!("\"{}\"", escape(value, "\\\"$`")),
+            // Service file values run to the end of the line and are never unquoted
+            ExportFormat::PgService => value.replace('\n', " "),
+            // Option files treat `#` as a comment outside of quotes
+            ExportFormat::MysqlOptionFile => format!("\"{}\"", escape(value, "\\\"")),
+        }
+    }
+
+    // What writing `content` to `path` would replace, if anything
+    pub fn conflict(self, path: &str, content: &str) -> Option<String> {
+        let path = expand_home(path);
+        let existing = fs::read_to_string(&path).ok()?;
+        match self {
+            ExportFormat::PgService | ExportFormat::MysqlOptionFile => {
+                let header = content.lines().next().unwrap_or_default().trim();
+                existing
+                    .lines()
+                    .any(|line| line.trim() == header)
+                    .then(|| format!("{} already has a {} group", path.display(), header))
+            },
+            ExportFormat::Libpq | ExportFormat::Env => Some(format!("{} already exists", path.display())),
+        }
+    }
+
+    // Service and option files hold many groups, only ours is replaced,
+    // anything already there is only replaced when `overwrite` is set
+    pub fn write(self, path: &str, content: &str, overwrite: bool) -> io::Result<PathBuf> {
+        if !overwrite {
+            if let Some(conflict) = self.conflict(path, content) {
+                return Err(io::Error::new(io::ErrorKind::AlreadyExists, conflict));
+            }
+        }
+        let path = expand_home(path);
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let content = match self {
+            ExportFormat::PgService | ExportFormat::MysqlOptionFile => {
+                let existing = fs::read_to_string(&path).unwrap_or_default();
+                merge_ini_section(&existing, content)
+            },
+            ExportFormat::Libpq | ExportFormat::Env => content.to_string(),
+        };
+
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+}
+
+// Backslash escapes the given characters and newlines
+fn escape(value: &str, special: &str) -> String {
+    let mut escaped = String::new();
+    for character in value.chars() {
+        match character {
+            '\n' => escaped.push_str("\\n"),
+            character if special.contains(character) => {
+                escaped.push('\\');
+                escaped.push(character);
+            },
+            character => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+fn section_name(config: &DbConfig) -> String {
+    config.name.replace(|character: char| !character.is_alphanumeric() && character != '-' && character != '_', "_")
+}
+
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    }
+}
+
+// Replaces the `[section]` group found at the top of `section` within `existing`
+fn merge_ini_section(existing: &str, section: &str) -> String {
+    let header = section.lines().next().unwrap_or_default().trim();
+
+    let mut merged = String::new();
+    let mut skipping = false;
+    for line in existing.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            skipping = trimmed == header;
+        }
+        if !skipping {
+            merged.push_str(line);
+            merged.push('\n');
+        }
+    }
+
+    if !merged.is_empty() && !merged.ends_with("\n\n") {
+        merged.push('\n');
+    }
+    merged.push_str(section);
+    merged
+}
+
+impl Tsh {
+    pub fn read_db_config(&self, args: &ConnectionArgs, login: &AuditEntry) -> Option<DbConfig> {
+        let logged_in = self.active_databases.contains(&args.instance);
+        Tsh::fetch_db_config(args, (!logged_in).then_some(login))
+    }
+
+    // Logs in for the certificate files, `login` carries who the login is audited for and
+    // the connection fields are filled in here
+    pub fn audited_db_login(args: &ConnectionArgs, login: &AuditEntry) -> bool {
+        event!(Level::INFO, "logging into database instance for its certificates: {}", args.instance);
+
+        let login_cmd = Command::new("tsh")
+            .args([
+                "db",
+                "login",
+                &format!("--db-user={}", args.db_user),
+                &format!("--db-name={}", args.db_name),
+                &args.instance,
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        let mut entry = login.clone();
+        entry.instance = args.instance.clone();
+        entry.db_user = args.db_user.clone();
+        entry.db_name = args.db_name.clone();
+        entry.exit_status = command_exit_code("teleport db login", login_cmd);
+        audit::record(&entry);
+        entry.exit_status == Some(0)
+    }
+
+    // Associated so it can run off the render thread, `login` is only needed when not logged in yet
+    pub fn fetch_db_config(args: &ConnectionArgs, login: Option<&AuditEntry>) -> Option<DbConfig> {
+        event!(Level::DEBUG, "reading database config for: {}", args.instance);
+
+        let db_user = format!("--db-user={}", args.db_user);
+        let db_name = format!("--db-name={}", args.db_name);
+
+        // The config points at the certificate files, which only exist once logged in
+        if let Some(login) = login {
+            if !Tsh::audited_db_login(args, login) {
+                return None;
+            }
+        }
+
+        let teleport_cmd = Command::new("tsh")
+            .args(["db", "config", "--format=json", &db_user, &db_name, &args.instance])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        match teleport_cmd {
+            Ok(output) if output.status.success() => {
+                match serde_json::from_slice::<DbConfig>(&output.stdout) {
+                    Ok(mut config) => {
+                        // Older tsh versions omit the user and database when not overridden
+                        if config.user.is_empty() {
+                            config.user = args.db_user.clone();
+                        }
+                        if config.database.is_empty() {
+                            config.database = args.db_name.clone();
+                        }
+                        Some(config)
+                    },
+                    Err(err) => {
+                        event!(Level::ERROR, "failed to deserialize teleport db config: {}", err);
+                        None
+                    }
+                }
+            },
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                event!(Level::ERROR, "teleport db config failed with status {}: {}", output.status, stderr);
+                None
+            },
+            Err(err) => {
+                event!(Level::ERROR, "failed to get output from teleport db config: {}", err);
+                None
+            },
+        }
+    }
+}
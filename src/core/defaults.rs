@@ -0,0 +1,68 @@
+use serde::Deserialize;
+
+use super::{selector::LabelSelector, tsh::Database};
+
+// Preferred connection values for instances matching a label selector
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConnectionDefault {
+    pub selector: LabelSelector,
+    pub db_user: Option<String>,
+    pub db_name: Option<String>,
+}
+
+// A resolved value along with the selector of the rule it came from
+#[derive(Debug, Clone)]
+pub struct AppliedDefault {
+    pub value: String,
+    pub selector: LabelSelector,
+}
+
+impl AppliedDefault {
+    pub fn hint(&self) -> String {
+        format!("{} from rule `{}`", self.value, self.selector.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedDefaults {
+    pub db_user: Option<AppliedDefault>,
+    pub db_name: Option<AppliedDefault>,
+}
+
+impl ConnectionDefault {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.db_user.is_none() && self.db_name.is_none() {
+            return Err("sets neither db_user nor db_name".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Each value comes from the first matching rule that sets it, a preferred user
+// is only kept when the instance actually allows it
+pub fn resolve_defaults(rules: &[ConnectionDefault], database: &Database) -> ResolvedDefaults {
+    let mut resolved = ResolvedDefaults::default();
+
+    for rule in rules.iter().filter(|rule| rule.selector.matches(&database.metadata.labels)) {
+        if resolved.db_user.is_none() {
+            if let Some(db_user) = &rule.db_user {
+                if database.users.allowed.contains(db_user) {
+                    resolved.db_user = Some(AppliedDefault {
+                        value: db_user.clone(),
+                        selector: rule.selector.clone(),
+                    });
+                }
+            }
+        }
+        if resolved.db_name.is_none() {
+            if let Some(db_name) = &rule.db_name {
+                resolved.db_name = Some(AppliedDefault {
+                    value: db_name.clone(),
+                    selector: rule.selector.clone(),
+                });
+            }
+        }
+    }
+
+    resolved
+}
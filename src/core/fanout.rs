@@ -0,0 +1,262 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use serde::Deserialize;
+use tracing::{event, Level};
+
+use super::{
+    defaults::{resolve_defaults, ConnectionDefault},
+    hooks::{run_hooks, Hook, HookContext, HookStage},
+    query::{DatabaseKind, QueryConnection, QueryEvent, QueryResult, MAX_ROWS},
+    session_limit::parse_duration,
+    tsh::{ConnectionArgs, Database},
+};
+
+const DEFAULT_CONCURRENCY: usize = 4;
+
+const DEFAULT_TIMEOUT: &str = "30s";
+
+// How often waiting workers look for a cancelled run
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+// Limits of a query run on several databases at once
+#[derive(Debug, Clone, Deserialize)]
+pub struct FanoutConfig {
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    #[serde(default = "default_timeout")]
+    pub timeout: String,
+}
+
+impl Default for FanoutConfig {
+    fn default() -> FanoutConfig {
+        FanoutConfig {
+            concurrency: DEFAULT_CONCURRENCY,
+            timeout: DEFAULT_TIMEOUT.to_string(),
+        }
+    }
+}
+
+impl FanoutConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.concurrency == 0 {
+            return Err("concurrency must be at least 1".to_string());
+        }
+        parse_duration(&self.timeout).map(|_| ())
+    }
+
+    pub fn timeout(&self) -> Duration {
+        parse_duration(&self.timeout).unwrap_or_else(|_| parse_duration(DEFAULT_TIMEOUT).unwrap_or_default())
+    }
+}
+
+fn default_concurrency() -> usize {
+    DEFAULT_CONCURRENCY
+}
+
+fn default_timeout() -> String {
+    DEFAULT_TIMEOUT.to_string()
+}
+
+pub struct FanoutTarget {
+    pub args: ConnectionArgs,
+    pub kind: DatabaseKind,
+    pub database: Database,
+}
+
+// Empty values fall back to the instance's default rules, then to its only allowed user
+pub fn resolve_target(
+    database: &Database,
+    db_user: &str,
+    db_name: &str,
+    defaults: &[ConnectionDefault],
+) -> Result<FanoutTarget, String> {
+    let Some(kind) = DatabaseKind::from_protocol(&database.spec.protocol) else {
+        return Err(format!("{} databases cannot be queried", database.spec.protocol));
+    };
+    let resolved = resolve_defaults(defaults, database);
+
+    let db_user = match (db_user.is_empty(), resolved.db_user) {
+        (false, _) => db_user.to_string(),
+        (true, Some(default)) => default.value,
+        (true, None) if database.users.allowed.len() == 1 => database.users.allowed[0].clone(),
+        (true, None) => return Err("no database user given and no default applies".to_string()),
+    };
+    if !database.users.allowed.contains(&db_user) {
+        return Err(format!("database user `{}` is not allowed", db_user));
+    }
+    let db_name = match (db_name.is_empty(), resolved.db_name) {
+        (true, Some(default)) => default.value,
+        _ => db_name.to_string(),
+    };
+
+    Ok(FanoutTarget {
+        args: ConnectionArgs {
+            instance: database.metadata.name.clone(),
+            db_user,
+            db_name,
+        },
+        kind,
+        database: database.clone(),
+    })
+}
+
+pub struct FanoutOutcome {
+    pub args: ConnectionArgs,
+    pub result: Result<QueryResult, String>,
+    pub elapsed: Duration,
+}
+
+pub enum FanoutEvent {
+    Started(String),
+    Finished(FanoutOutcome),
+}
+
+// Runs one statement on every target through its own tunnel, a few at a time,
+// dropping the run cancels whatever is still queued or running
+pub struct FanoutRun {
+    events: Receiver<FanoutEvent>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl FanoutRun {
+    pub fn start(
+        targets: Vec<FanoutTarget>,
+        sql: &str,
+        config: &FanoutConfig,
+        hooks: Vec<Hook>,
+    ) -> FanoutRun {
+        let (sender, events) = channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let workers = config.concurrency.min(targets.len());
+        let queue = Arc::new(Mutex::new(VecDeque::from(targets)));
+        let timeout = config.timeout();
+        let hooks = Arc::new(hooks);
+
+        for _ in 0..workers {
+            let (queue, sender, cancelled, hooks) = (queue.clone(), sender.clone(), cancelled.clone(), hooks.clone());
+            let sql = sql.to_string();
+            thread::spawn(move || {
+                while let Some(target) = next_target(&queue) {
+                    let outcome = run_target(&target, &sql, timeout, &cancelled, &hooks, &sender);
+                    if sender.send(FanoutEvent::Finished(outcome)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        FanoutRun { events, cancelled }
+    }
+
+    pub fn try_event(&self) -> Option<FanoutEvent> {
+        self.events.try_recv().ok()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for FanoutRun {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+fn next_target(queue: &Mutex<VecDeque<FanoutTarget>>) -> Option<FanoutTarget> {
+    queue.lock().ok()?.pop_front()
+}
+
+fn run_target(
+    target: &FanoutTarget,
+    sql: &str,
+    timeout: Duration,
+    cancelled: &AtomicBool,
+    hooks: &[Hook],
+    sender: &Sender<FanoutEvent>,
+) -> FanoutOutcome {
+    let started = Instant::now();
+    let mut context = HookContext {
+        args: &target.args,
+        database: Some(&target.database),
+        method: "fanout",
+        exit_status: None,
+    };
+
+    let result = if cancelled.load(Ordering::Relaxed) {
+        Err("cancelled".to_string())
+    } else {
+        let _ = sender.send(FanoutEvent::Started(target.args.instance.clone()));
+        match run_hooks(hooks, HookStage::Pre, &context) {
+            Ok(()) => {
+                let result = query_target(target, sql, MAX_ROWS, timeout, cancelled);
+                context.exit_status = Some(if result.is_ok() { 0 } else { 1 });
+                if let Err(err) = run_hooks(hooks, HookStage::Post, &context) {
+                    event!(Level::ERROR, "post-connect hook failed for {}: {}", target.args.instance, err);
+                }
+                result
+            },
+            Err(err) => Err(format!("pre-connect hook failed: {}", err)),
+        }
+    };
+
+    FanoutOutcome {
+        args: target.args.clone(),
+        result,
+        elapsed: started.elapsed(),
+    }
+}
+
+// The timeout covers the tunnel, the connection and the statement, a statement still
+// running when it expires is cancelled on the server
+pub fn query_target(
+    target: &FanoutTarget,
+    sql: &str,
+    max_rows: usize,
+    timeout: Duration,
+    cancelled: &AtomicBool,
+) -> Result<QueryResult, String> {
+    let deadline = Instant::now() + timeout;
+    let connection = QueryConnection::open(target.args.clone(), target.kind);
+
+    let cancel = match wait_for_event(&connection, deadline, cancelled)? {
+        QueryEvent::Connected(cancel) => cancel,
+        QueryEvent::Closed(err) => return Err(err),
+        _ => return Err("unexpected connection event".to_string()),
+    };
+    if !connection.execute_limited(sql, max_rows) {
+        return Err("Connection closed".to_string());
+    }
+    match wait_for_event(&connection, deadline, cancelled) {
+        Ok(QueryEvent::Finished(result)) => result,
+        Ok(QueryEvent::Closed(err)) => Err(err),
+        Ok(_) => Err("unexpected connection event".to_string()),
+        Err(err) => {
+            cancel.cancel();
+            Err(err)
+        },
+    }
+}
+
+fn wait_for_event(connection: &QueryConnection, deadline: Instant, cancelled: &AtomicBool) -> Result<QueryEvent, String> {
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err("cancelled".to_string());
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err("timed out".to_string());
+        }
+        if let Some(event) = connection.wait_event(remaining.min(CANCEL_CHECK_INTERVAL)) {
+            return Ok(event);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use std::str::FromStr;
+use ratatui::style::Color;
+use serde::Deserialize;
+
+use super::{selector::LabelSelector, tsh::Database};
+
+// Extra confirmation for instances matching a label selector, eg. `env=prod`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Guardrail {
+    pub selector: LabelSelector,
+    pub color: Option<String>,
+    #[serde(default)]
+    pub require_reason: bool,
+}
+
+impl Guardrail {
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.color {
+            Some(color) => Color::from_str(color)
+                .map(|_| ())
+                .map_err(|_| format!("unknown color `{}`", color)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+            .as_deref()
+            .and_then(|color| Color::from_str(color).ok())
+            .unwrap_or(Color::Red)
+    }
+}
+
+// The first matching guardrail wins, so list the strictest rules first
+pub fn find_guardrail<'a>(guardrails: &'a [Guardrail], database: &Database) -> Option<&'a Guardrail> {
+    guardrails
+        .iter()
+        .find(|guardrail| guardrail.selector.matches(&database.metadata.labels))
+}
@@ -0,0 +1,236 @@
+use std::{
+    sync::mpsc::{channel, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+use mysql::prelude::Queryable;
+use tracing::{event, Level};
+
+use super::{
+    hooks::{run_hooks, Hook, HookContext, HookStage},
+    query::{connect_client_within, Client, DatabaseKind},
+    schema::{mysql_rows, postgres_rows},
+    tsh::{ConnectionArgs, Database},
+    tunnel::{pick_free_port, Tunnel},
+};
+
+// TLS as seen by the database server, ie. between the Teleport agent and the database
+const POSTGRES_TLS_SQL: &str = "SELECT ssl::text, version, cipher, bits::text FROM pg_stat_ssl WHERE pid = pg_backend_pid()";
+
+const MYSQL_TLS_SQL: &str = "SHOW SESSION STATUS WHERE Variable_name IN ('Ssl_version', 'Ssl_cipher')";
+
+// A server that accepts the tunnel but never answers fails the check instead of hanging it
+const PROBE_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+const PROBE_STATEMENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum HealthStage {
+    Hook,
+    Tunnel,
+    Handshake,
+    Query,
+}
+
+impl HealthStage {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            HealthStage::Hook => "pre-connect hook",
+            HealthStage::Tunnel => "tunnel",
+            HealthStage::Handshake => "handshake",
+            HealthStage::Query => "SELECT 1",
+        }
+    }
+}
+
+// Outcome of one connection test, kept per instance until the next one
+#[derive(Clone)]
+pub struct HealthCheck {
+    pub args: ConnectionArgs,
+    pub protocol: String,
+    pub checked_at: String,
+    pub tunnel: Option<Duration>,
+    pub handshake: Option<Duration>,
+    pub round_trip: Option<Duration>,
+    pub server_version: Option<String>,
+    pub tls: Option<String>,
+    pub failure: Option<(HealthStage, String)>,
+    pub elapsed: Duration,
+}
+
+impl HealthCheck {
+    fn new(args: &ConnectionArgs, protocol: &str) -> HealthCheck {
+        HealthCheck {
+            args: args.clone(),
+            protocol: protocol.to_string(),
+            checked_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            tunnel: None,
+            handshake: None,
+            round_trip: None,
+            server_version: None,
+            tls: None,
+            failure: None,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    // Protocols kith cannot speak are only checked up to the tunnel
+    pub fn is_partial(&self) -> bool {
+        self.is_healthy() && DatabaseKind::from_protocol(&self.protocol).is_none()
+    }
+
+    pub fn summary(&self) -> String {
+        match &self.failure {
+            Some((stage, err)) => format!("{} failed: {}", stage.to_string(), err),
+            None if self.is_partial() => format!("Tunnel up in {}", format_latency(self.tunnel)),
+            None => format!("Healthy, SELECT 1 in {}", format_latency(self.round_trip)),
+        }
+    }
+
+    // Label/value rows shared by the result popup and the copied report
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            ("Target", format!("{}@{}/{}", self.args.db_user, self.args.instance, self.args.db_name)),
+            ("Checked at", self.checked_at.clone()),
+            ("Status", self.summary()),
+            ("Tunnel", format_latency(self.tunnel)),
+        ];
+        if DatabaseKind::from_protocol(&self.protocol).is_some() {
+            fields.push(("Handshake", format_latency(self.handshake)));
+            fields.push(("SELECT 1", format_latency(self.round_trip)));
+        } else {
+            fields.push(("Handshake", format!("not supported for {}", self.protocol)));
+        }
+        fields.push(("Server version", self.server_version.clone().unwrap_or("-".to_string())));
+        fields.push(("Server TLS", self.tls.clone().unwrap_or("-".to_string())));
+        fields.push(("Total", format_latency(Some(self.elapsed))));
+        fields
+    }
+
+    pub fn report(&self) -> String {
+        self.fields()
+            .into_iter()
+            .map(|(label, value)| format!("{}: {}\n", label, value))
+            .collect()
+    }
+}
+
+pub fn format_latency(latency: Option<Duration>) -> String {
+    match latency {
+        Some(latency) => format!("{:.1} ms", latency.as_secs_f64() * 1000.0),
+        None => "-".to_string(),
+    }
+}
+
+// Runs one test in the background, dropping the run only discards its result
+pub struct HealthCheckRun {
+    result: Receiver<HealthCheck>,
+}
+
+impl HealthCheckRun {
+    pub fn start(args: ConnectionArgs, database: Database, hooks: Vec<Hook>) -> HealthCheckRun {
+        let (sender, result) = channel();
+        thread::spawn(move || {
+            let _ = sender.send(check(&args, &database, &hooks));
+        });
+        HealthCheckRun { result }
+    }
+
+    pub fn try_result(&self) -> Option<HealthCheck> {
+        self.result.try_recv().ok()
+    }
+}
+
+fn check(args: &ConnectionArgs, database: &Database, hooks: &[Hook]) -> HealthCheck {
+    let started = Instant::now();
+    let mut health = HealthCheck::new(args, &database.spec.protocol);
+    let mut context = HookContext {
+        args,
+        database: Some(database),
+        method: "health_check",
+        exit_status: None,
+    };
+
+    match run_hooks(hooks, HookStage::Pre, &context) {
+        Ok(()) => {
+            if let Err(failure) = probe(&mut health) {
+                event!(Level::ERROR, "connection test of {} failed at {}: {}", args.instance, failure.0.to_string(), failure.1);
+                health.failure = Some(failure);
+            }
+            context.exit_status = Some(if health.is_healthy() { 0 } else { 1 });
+            if let Err(err) = run_hooks(hooks, HookStage::Post, &context) {
+                event!(Level::ERROR, "post-connect hook failed for {}: {}", args.instance, err);
+            }
+        },
+        Err(err) => health.failure = Some((HealthStage::Hook, err)),
+    }
+
+    health.elapsed = started.elapsed();
+    health
+}
+
+fn probe(health: &mut HealthCheck) -> Result<(), (HealthStage, String)> {
+    let args = health.args.clone();
+    let started = Instant::now();
+    let port = pick_free_port().map_err(|err| (HealthStage::Tunnel, format!("no free local port: {}", err)))?;
+    // The tunnel is kept until the probe is done
+    let _tunnel = Tunnel::start(&args, port).map_err(|err| (HealthStage::Tunnel, err.to_string()))?;
+    health.tunnel = Some(started.elapsed());
+
+    let Some(kind) = DatabaseKind::from_protocol(&health.protocol) else {
+        return Ok(());
+    };
+    let started = Instant::now();
+    let mut client = connect_client_within(&args, kind, port, PROBE_CONNECT_TIMEOUT, Some(PROBE_STATEMENT_TIMEOUT))
+        .map_err(|err| (HealthStage::Handshake, err))?;
+    health.handshake = Some(started.elapsed());
+
+    let started = Instant::now();
+    match &mut client {
+        Client::Postgres(client) => client.simple_query("SELECT 1").map(|_| ()).map_err(|err| err.to_string()),
+        Client::MySql(conn) => conn.query_drop("SELECT 1").map_err(|err| err.to_string()),
+    }.map_err(|err| (HealthStage::Query, err))?;
+    health.round_trip = Some(started.elapsed());
+
+    // Details are best effort, the roles used for testing may not be allowed to read them
+    match &mut client {
+        Client::Postgres(client) => {
+            health.server_version = postgres_rows(client, "SHOW server_version", &[])
+                .ok()
+                .and_then(|rows| rows.into_iter().next())
+                .and_then(|row| row.into_iter().next().flatten());
+            health.tls = postgres_rows(client, POSTGRES_TLS_SQL, &[])
+                .ok()
+                .and_then(|rows| rows.into_iter().next())
+                .map(|row| {
+                    let text = |index: usize| row.get(index).cloned().flatten().unwrap_or_default();
+                    match text(0).as_str() {
+                        "true" => format!("{}, {}, {} bits", text(1), text(2), text(3)),
+                        _ => "not encrypted".to_string(),
+                    }
+                });
+        },
+        Client::MySql(conn) => {
+            health.server_version = mysql_rows(conn, "SELECT VERSION()", vec![])
+                .ok()
+                .and_then(|rows| rows.into_iter().next())
+                .and_then(|row| row.into_iter().next().flatten());
+            health.tls = mysql_rows(conn, MYSQL_TLS_SQL, vec![]).ok().map(|rows| {
+                let value = |name: &str| rows
+                    .iter()
+                    .find(|row| row.first().cloned().flatten().as_deref() == Some(name))
+                    .and_then(|row| row.get(1).cloned().flatten())
+                    .unwrap_or_default();
+                match (value("Ssl_version"), value("Ssl_cipher")) {
+                    (_, cipher) if cipher.is_empty() => "not encrypted".to_string(),
+                    (version, cipher) => format!("{}, {}", version, cipher),
+                }
+            });
+        },
+    }
+    Ok(())
+}
@@ -0,0 +1,227 @@
+use std::{
+    io::{Read, Write},
+    process::{Command, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{event, Level};
+
+use super::{
+    selector::LabelSelector,
+    session_limit::parse_duration,
+    tsh::{ConnectionArgs, Database},
+};
+
+const DEFAULT_TIMEOUT: &str = "30s";
+// How often a running hook is checked for having exited
+const WAIT_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookStage {
+    Pre,
+    Post,
+}
+
+impl HookStage {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            HookStage::Pre => "pre",
+            HookStage::Post => "post",
+        }
+    }
+}
+
+// A shell command run around database connections, eg. to post a notification or log a ticket ID,
+// it runs in its own process so it cannot change the connection's environment
+#[derive(Debug, Clone, Deserialize)]
+pub struct Hook {
+    pub name: String,
+    pub stage: HookStage,
+    pub command: String,
+    pub selector: Option<LabelSelector>,
+    #[serde(default = "default_timeout")]
+    pub timeout: String,
+}
+
+// What a hook is told about the connection, passed as `KITH_*` variables and as JSON on stdin
+pub struct HookContext<'a> {
+    pub args: &'a ConnectionArgs,
+    pub database: Option<&'a Database>,
+    pub method: &'a str,
+    pub exit_status: Option<i32>,
+}
+
+impl Hook {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.command.trim().is_empty() {
+            return Err("command is empty".to_string());
+        }
+        parse_duration(&self.timeout).map(|_| ())
+    }
+
+    fn timeout(&self) -> Duration {
+        parse_duration(&self.timeout).unwrap_or_else(|_| parse_duration(DEFAULT_TIMEOUT).unwrap_or_default())
+    }
+
+    fn applies_to(&self, context: &HookContext) -> bool {
+        match (&self.selector, context.database) {
+            (Some(selector), Some(database)) => selector.matches(&database.metadata.labels),
+            (Some(_), None) => false,
+            (None, _) => true,
+        }
+    }
+
+    fn run(&self, stage: HookStage, context: &HookContext) -> Result<(), String> {
+        event!(Level::INFO, "running {}-connect hook: {}", stage.to_string(), self.name);
+
+        let mut child = Command::new("sh")
+            .args(["-c", &self.command])
+            .envs(environment(stage, context))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("hook `{}` failed to start: {}", self.name, err))?;
+
+        // Fed from its own thread so a hook writing a lot before reading stdin can't deadlock,
+        // hooks that ignore stdin may exit before reading it, a broken pipe is expected then
+        let writer = child.stdin.take().map(|mut stdin| {
+            let payload = payload(stage, context).to_string();
+            let name = self.name.clone();
+            thread::spawn(move || {
+                if let Err(err) = stdin.write_all(payload.as_bytes()) {
+                    event!(Level::DEBUG, "hook {} did not read its stdin: {}", name, err);
+                }
+            })
+        });
+
+        let stdout = child.stdout.take().map(read_pipe);
+        let stderr = child.stderr.take().map(read_pipe);
+
+        // A hung hook is killed, its reader threads are left to end with whatever it spawned
+        let deadline = Instant::now() + self.timeout();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    event!(Level::ERROR, "hook {} killed after {}", self.name, self.timeout.trim());
+                    return Err(format!("hook `{}` timed out after {}", self.name, self.timeout.trim()));
+                },
+                Ok(None) => thread::sleep(WAIT_INTERVAL),
+                Err(err) => return Err(format!("hook `{}` failed: {}", self.name, err)),
+            }
+        };
+        if let Some(writer) = writer {
+            let _ = writer.join();
+        }
+        let stdout = stdout.and_then(|reader| reader.join().ok()).unwrap_or_default();
+        let stderr = stderr.and_then(|reader| reader.join().ok()).unwrap_or_default();
+
+        if !stdout.trim().is_empty() {
+            event!(Level::DEBUG, "hook {} output: {}", self.name, stdout.trim());
+        }
+
+        if status.success() {
+            return Ok(());
+        }
+
+        event!(Level::ERROR, "hook {} failed with status {}: {}", self.name, status, stderr);
+        match stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+            Some(line) => Err(format!("hook `{}` failed: {}", self.name, line.trim())),
+            None => Err(format!("hook `{}` exited with {}", self.name, status)),
+        }
+    }
+}
+
+fn default_timeout() -> String {
+    DEFAULT_TIMEOUT.to_string()
+}
+
+fn read_pipe<R: Read + Send + 'static>(mut pipe: R) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = pipe.read_to_end(&mut output);
+        String::from_utf8_lossy(&output).into_owned()
+    })
+}
+
+// Pre-connect hooks stop at the first failure so the connection can be aborted,
+// post-connect hooks all run and only the first failure is reported
+pub fn run_hooks(hooks: &[Hook], stage: HookStage, context: &HookContext) -> Result<(), String> {
+    let mut first_error = None;
+    for hook in hooks.iter().filter(|hook| hook.stage == stage && hook.applies_to(context)) {
+        if let Err(err) = hook.run(stage, context) {
+            if stage == HookStage::Pre {
+                return Err(err);
+            }
+            first_error.get_or_insert(err);
+        }
+    }
+    match first_error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+fn environment(stage: HookStage, context: &HookContext) -> Vec<(String, String)> {
+    let mut variables = vec![
+        ("KITH_HOOK_STAGE".to_string(), stage.to_string().to_string()),
+        ("KITH_CONNECT_METHOD".to_string(), context.method.to_string()),
+        ("KITH_DB_INSTANCE".to_string(), context.args.instance.clone()),
+        ("KITH_DB_USER".to_string(), context.args.db_user.clone()),
+        ("KITH_DB_NAME".to_string(), context.args.db_name.clone()),
+    ];
+
+    if let Some(database) = context.database {
+        variables.push(("KITH_DB_PROTOCOL".to_string(), database.spec.protocol.clone()));
+        variables.push(("KITH_DB_URI".to_string(), database.spec.uri.clone()));
+        for (key, value) in &database.metadata.labels {
+            variables.push((format!("KITH_LABEL_{}", env_key(key)), value.clone()));
+        }
+    }
+
+    if let Some(code) = context.exit_status {
+        variables.push(("KITH_EXIT_STATUS".to_string(), code.to_string()));
+    }
+
+    variables
+}
+
+fn payload(stage: HookStage, context: &HookContext) -> serde_json::Value {
+    let database = context.database.map(|database| json!({
+        "name": database.metadata.name,
+        "description": database.metadata.description,
+        "protocol": database.spec.protocol,
+        "uri": database.spec.uri,
+        "labels": database.metadata.labels,
+        "allowed_users": database.users.allowed,
+    }));
+
+    json!({
+        "stage": stage.to_string(),
+        "method": context.method,
+        "connection": {
+            "instance": context.args.instance,
+            "db_user": context.args.db_user,
+            "db_name": context.args.db_name,
+        },
+        "database": database,
+        "exit_status": context.exit_status,
+    })
+}
+
+// `teleport.dev/origin` becomes `TELEPORT_DEV_ORIGIN`
+fn env_key(label: &str) -> String {
+    label
+        .chars()
+        .map(|character| match character {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => character.to_ascii_uppercase(),
+            _ => '_',
+        })
+        .collect()
+}
@@ -0,0 +1,522 @@
+use std::{
+    fs,
+    io,
+    path::PathBuf,
+};
+use quick_xml::{
+    events::{BytesEnd, BytesStart, BytesText, Event},
+    Reader,
+    Writer,
+};
+use serde_json::{json, Map, Value};
+use tracing::{event, Level};
+
+use super::{
+    audit::AuditEntry,
+    db_config::expand_home,
+    tsh::{ConnectionArgs, Database, Tsh},
+};
+
+// Entries written by kith are recognisable by this prefix so re-exports replace them
+const NAME_PREFIX: &str = "kith";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IdeTarget {
+    JetBrains,
+    DBeaver,
+    SqlTools,
+}
+
+impl IdeTarget {
+    pub const ALL: [IdeTarget; 3] = [
+        IdeTarget::JetBrains,
+        IdeTarget::DBeaver,
+        IdeTarget::SqlTools,
+    ];
+
+    pub fn to_string(self) -> &'static str {
+        match self {
+            IdeTarget::JetBrains => "JetBrains (dataSources.xml)",
+            IdeTarget::DBeaver => "DBeaver (data-sources.json)",
+            IdeTarget::SqlTools => "VS Code SQLTools (settings.json)",
+        }
+    }
+
+    pub fn default_path(self) -> String {
+        match self {
+            IdeTarget::JetBrains => ".idea/dataSources.xml".to_string(),
+            IdeTarget::DBeaver => {
+                if cfg!(target_os = "macos") {
+                    "~/Library/DBeaverData/workspace6/General/.dbeaver/data-sources.json".to_string()
+                } else {
+                    "~/.local/share/DBeaverData/workspace6/General/.dbeaver/data-sources.json".to_string()
+                }
+            },
+            IdeTarget::SqlTools => ".vscode/settings.json".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Driver {
+    Postgres,
+    Mysql,
+}
+
+impl Driver {
+    fn from_protocol(protocol: &str) -> Option<Driver> {
+        match protocol {
+            "postgres" | "cockroachdb" => Some(Driver::Postgres),
+            "mysql" => Some(Driver::Mysql),
+            _ => None,
+        }
+    }
+
+    fn jdbc_scheme(self) -> &'static str {
+        match self {
+            Driver::Postgres => "postgresql",
+            Driver::Mysql => "mysql",
+        }
+    }
+}
+
+struct SslPaths {
+    ca: String,
+    cert: String,
+    key: String,
+}
+
+pub struct DataSource {
+    name: String,
+    instance: String,
+    driver: Driver,
+    host: String,
+    port: u16,
+    user: String,
+    database: String,
+    ssl: SslPaths,
+}
+
+pub struct ExportSummary {
+    pub path: PathBuf,
+    pub exported: usize,
+    pub skipped: usize,
+}
+
+impl DataSource {
+    fn jdbc_url(&self) -> String {
+        let mut url = format!(
+            "jdbc:{}://{}:{}/{}",
+            self.driver.jdbc_scheme(), self.host, self.port, self.database,
+        );
+        match self.driver {
+            Driver::Postgres => url.push_str(&format!(
+                "?sslmode=verify-full&sslrootcert={}&sslcert={}&sslkey={}",
+                self.ssl.ca, self.ssl.cert, self.ssl.key,
+            )),
+            Driver::Mysql => url.push_str(&format!(
+                "?sslMode=VERIFY_IDENTITY&sslCa={}&sslCert={}&sslKey={}",
+                self.ssl.ca, self.ssl.cert, self.ssl.key,
+            )),
+        }
+        url
+    }
+
+    // Stable identifier derived from the instance so re-exports update in place
+    fn uuid(&self) -> String {
+        let seed = format!("{}-{}", NAME_PREFIX, self.instance);
+        let first = seed.bytes().fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+        let second = seed.bytes().rev().fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+        let hex = format!("{:016x}{:016x}", first, second);
+        format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+    }
+}
+
+impl Tsh {
+    // Resolves connection details for every exportable database from its certificate, using the first allowed user
+    pub fn build_data_sources(databases: &[Database], active: &[String], login: &AuditEntry) -> (Vec<DataSource>, usize) {
+        let mut sources = vec![];
+        let mut skipped = 0;
+
+        for database in databases {
+            let instance = database.metadata.name.clone();
+            let (Some(driver), Some(user)) = (
+                Driver::from_protocol(&database.spec.protocol),
+                database.users.allowed.first(),
+            ) else {
+                skipped += 1;
+                continue;
+            };
+
+            let args = ConnectionArgs {
+                instance: instance.clone(),
+                db_user: user.clone(),
+                db_name: String::new(),
+            };
+            let Some(config) = Tsh::fetch_db_config(&args, (!active.contains(&instance)).then_some(login)) else {
+                skipped += 1;
+                continue;
+            };
+            sources.push(DataSource {
+                name: format!("{}: {}", NAME_PREFIX, instance),
+                instance,
+                driver,
+                host: config.host,
+                port: config.port,
+                user: user.clone(),
+                database: config.database,
+                ssl: SslPaths {
+                    ca: config.ca,
+                    cert: config.cert,
+                    key: config.key,
+                },
+            });
+        }
+
+        (sources, skipped)
+    }
+}
+
+pub fn export(target: IdeTarget, path: &str, sources: &[DataSource], skipped: usize) -> io::Result<ExportSummary> {
+    let path = expand_home(path);
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+
+    let existing = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+
+    let content = match target {
+        IdeTarget::JetBrains => merge_jetbrains(&existing, sources)?,
+        IdeTarget::DBeaver => merge_dbeaver(&existing, sources)?,
+        IdeTarget::SqlTools => merge_sqltools(&existing, sources)?,
+    };
+
+    fs::write(&path, content)?;
+    event!(Level::INFO, "exported {} data sources to {}", sources.len(), path.display());
+
+    Ok(ExportSummary {
+        path,
+        exported: sources.len(),
+        skipped,
+    })
+}
+
+// JetBrains only reads data sources from this component of the project file
+const DATA_SOURCE_MANAGER: &str = "DataSourceManagerImpl";
+
+const JETBRAINS_TEMPLATE: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+    "<project version=\"4\">\n",
+    "  <component name=\"DataSourceManagerImpl\" format=\"xml\" multifile-model=\"true\">\n",
+    "  </component>\n",
+    "</project>\n",
+);
+
+fn xml_error(err: impl std::fmt::Display) -> io::Error {
+    io::Error::other(format!("failed to parse dataSources.xml: {}", err))
+}
+
+fn attribute(element: &BytesStart, name: &str) -> io::Result<Option<String>> {
+    match element.try_get_attribute(name).map_err(xml_error)? {
+        Some(attribute) => Ok(Some(attribute.unescape_value().map_err(xml_error)?.into_owned())),
+        None => Ok(None),
+    }
+}
+
+// Line break and indentation of the last line of whitespace, or the default for an element at depth
+fn line_indent(whitespace: &str, depth: usize) -> String {
+    match whitespace.rfind('\n') {
+        Some(index) => whitespace[index..].to_string(),
+        None => format!("\n{}", "  ".repeat(depth)),
+    }
+}
+
+// Writes each data source on its own lines, indent is the line break and spaces before the opening tag
+fn write_data_sources(writer: &mut Writer<Vec<u8>>, sources: &[DataSource], indent: &str) -> io::Result<()> {
+    for source in sources {
+        let uuid = source.uuid();
+        let mut element = BytesStart::new("data-source");
+        element.push_attribute(("source", "LOCAL"));
+        element.push_attribute(("name", source.name.as_str()));
+        element.push_attribute(("uuid", uuid.as_str()));
+        writer.write_event(Event::Text(BytesText::new(indent)))?;
+        writer.write_event(Event::Start(element))?;
+
+        let children = [
+            ("driver-ref", source.driver.jdbc_scheme().to_string()),
+            ("synchronize", "true".to_string()),
+            ("jdbc-url", source.jdbc_url()),
+            ("user-name", source.user.clone()),
+            ("working-dir", "$ProjectFileDir$".to_string()),
+        ];
+        for (tag, value) in children {
+            writer.write_event(Event::Text(BytesText::new(&format!("{}  ", indent))))?;
+            writer.create_element(tag).write_text_content(BytesText::new(&value))?;
+        }
+        writer.write_event(Event::Text(BytesText::new(indent)))?;
+        writer.write_event(Event::End(BytesEnd::new("data-source")))?;
+    }
+    Ok(())
+}
+
+// Replaces our previous <data-source> elements in the data source manager and copies everything else as is
+fn merge_jetbrains(existing: &str, sources: &[DataSource]) -> io::Result<String> {
+    let existing = if existing.trim().is_empty() { JETBRAINS_TEMPLATE } else { existing };
+    let uuids: Vec<String> = sources.iter().map(|source| source.uuid()).collect();
+
+    let mut reader = Reader::from_str(existing);
+    let mut writer = Writer::new(Vec::new());
+    let mut depth = 0;
+    let mut manager_depth = None;
+    let mut skip_depth = 0;
+    let mut inserted = false;
+    // Whitespace is held back so it can be dropped along with a replaced element
+    let mut pending = String::new();
+
+    loop {
+        let event = reader.read_event().map_err(xml_error)?;
+        if skip_depth > 0 {
+            match event {
+                Event::Start(_) => skip_depth += 1,
+                Event::End(_) => skip_depth -= 1,
+                Event::Eof => return Err(xml_error("unexpected end of file")),
+                _ => {},
+            }
+            continue;
+        }
+
+        match event {
+            Event::Text(text) if text.iter().all(u8::is_ascii_whitespace) => {
+                pending.push_str(&String::from_utf8_lossy(&text));
+            },
+            Event::Start(element) => {
+                depth += 1;
+                if depth == 1 && element.name().as_ref() != b"project" {
+                    return Err(io::Error::other("dataSources.xml is not a JetBrains project file"));
+                }
+                let name = element.name();
+                if name.as_ref() == b"data-source" && manager_depth == Some(depth - 1) {
+                    if let Some(uuid) = attribute(&element, "uuid")? {
+                        if uuids.contains(&uuid) {
+                            pending.clear();
+                            depth -= 1;
+                            skip_depth = 1;
+                            continue;
+                        }
+                    }
+                }
+                if depth == 2 && name.as_ref() == b"component" && attribute(&element, "name")?.as_deref() == Some(DATA_SOURCE_MANAGER) {
+                    manager_depth = Some(depth);
+                }
+                writer.write_event(Event::Text(BytesText::new(&pending)))?;
+                pending.clear();
+                writer.write_event(Event::Start(element))?;
+            },
+            Event::Empty(element) => {
+                let name = element.name();
+                if name.as_ref() == b"data-source" && manager_depth == Some(depth) {
+                    if let Some(uuid) = attribute(&element, "uuid")? {
+                        if uuids.contains(&uuid) {
+                            pending.clear();
+                            continue;
+                        }
+                    }
+                }
+                let indent = line_indent(&pending, depth);
+                writer.write_event(Event::Text(BytesText::new(&pending)))?;
+                pending.clear();
+                if depth == 1 && name.as_ref() == b"component" && attribute(&element, "name")?.as_deref() == Some(DATA_SOURCE_MANAGER) {
+                    writer.write_event(Event::Start(element.to_owned()))?;
+                    write_data_sources(&mut writer, sources, &format!("{}  ", indent))?;
+                    writer.write_event(Event::Text(BytesText::new(&indent)))?;
+                    writer.write_event(Event::End(element.to_end().into_owned()))?;
+                    inserted = true;
+                } else {
+                    writer.write_event(Event::Empty(element))?;
+                }
+            },
+            Event::End(element) => {
+                if manager_depth == Some(depth) {
+                    // The closing tag goes on its own line after the new entries
+                    pending = line_indent(&pending, depth - 1);
+                    write_data_sources(&mut writer, sources, &format!("{}  ", pending))?;
+                    manager_depth = None;
+                    inserted = true;
+                } else if depth == 1 && !inserted {
+                    // No data source manager yet, add one at the end of the project
+                    pending = line_indent(&pending, 0);
+                    let indent = format!("{}  ", pending);
+                    let mut component = BytesStart::new("component");
+                    component.push_attribute(("name", DATA_SOURCE_MANAGER));
+                    component.push_attribute(("format", "xml"));
+                    component.push_attribute(("multifile-model", "true"));
+                    writer.write_event(Event::Text(BytesText::new(&indent)))?;
+                    writer.write_event(Event::Start(component))?;
+                    write_data_sources(&mut writer, sources, &format!("{}  ", indent))?;
+                    writer.write_event(Event::Text(BytesText::new(&indent)))?;
+                    writer.write_event(Event::End(BytesEnd::new("component")))?;
+                    inserted = true;
+                }
+                writer.write_event(Event::Text(BytesText::new(&pending)))?;
+                pending.clear();
+                writer.write_event(Event::End(element))?;
+                depth -= 1;
+            },
+            Event::Eof if depth > 0 => return Err(xml_error("unexpected end of file")),
+            Event::Eof => break,
+            event => {
+                writer.write_event(Event::Text(BytesText::new(&pending)))?;
+                pending.clear();
+                writer.write_event(event)?;
+            },
+        }
+    }
+    writer.write_event(Event::Text(BytesText::new(&pending)))?;
+
+    if !inserted {
+        return Err(io::Error::other("dataSources.xml has no <project> element"));
+    }
+    String::from_utf8(writer.into_inner()).map_err(io::Error::other)
+}
+
+fn parse_json_object(existing: &str, file: &str) -> io::Result<Map<String, Value>> {
+    if existing.trim().is_empty() {
+        return Ok(Map::new());
+    }
+    // Refuse to rewrite files we can't fully parse (eg. JSON with comments)
+    match serde_json::from_str::<Value>(existing) {
+        Ok(Value::Object(object)) => Ok(object),
+        Ok(_) => Err(io::Error::other(format!("{} is not a JSON object", file))),
+        Err(err) => Err(io::Error::other(format!("failed to parse {}: {}", file, err))),
+    }
+}
+
+fn merge_dbeaver(existing: &str, sources: &[DataSource]) -> io::Result<String> {
+    let mut root = parse_json_object(existing, "data-sources.json")?;
+
+    let connections = root
+        .entry("connections")
+        .or_insert_with(|| Value::Object(Map::new()));
+    let Value::Object(connections) = connections else {
+        return Err(io::Error::other("data-sources.json connections is not an object"));
+    };
+
+    for source in sources {
+        let (provider, driver) = match source.driver {
+            Driver::Postgres => ("postgresql", "postgres-jdbc"),
+            Driver::Mysql => ("mysql", "mysql8"),
+        };
+
+        let mut configuration = json!({
+            "host": source.host,
+            "port": source.port.to_string(),
+            "database": source.database,
+            "url": source.jdbc_url(),
+            "user": source.user,
+            "auth-model": "native",
+            "type": "dev",
+        });
+        // Each SSL handler reads its own property names
+        configuration["handlers"] = match source.driver {
+            Driver::Postgres => json!({
+                "postgre_ssl": {
+                    "type": "CONFIG",
+                    "enabled": true,
+                    "properties": {
+                        "ssl.mode": "verify-full",
+                        "ssl.root.cert": source.ssl.ca,
+                        "ssl.cert": source.ssl.cert,
+                        "ssl.key": source.ssl.key,
+                    },
+                },
+            }),
+            Driver::Mysql => json!({
+                "mysql_ssl": {
+                    "type": "CONFIG",
+                    "enabled": true,
+                    "properties": {
+                        "ssl.require": true,
+                        "ssl.verify.server": true,
+                        "ssl.ca.cert": source.ssl.ca,
+                        "ssl.client.cert": source.ssl.cert,
+                        "ssl.client.key": source.ssl.key,
+                    },
+                },
+            }),
+        };
+
+        let id = format!("{}-{}", NAME_PREFIX, source.instance);
+        connections.insert(id, json!({
+            "provider": provider,
+            "driver": driver,
+            "name": source.name,
+            "save-password": false,
+            "configuration": configuration,
+        }));
+    }
+
+    serde_json::to_string_pretty(&Value::Object(root)).map_err(io::Error::other)
+}
+
+fn merge_sqltools(existing: &str, sources: &[DataSource]) -> io::Result<String> {
+    const CONNECTIONS_KEY: &str = "sqltools.connections";
+
+    let mut root = parse_json_object(existing, "settings.json")?;
+
+    let connections = root
+        .entry(CONNECTIONS_KEY)
+        .or_insert_with(|| Value::Array(vec![]));
+    let Value::Array(connections) = connections else {
+        return Err(io::Error::other("sqltools.connections is not an array"));
+    };
+
+    let names: Vec<&str> = sources.iter().map(|source| source.name.as_str()).collect();
+    connections.retain(|connection| {
+        let name = connection.get("name").and_then(Value::as_str).unwrap_or_default();
+        !names.contains(&name)
+    });
+
+    for source in sources {
+        let mut connection = json!({
+            "name": source.name,
+            "server": source.host,
+            "port": source.port,
+            "database": source.database,
+            "username": source.user,
+            "previewLimit": 50,
+        });
+        match source.driver {
+            Driver::Postgres => {
+                connection["driver"] = json!("PostgreSQL");
+                connection["pgOptions"] = json!({
+                    "ssl": {
+                        "rejectUnauthorized": true,
+                        "ca": source.ssl.ca,
+                        "cert": source.ssl.cert,
+                        "key": source.ssl.key,
+                    },
+                });
+            },
+            Driver::Mysql => {
+                connection["driver"] = json!("MySQL");
+                connection["mysqlOptions"] = json!({
+                    "ssl": {
+                        "rejectUnauthorized": true,
+                        "ca": source.ssl.ca,
+                        "cert": source.ssl.cert,
+                        "key": source.ssl.key,
+                    },
+                });
+            },
+        }
+        connections.push(connection);
+    }
+
+    serde_json::to_string_pretty(&Value::Object(root)).map_err(io::Error::other)
+}
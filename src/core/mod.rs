@@ -1 +1,28 @@
 pub mod tsh;
+pub mod access_request;
+pub mod poller;
+pub mod resources;
+pub mod clipboard;
+pub mod db_config;
+pub mod task;
+pub mod ide_export;
+pub mod subshell;
+pub mod selector;
+pub mod tunnel;
+pub mod template;
+pub mod hooks;
+pub mod guardrail;
+pub mod audit;
+pub mod defaults;
+pub mod session_limit;
+pub mod pty_session;
+pub mod recording;
+pub mod query;
+pub mod schema;
+pub mod result_export;
+pub mod fanout;
+pub mod snippet;
+pub mod activity;
+pub mod schema_diff;
+pub mod health_check;
+pub mod worker;
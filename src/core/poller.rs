@@ -0,0 +1,60 @@
+use std::{
+    sync::mpsc::{channel, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+// Periodically runs a fetch on a background thread so slow tsh calls don't block rendering
+pub struct Poller<T> {
+    interval: Duration,
+    receiver: Option<Receiver<T>>,
+    last_fetch: Option<Instant>,
+}
+
+impl<T: Send + 'static> Poller<T> {
+    pub fn new(interval: Duration) -> Poller<T> {
+        Poller {
+            interval,
+            receiver: None,
+            last_fetch: None,
+        }
+    }
+
+    // Forces the next poll to start a fetch
+    pub fn refresh_now(&mut self) {
+        self.last_fetch = None;
+    }
+
+    // Returns the result of a completed fetch and starts a new one when due
+    pub fn poll<F>(&mut self, fetch: F) -> Option<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let mut result = None;
+        if let Some(receiver) = &self.receiver {
+            match receiver.try_recv() {
+                Ok(value) => {
+                    result = Some(value);
+                    self.receiver = None;
+                },
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => self.receiver = None,
+            }
+        }
+
+        let due = match self.last_fetch {
+            Some(instant) => instant.elapsed() >= self.interval,
+            None => true,
+        };
+        if due {
+            let (sender, receiver) = channel();
+            thread::spawn(move || {
+                let _ = sender.send(fetch());
+            });
+            self.receiver = Some(receiver);
+            self.last_fetch = Some(Instant::now());
+        }
+
+        result
+    }
+}
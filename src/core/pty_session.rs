@@ -0,0 +1,442 @@
+use std::{
+    env,
+    io::{self, stdout, Read, Write},
+    os::unix::process::ExitStatusExt,
+    process::{Command, ExitStatus},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use crossterm::{
+    event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    terminal,
+};
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use tracing::{event, Level};
+
+use super::{
+    recording::{Recording, SharedRecording},
+    resources::SshArgs,
+    session_limit::{format_duration, terminate_descendants, SessionDeadline, SessionOutcome},
+    tsh::ConnectionArgs,
+};
+
+// Lines kept above the visible screen of each session
+const SCROLLBACK_LINES: usize = 1000;
+
+// How long a recorded in-place session waits on input before checking the child
+const INPUT_POLL: Duration = Duration::from_millis(50);
+
+// What a session was opened for, kept for hooks and the audit log once it ends
+pub enum SessionOrigin {
+    Database {
+        args: ConnectionArgs,
+        method: String,
+        reason: Option<String>,
+    },
+    Ssh(SshArgs),
+}
+
+// A child process attached to a pseudo-terminal, its output is parsed into a VT100 screen
+pub struct PtySession {
+    pub title: String,
+    pub origin: SessionOrigin,
+    pub parser: Arc<Mutex<vt100::Parser>>,
+    pub started: Instant,
+    pub deadline: Option<SessionDeadline>,
+    pub warned: bool,
+    pub expired: bool,
+    pub recording: Option<SharedRecording>,
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    size: (u16, u16),
+}
+
+impl PtySession {
+    pub fn spawn(
+        title: &str,
+        origin: SessionOrigin,
+        command: CommandBuilder,
+        rows: u16,
+        cols: u16,
+        deadline: Option<SessionDeadline>,
+        recording: Option<Recording>,
+    ) -> io::Result<PtySession> {
+        event!(Level::INFO, "spawning session {} ({}x{})", title, cols, rows);
+
+        let (master, child) = open_pty(command, rows, cols)?;
+        let reader = master.try_clone_reader().map_err(io::Error::other)?;
+        let writer = master.take_writer().map_err(io::Error::other)?;
+        let parser = Arc::new(Mutex::new(vt100::Parser::new(rows, cols, SCROLLBACK_LINES)));
+        let recording = recording.map(|recording| Arc::new(Mutex::new(recording)));
+        spawn_reader(title.to_string(), reader, Arc::clone(&parser), recording.clone(), false);
+
+        Ok(PtySession {
+            title: title.to_string(),
+            origin,
+            parser,
+            started: Instant::now(),
+            deadline,
+            warned: false,
+            expired: false,
+            recording,
+            master,
+            writer,
+            child,
+            size: (rows, cols),
+        })
+    }
+
+    pub fn write_input(&mut self, bytes: &[u8]) {
+        let result = self.writer
+            .write_all(bytes)
+            .and_then(|_| self.writer.flush());
+        if let Err(err) = result {
+            event!(Level::ERROR, "failed to write to session {}: {}", self.title, err);
+        }
+    }
+
+    // Only forwards actual changes, every resize sends SIGWINCH to the child
+    pub fn resize(&mut self, rows: u16, cols: u16) {
+        if self.size == (rows, cols) || rows == 0 || cols == 0 {
+            return;
+        }
+        self.size = (rows, cols);
+
+        if let Err(err) = self.master.resize(pty_size(rows, cols)) {
+            event!(Level::ERROR, "failed to resize session {}: {}", self.title, err);
+        }
+        if let Ok(mut parser) = self.parser.lock() {
+            parser.screen_mut().set_size(rows, cols);
+        }
+        if let Some(recording) = &self.recording {
+            if let Ok(mut recording) = recording.lock() {
+                recording.resize(cols, rows);
+            }
+        }
+    }
+
+    pub fn recording_path(&self) -> Option<String> {
+        let recording = self.recording.as_ref()?.lock().ok()?;
+        Some(recording.path.display().to_string())
+    }
+
+    // `Some` once the child exited, with its exit code
+    pub fn try_exit_code(&mut self) -> Option<u32> {
+        match self.child.try_wait() {
+            Ok(Some(status)) => Some(status.exit_code()),
+            Ok(None) => None,
+            Err(err) => {
+                event!(Level::ERROR, "failed to poll session {}: {}", self.title, err);
+                None
+            },
+        }
+    }
+
+    // Blocks until the child exited, used when kith closes with sessions still open
+    pub fn wait_exit_code(&mut self) -> u32 {
+        match self.child.wait() {
+            Ok(status) => status.exit_code(),
+            Err(err) => {
+                event!(Level::ERROR, "failed to wait for session {}: {}", self.title, err);
+                1
+            },
+        }
+    }
+
+    pub fn terminate(&mut self) {
+        event!(Level::INFO, "terminating session {}", self.title);
+        if let Some(pid) = self.child.process_id() {
+            terminate_descendants(pid);
+        }
+        if let Err(err) = self.child.kill() {
+            event!(Level::DEBUG, "failed to kill session {}: {}", self.title, err);
+        }
+    }
+}
+
+impl Drop for PtySession {
+    fn drop(&mut self) {
+        if self.try_exit_code().is_none() {
+            self.terminate();
+        }
+        let _ = self.child.wait();
+    }
+}
+
+pub fn database_command(args: &ConnectionArgs) -> CommandBuilder {
+    let mut command = CommandBuilder::new("tsh");
+    command.args([
+        "db".to_string(),
+        "connect".to_string(),
+        format!("--db-user={}", args.db_user),
+        format!("--db-name={}", args.db_name),
+        args.instance.clone(),
+    ]);
+    command
+}
+
+pub fn ssh_command(args: &SshArgs) -> CommandBuilder {
+    let mut command = CommandBuilder::new("tsh");
+    command.args(["ssh".to_string(), format!("{}@{}", args.login, args.host)]);
+    command
+}
+
+// Runs a command on kith's own terminal through a pseudo-terminal so its output can be recorded,
+// keys are forwarded the same way as in a session tab
+pub fn run_recorded(
+    command: &Command,
+    deadline: Option<SessionDeadline>,
+    recording: Recording,
+) -> io::Result<SessionOutcome> {
+    let (cols, rows) = terminal::size()?;
+    let (master, mut child) = open_pty(to_command_builder(command), rows, cols)?;
+    let reader = master.try_clone_reader().map_err(io::Error::other)?;
+    let mut writer = master.take_writer().map_err(io::Error::other)?;
+    // Only tracks the cursor key mode, the real screen is the user's terminal
+    let parser = Arc::new(Mutex::new(vt100::Parser::new(rows, cols, 0)));
+    let recording = Arc::new(Mutex::new(recording));
+    spawn_reader("in-place".to_string(), reader, Arc::clone(&parser), Some(Arc::clone(&recording)), true);
+
+    if let Ok(recording) = recording.lock() {
+        eprintln!("kith: recording this session to {}.", recording.path.display());
+    }
+    if let Some(deadline) = &deadline {
+        eprintln!(
+            "kith: this session is limited to {} and ends at {}.",
+            format_duration(deadline.max_duration),
+            deadline.ends_at(),
+        );
+    }
+
+    let _raw_mode = RawMode::enable()?;
+    let mut warned = false;
+    let outcome = loop {
+        if let Some(status) = child.try_wait()? {
+            break SessionOutcome { status: exit_status(status.exit_code()), expired: false };
+        }
+        if let Some(deadline) = &deadline {
+            if deadline.is_expired() {
+                eprint!("\r\nkith: session time limit reached, terminating.\r\n");
+                if let Some(pid) = child.process_id() {
+                    event!(Level::INFO, "session limit reached, terminating pid {}", pid);
+                    terminate_descendants(pid);
+                }
+                let _ = child.kill();
+                let status = child.wait()?;
+                break SessionOutcome { status: exit_status(status.exit_code()), expired: true };
+            }
+            if !warned && deadline.in_warning() {
+                eprint!("\r\nkith: session ends in {}.\r\n", format_duration(deadline.remaining()));
+                warned = true;
+            }
+        }
+
+        if !crossterm::event::poll(INPUT_POLL)? {
+            continue;
+        }
+        match crossterm::event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                let application_cursor = parser
+                    .lock()
+                    .map(|parser| parser.screen().application_cursor())
+                    .unwrap_or(false);
+                writer.write_all(&key_to_bytes(key, application_cursor))?;
+                writer.flush()?;
+            },
+            Event::Paste(text) => {
+                writer.write_all(text.as_bytes())?;
+                writer.flush()?;
+            },
+            Event::Resize(cols, rows) => {
+                if let Err(err) = master.resize(pty_size(rows, cols)) {
+                    event!(Level::ERROR, "failed to resize in-place session: {}", err);
+                }
+                if let Ok(mut parser) = parser.lock() {
+                    parser.screen_mut().set_size(rows, cols);
+                }
+                if let Ok(mut recording) = recording.lock() {
+                    recording.resize(cols, rows);
+                }
+            },
+            _ => {},
+        }
+    };
+    Ok(outcome)
+}
+
+// Leaves raw mode however the in-place loop ends, including on errors
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> io::Result<RawMode> {
+        terminal::enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        if let Err(err) = terminal::disable_raw_mode() {
+            event!(Level::ERROR, "failed to leave raw mode: {}", err);
+        }
+    }
+}
+
+// Encodes a key press the way an xterm would send it to the child
+pub fn key_to_bytes(key: KeyEvent, application_cursor: bool) -> Vec<u8> {
+    let cursor = |code: u8| -> Vec<u8> {
+        if application_cursor {
+            vec![0x1b, b'O', code]
+        } else {
+            vec![0x1b, b'[', code]
+        }
+    };
+
+    let mut bytes = match key.code {
+        KeyCode::Char(character) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            match character.to_ascii_lowercase() {
+                letter @ 'a'..='z' => vec![letter as u8 - b'a' + 1],
+                ' ' | '@' | '2' => vec![0],
+                '[' | '3' => vec![0x1b],
+                '\\' | '4' => vec![0x1c],
+                ']' | '5' => vec![0x1d],
+                '^' | '6' => vec![0x1e],
+                '_' | '7' | '/' => vec![0x1f],
+                _ => vec![],
+            }
+        },
+        KeyCode::Char(character) => character.to_string().into_bytes(),
+        KeyCode::Enter => vec![b'\r'],
+        KeyCode::Backspace => vec![0x7f],
+        KeyCode::Tab => vec![b'\t'],
+        KeyCode::BackTab => b"\x1b[Z".to_vec(),
+        KeyCode::Esc => vec![0x1b],
+        KeyCode::Up => cursor(b'A'),
+        KeyCode::Down => cursor(b'B'),
+        KeyCode::Right => cursor(b'C'),
+        KeyCode::Left => cursor(b'D'),
+        KeyCode::Home => cursor(b'H'),
+        KeyCode::End => cursor(b'F'),
+        KeyCode::PageUp => b"\x1b[5~".to_vec(),
+        KeyCode::PageDown => b"\x1b[6~".to_vec(),
+        KeyCode::Insert => b"\x1b[2~".to_vec(),
+        KeyCode::Delete => b"\x1b[3~".to_vec(),
+        KeyCode::F(number) => match number {
+            1 => b"\x1bOP".to_vec(),
+            2 => b"\x1bOQ".to_vec(),
+            3 => b"\x1bOR".to_vec(),
+            4 => b"\x1bOS".to_vec(),
+            5 => b"\x1b[15~".to_vec(),
+            6 => b"\x1b[17~".to_vec(),
+            7 => b"\x1b[18~".to_vec(),
+            8 => b"\x1b[19~".to_vec(),
+            9 => b"\x1b[20~".to_vec(),
+            10 => b"\x1b[21~".to_vec(),
+            11 => b"\x1b[23~".to_vec(),
+            12 => b"\x1b[24~".to_vec(),
+            _ => vec![],
+        },
+        _ => vec![],
+    };
+
+    // Alt sends an escape prefix, like xterm's metaSendsEscape
+    if key.modifiers.contains(KeyModifiers::ALT) && !bytes.is_empty() {
+        bytes.insert(0, 0x1b);
+    }
+    bytes
+}
+
+fn open_pty(
+    mut command: CommandBuilder,
+    rows: u16,
+    cols: u16,
+) -> io::Result<(Box<dyn MasterPty + Send>, Box<dyn Child + Send + Sync>)> {
+    let pair = native_pty_system()
+        .openpty(pty_size(rows, cols))
+        .map_err(io::Error::other)?;
+
+    command.env("TERM", "xterm-256color");
+    if command.get_cwd().is_none() {
+        if let Ok(cwd) = env::current_dir() {
+            command.cwd(cwd);
+        }
+    }
+    let child = pair.slave
+        .spawn_command(command)
+        .map_err(io::Error::other)?;
+    // The slave end is only needed by the child, keeping it open would hide its exit
+    drop(pair.slave);
+
+    Ok((pair.master, child))
+}
+
+fn to_command_builder(command: &Command) -> CommandBuilder {
+    let mut builder = CommandBuilder::new(command.get_program());
+    builder.args(command.get_args());
+    for (key, value) in command.get_envs() {
+        match value {
+            Some(value) => builder.env(key, value),
+            None => builder.env_remove(key),
+        }
+    }
+    if let Some(dir) = command.get_current_dir() {
+        builder.cwd(dir);
+    }
+    builder
+}
+
+// The pty only reports an exit code, signals are folded into it
+fn exit_status(code: u32) -> ExitStatus {
+    ExitStatus::from_raw((code as i32) << 8)
+}
+
+// Feeds the screen and the transcript, in-place sessions also echo to kith's terminal
+fn spawn_reader(
+    title: String,
+    mut reader: Box<dyn Read + Send>,
+    parser: Arc<Mutex<vt100::Parser>>,
+    recording: Option<SharedRecording>,
+    echo: bool,
+) {
+    thread::spawn(move || {
+        let mut buffer = [0u8; 8192];
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(count) => {
+                    let output = &buffer[..count];
+                    if echo {
+                        let mut stdout = stdout();
+                        if stdout.write_all(output).and_then(|_| stdout.flush()).is_err() {
+                            break;
+                        }
+                    }
+                    if let Some(recording) = &recording {
+                        if let Ok(mut recording) = recording.lock() {
+                            recording.output(output);
+                        }
+                    }
+                    match parser.lock() {
+                        Ok(mut parser) => parser.process(output),
+                        Err(_) => break,
+                    }
+                },
+                Err(err) => {
+                    event!(Level::DEBUG, "session {} output closed: {}", title, err);
+                    break;
+                },
+            }
+        }
+    });
+}
+
+fn pty_size(rows: u16, cols: u16) -> PtySize {
+    PtySize {
+        rows,
+        cols,
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
@@ -0,0 +1,572 @@
+use std::{
+    io::Write,
+    iter::Peekable,
+    str::CharIndices,
+    thread,
+    time::{Duration, Instant},
+};
+use mysql::prelude::Queryable;
+use postgres::{NoTls, SimpleQueryMessage};
+use tracing::{event, Level};
+
+use super::{
+    result_export::{create_file, ExportedFile, ResultFormat, ResultWriter},
+    schema::{mysql_children, postgres_children, SchemaNode, SchemaPath},
+    tsh::ConnectionArgs,
+    tunnel::{pick_free_port, Tunnel, TUNNEL_HOST},
+    worker::Worker,
+};
+
+// Rows kept per result, the rest is only counted
+pub const MAX_ROWS: usize = 5000;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Rows fetched at a time when exporting from PostgreSQL
+const EXPORT_BATCH_SIZE: usize = 1000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DatabaseKind {
+    Postgres,
+    MySql,
+}
+
+impl DatabaseKind {
+    pub fn from_protocol(protocol: &str) -> Option<DatabaseKind> {
+        match protocol {
+            "postgres" => Some(DatabaseKind::Postgres),
+            "mysql" => Some(DatabaseKind::MySql),
+            _ => None,
+        }
+    }
+
+    pub fn to_string(self) -> &'static str {
+        match self {
+            DatabaseKind::Postgres => "PostgreSQL",
+            DatabaseKind::MySql => "MySQL",
+        }
+    }
+}
+
+// The last statement's columns and rows, `None` values are SQL NULLs
+#[derive(Clone, Default)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Option<String>>>,
+    pub total_rows: usize,
+    pub affected: Option<u64>,
+    pub elapsed: Duration,
+}
+
+impl QueryResult {
+    pub fn summary(&self) -> String {
+        let elapsed = format!("{:.1} ms", self.elapsed.as_secs_f64() * 1000.0);
+        if self.columns.is_empty() {
+            return match self.affected {
+                Some(count) => format!("{} rows affected in {}", count, elapsed),
+                None => format!("Done in {}", elapsed),
+            };
+        }
+        if self.total_rows > self.rows.len() {
+            format!("{} of {} rows shown in {}", self.rows.len(), self.total_rows, elapsed)
+        } else {
+            format!("{} rows in {}", self.total_rows, elapsed)
+        }
+    }
+
+    pub fn push_row(&mut self, row: Vec<Option<String>>) {
+        self.push_row_within(row, MAX_ROWS);
+    }
+
+    fn push_row_within(&mut self, row: Vec<Option<String>>, max_rows: usize) {
+        self.total_rows += 1;
+        if self.rows.len() < max_rows {
+            self.rows.push(row);
+        }
+    }
+}
+
+pub enum QueryEvent {
+    Connected(CancelHandle),
+    Finished(Result<QueryResult, String>),
+    Schema(SchemaPath, Result<Vec<SchemaNode>, String>),
+    Exported(Result<ExportedFile, String>),
+    // The worker stopped, the connection can no longer be used
+    Closed(String),
+}
+
+// Cancels the running statement from outside the worker
+pub enum CancelHandle {
+    Postgres(postgres::CancelToken),
+    MySql { opts: mysql::Opts, connection_id: u32 },
+}
+
+impl CancelHandle {
+    // Cancelling opens another connection, it runs in the background
+    pub fn cancel(&self) {
+        match self {
+            CancelHandle::Postgres(token) => {
+                let token = token.clone();
+                thread::spawn(move || {
+                    if let Err(err) = token.cancel_query(NoTls) {
+                        event!(Level::ERROR, "failed to cancel query: {}", err);
+                    }
+                });
+            },
+            CancelHandle::MySql { opts, connection_id } => {
+                let (opts, connection_id) = (opts.clone(), *connection_id);
+                thread::spawn(move || {
+                    let result = mysql::Conn::new(opts)
+                        .and_then(|mut conn| conn.query_drop(format!("KILL QUERY {}", connection_id)));
+                    if let Err(err) = result {
+                        event!(Level::ERROR, "failed to cancel query: {}", err);
+                    }
+                });
+            },
+        }
+    }
+}
+
+enum QueryRequest {
+    Execute { sql: String, max_rows: usize },
+    Browse(SchemaPath),
+    Export { sql: String, format: ResultFormat, path: String, overwrite: bool },
+}
+
+pub(crate) enum Client {
+    Postgres(Box<postgres::Client>),
+    MySql(mysql::Conn),
+}
+
+// Requests run in order on a worker owning the tunnel and the client,
+// dropping the connection stops the worker, the client and then the tunnel
+pub struct QueryConnection {
+    worker: Worker<QueryRequest, QueryEvent>,
+}
+
+impl QueryConnection {
+    pub fn open(args: ConnectionArgs, kind: DatabaseKind) -> QueryConnection {
+        let worker = Worker::spawn(
+            "query",
+            args,
+            move |args| {
+                let (client, tunnel) = connect(args, kind)?;
+                let cancel = cancel_handle(&client, args, tunnel.port);
+                Ok(((client, tunnel), QueryEvent::Connected(cancel)))
+            },
+            |(client, _tunnel), _, request| match request {
+                QueryRequest::Execute { sql, max_rows } => QueryEvent::Finished(execute(client, &sql, max_rows)),
+                QueryRequest::Browse(path) => {
+                    let children = browse(client, &path);
+                    QueryEvent::Schema(path, children)
+                },
+                QueryRequest::Export { sql, format, path, overwrite } => {
+                    QueryEvent::Exported(export(client, &sql, format, &path, overwrite))
+                },
+            },
+            QueryEvent::Closed,
+        );
+        QueryConnection { worker }
+    }
+
+    pub fn execute(&self, sql: &str) -> bool {
+        self.execute_limited(sql, MAX_ROWS)
+    }
+
+    // Keeps up to `max_rows` rows of the result, the rest is only counted
+    pub fn execute_limited(&self, sql: &str, max_rows: usize) -> bool {
+        self.worker.send(QueryRequest::Execute { sql: sql.to_string(), max_rows })
+    }
+
+    // Requests are handled in order, browsing waits for a running statement
+    pub fn browse(&self, path: SchemaPath) -> bool {
+        self.worker.send(QueryRequest::Browse(path))
+    }
+
+    pub fn export(&self, sql: &str, format: ResultFormat, path: &str, overwrite: bool) -> bool {
+        let request = QueryRequest::Export {
+            sql: sql.to_string(),
+            format,
+            path: path.to_string(),
+            overwrite,
+        };
+        self.worker.send(request)
+    }
+
+    // Blocks up to `timeout`, for callers running off the render loop
+    pub fn wait_event(&self, timeout: Duration) -> Option<QueryEvent> {
+        self.worker.wait_event(timeout)
+    }
+
+    pub fn try_event(&self) -> Option<QueryEvent> {
+        self.worker.try_event()
+    }
+}
+
+pub(crate) fn connect(args: &ConnectionArgs, kind: DatabaseKind) -> Result<(Client, Tunnel), String> {
+    let port = pick_free_port().map_err(|err| format!("no free local port: {}", err))?;
+    let tunnel = Tunnel::start(args, port).map_err(|err| format!("failed to start tunnel: {}", err))?;
+    let client = connect_client(args, kind, port)?;
+    Ok((client, tunnel))
+}
+
+// The tunnel authenticates with the database certificate, no password is needed
+pub(crate) fn connect_client(args: &ConnectionArgs, kind: DatabaseKind, port: u16) -> Result<Client, String> {
+    connect_client_within(args, kind, port, CONNECT_TIMEOUT, None)
+}
+
+// A statement timeout is enforced by PostgreSQL, MySQL has none for every statement
+// so reads and writes on its socket are bounded instead
+pub(crate) fn connect_client_within(
+    args: &ConnectionArgs,
+    kind: DatabaseKind,
+    port: u16,
+    connect_timeout: Duration,
+    statement_timeout: Option<Duration>,
+) -> Result<Client, String> {
+    let client = match kind {
+        DatabaseKind::Postgres => {
+            let mut config = postgres::Config::new();
+            config
+                .host(TUNNEL_HOST)
+                .port(port)
+                .user(&args.db_user)
+                .dbname(&args.db_name)
+                .application_name("kith")
+                .connect_timeout(connect_timeout);
+            if let Some(timeout) = statement_timeout {
+                config.options(&format!("-c statement_timeout={}", timeout.as_millis()));
+            }
+            config
+                .connect(NoTls)
+                .map(|client| Client::Postgres(Box::new(client)))
+                .map_err(|err| postgres_error(&err))?
+        },
+        DatabaseKind::MySql => {
+            let opts = mysql::OptsBuilder::from_opts(mysql_opts(args, port))
+                .tcp_connect_timeout(Some(connect_timeout))
+                .read_timeout(statement_timeout)
+                .write_timeout(statement_timeout);
+            mysql::Conn::new(opts)
+                .map(Client::MySql)
+                .map_err(|err| err.to_string())?
+        },
+    };
+    Ok(client)
+}
+
+// Tunnel and client for views that only speak PostgreSQL
+pub(crate) fn connect_postgres(args: &ConnectionArgs) -> Result<(postgres::Client, Tunnel), String> {
+    match connect(args, DatabaseKind::Postgres)? {
+        (Client::Postgres(client), tunnel) => Ok((*client, tunnel)),
+        (Client::MySql(_), _) => Err("not a PostgreSQL connection".to_string()),
+    }
+}
+
+fn mysql_opts(args: &ConnectionArgs, port: u16) -> mysql::Opts {
+    mysql::OptsBuilder::new()
+        .ip_or_hostname(Some(TUNNEL_HOST))
+        .tcp_port(port)
+        .user(Some(&args.db_user))
+        .db_name(Some(&args.db_name))
+        .prefer_socket(false)
+        .tcp_connect_timeout(Some(CONNECT_TIMEOUT))
+        .into()
+}
+
+fn cancel_handle(client: &Client, args: &ConnectionArgs, port: u16) -> CancelHandle {
+    match client {
+        Client::Postgres(client) => CancelHandle::Postgres(client.cancel_token()),
+        Client::MySql(conn) => CancelHandle::MySql {
+            opts: mysql_opts(args, port),
+            connection_id: conn.connection_id(),
+        },
+    }
+}
+
+fn execute(client: &mut Client, sql: &str, max_rows: usize) -> Result<QueryResult, String> {
+    let started = Instant::now();
+    let mut result = match client {
+        Client::Postgres(client) => execute_postgres(client, sql, max_rows)?,
+        Client::MySql(conn) => execute_mysql(conn, sql, max_rows)?,
+    };
+    result.elapsed = started.elapsed();
+    Ok(result)
+}
+
+// Runs the query again and streams its rows to the file, without the display cap
+fn export(client: &mut Client, sql: &str, format: ResultFormat, path: &str, overwrite: bool) -> Result<ExportedFile, String> {
+    let kind = match client {
+        Client::Postgres(_) => DatabaseKind::Postgres,
+        Client::MySql(_) => DatabaseKind::MySql,
+    };
+    let sql = single_statement(sql.trim(), kind)?;
+
+    let (path, file) = create_file(path, overwrite).map_err(|err| format!("failed to create the file: {}", err))?;
+    let exported = match client {
+        Client::Postgres(client) => export_postgres(client, sql, format, file),
+        Client::MySql(conn) => export_mysql(conn, sql, format, file),
+    };
+    match exported {
+        Ok(rows) => Ok(ExportedFile { path, rows }),
+        Err(err) => {
+            // A partial file would look like a complete export
+            let _ = std::fs::remove_file(&path);
+            Err(err)
+        },
+    }
+}
+
+// The statement without its trailing `;`, both servers would run anything after it too.
+// Semicolons in strings, quoted identifiers and comments are part of the statement
+fn single_statement(sql: &str, kind: DatabaseKind) -> Result<&str, String> {
+    let mut chars = sql.char_indices().peekable();
+    let mut end = None;
+    while let Some((index, character)) = chars.next() {
+        let next = chars.peek().map(|(_, next)| *next);
+        match character {
+            '-' if next == Some('-') => skip_line(&mut chars),
+            '#' if kind == DatabaseKind::MySql => skip_line(&mut chars),
+            '/' if next == Some('*') => {
+                chars.next();
+                let mut previous = ' ';
+                for (_, character) in chars.by_ref() {
+                    if previous == '*' && character == '/' {
+                        break;
+                    }
+                    previous = character;
+                }
+            },
+            ';' => {
+                end.get_or_insert(index);
+            },
+            character if character.is_whitespace() => {},
+            _ if end.is_some() => return Err("only a single query can be exported".to_string()),
+            '\'' | '"' | '`' => {
+                // MySQL strings take backslash escapes, doubled quotes work for both
+                let backslash_escapes = kind == DatabaseKind::MySql && character != '`';
+                while let Some((_, inner)) = chars.next() {
+                    if backslash_escapes && inner == '\\' {
+                        chars.next();
+                    } else if inner == character {
+                        break;
+                    }
+                }
+            },
+            '$' if kind == DatabaseKind::Postgres => {
+                // Dollar quoting, `$tag$ ... $tag$`, a `$1` is not a tag
+                let rest = &sql[index + 1..];
+                let tag_length = rest.find(|character: char| !character.is_alphanumeric() && character != '_').unwrap_or(rest.len());
+                if rest[tag_length..].starts_with('$') && !rest.starts_with(|character: char| character.is_ascii_digit()) {
+                    let tag = &sql[index..index + tag_length + 2];
+                    let body = index + tag.len();
+                    let stop = sql[body..].find(tag).map_or(sql.len(), |position| body + position + tag.len());
+                    while chars.next_if(|(index, _)| *index < stop).is_some() {}
+                }
+            },
+            _ => {},
+        }
+    }
+    Ok(sql[..end.unwrap_or(sql.len())].trim_end())
+}
+
+fn skip_line(chars: &mut Peekable<CharIndices>) {
+    for (_, character) in chars.by_ref() {
+        if character == '\n' {
+            break;
+        }
+    }
+}
+
+fn browse(client: &mut Client, path: &SchemaPath) -> Result<Vec<SchemaNode>, String> {
+    match client {
+        Client::Postgres(client) => postgres_children(client, path),
+        Client::MySql(conn) => mysql_children(conn, path),
+    }
+}
+
+// The simple query protocol returns every value as text, which is what gets displayed anyway
+fn execute_postgres(client: &mut postgres::Client, sql: &str, max_rows: usize) -> Result<QueryResult, String> {
+    let messages = client.simple_query(sql).map_err(|err| postgres_error(&err))?;
+
+    let mut result = QueryResult::default();
+    let mut statement_done = false;
+    for message in messages {
+        if statement_done {
+            result = QueryResult::default();
+            statement_done = false;
+        }
+        match message {
+            SimpleQueryMessage::RowDescription(columns) => {
+                result.columns = columns.iter().map(|column| column.name().to_string()).collect();
+            },
+            SimpleQueryMessage::Row(row) => {
+                if result.columns.is_empty() {
+                    result.columns = row.columns().iter().map(|column| column.name().to_string()).collect();
+                }
+                result.push_row_within((0..row.len()).map(|index| row.get(index).map(str::to_string)).collect(), max_rows);
+            },
+            SimpleQueryMessage::CommandComplete(count) => {
+                if result.columns.is_empty() {
+                    result.affected = Some(count);
+                }
+                statement_done = true;
+            },
+            _ => {},
+        }
+    }
+    Ok(result)
+}
+
+fn execute_mysql(conn: &mut mysql::Conn, sql: &str, max_rows: usize) -> Result<QueryResult, String> {
+    let mut query_result = conn.query_iter(sql).map_err(|err| err.to_string())?;
+
+    let mut result = QueryResult::default();
+    while let Some(set) = query_result.iter() {
+        result = QueryResult::default();
+        result.columns = set.columns()
+            .as_ref()
+            .iter()
+            .map(|column| column.name_str().to_string())
+            .collect();
+        if result.columns.is_empty() {
+            result.affected = Some(set.affected_rows());
+        }
+        for row in set {
+            let row = row.map_err(|err| err.to_string())?;
+            let values = row.unwrap_raw().into_iter().map(|value| value.and_then(mysql_value)).collect();
+            result.push_row_within(values, max_rows);
+        }
+    }
+    Ok(result)
+}
+
+// A cursor fetches the rows in batches, declaring it only accepts SELECT and VALUES queries
+fn export_postgres<W: Write>(
+    client: &mut postgres::Client,
+    sql: &str,
+    format: ResultFormat,
+    out: W,
+) -> Result<u64, String> {
+    client
+        .simple_query(&format!("DECLARE kith_export NO SCROLL CURSOR WITH HOLD FOR {}", sql))
+        .map_err(|err| postgres_error(&err))?;
+    let result = write_cursor(client, format, out);
+    if let Err(err) = client.simple_query("CLOSE kith_export") {
+        event!(Level::ERROR, "failed to close the export cursor: {}", err);
+    }
+    result
+}
+
+// The writer starts with the columns described by the first batch
+fn write_cursor<W: Write>(client: &mut postgres::Client, format: ResultFormat, out: W) -> Result<u64, String> {
+    let mut writer: Option<ResultWriter<W>> = None;
+    let mut out = Some(out);
+    loop {
+        let messages = client
+            .simple_query(&format!("FETCH FORWARD {} FROM kith_export", EXPORT_BATCH_SIZE))
+            .map_err(|err| postgres_error(&err))?;
+        let mut fetched = 0;
+        for message in messages {
+            match message {
+                SimpleQueryMessage::RowDescription(columns) if writer.is_none() => {
+                    let columns = columns.iter().map(|column| column.name().to_string()).collect();
+                    let out = out.take().ok_or("export writer already used")?;
+                    writer = Some(ResultWriter::new(out, format, columns).map_err(|err| err.to_string())?);
+                },
+                SimpleQueryMessage::Row(row) => {
+                    let Some(writer) = writer.as_mut() else {
+                        continue;
+                    };
+                    let values: Vec<Option<String>> = (0..row.len()).map(|index| row.get(index).map(str::to_string)).collect();
+                    writer.write_row(&values).map_err(|err| err.to_string())?;
+                    fetched += 1;
+                },
+                _ => {},
+            }
+        }
+        if fetched < EXPORT_BATCH_SIZE {
+            break;
+        }
+    }
+    match writer {
+        Some(writer) => writer.finish().map(|(_, rows)| rows).map_err(|err| err.to_string()),
+        None => Err("the query returned no columns".to_string()),
+    }
+}
+
+// Rows are read off the connection as they are iterated, statements that could change data are refused
+// since exporting runs the query a second time. `WITH` is left out as MySQL accepts it before UPDATE and DELETE
+fn export_mysql<W: Write>(conn: &mut mysql::Conn, sql: &str, format: ResultFormat, out: W) -> Result<u64, String> {
+    let keyword = sql.split_whitespace().next().unwrap_or_default().to_uppercase();
+    if !["SELECT", "SHOW", "DESCRIBE", "DESC", "EXPLAIN", "TABLE", "VALUES"].contains(&keyword.as_str()) {
+        return Err("only read queries can be exported".to_string());
+    }
+
+    let mut query_result = conn.query_iter(sql).map_err(|err| err.to_string())?;
+    let Some(set) = query_result.iter() else {
+        return Err("the query returned no result".to_string());
+    };
+    let columns: Vec<String> = set.columns()
+        .as_ref()
+        .iter()
+        .map(|column| column.name_str().to_string())
+        .collect();
+    if columns.is_empty() {
+        return Err("the query returned no columns".to_string());
+    }
+
+    let mut writer = ResultWriter::new(out, format, columns).map_err(|err| err.to_string())?;
+    for row in set {
+        let row = row.map_err(|err| err.to_string())?;
+        let values: Vec<Option<String>> = row.unwrap_raw().into_iter().map(|value| value.and_then(mysql_value)).collect();
+        writer.write_row(&values).map_err(|err| err.to_string())?;
+    }
+    writer.finish().map(|(_, rows)| rows).map_err(|err| err.to_string())
+}
+
+pub(crate) fn mysql_value(value: mysql::Value) -> Option<String> {
+    use mysql::Value;
+    let text = match value {
+        Value::NULL => return None,
+        Value::Bytes(bytes) => match String::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(err) => format!("0x{}", err.into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect::<String>()),
+        },
+        Value::Int(value) => value.to_string(),
+        Value::UInt(value) => value.to_string(),
+        Value::Float(value) => value.to_string(),
+        Value::Double(value) => value.to_string(),
+        Value::Date(year, month, day, hour, minute, second, micros) => {
+            let mut text = format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, hour, minute, second);
+            if micros > 0 {
+                text.push_str(&format!(".{:06}", micros));
+            }
+            text
+        },
+        Value::Time(negative, days, hours, minutes, seconds, micros) => {
+            let hours = days * 24 + hours as u32;
+            let mut text = format!("{}{:02}:{:02}:{:02}", if negative { "-" } else { "" }, hours, minutes, seconds);
+            if micros > 0 {
+                text.push_str(&format!(".{:06}", micros));
+            }
+            text
+        },
+    };
+    Some(text)
+}
+
+// Server errors carry their own severity, detail and hint, connection errors are shown as is
+pub(crate) fn postgres_error(err: &postgres::Error) -> String {
+    let Some(db_error) = err.as_db_error() else {
+        return err.to_string();
+    };
+    let mut message = format!("{}: {}", db_error.severity(), db_error.message());
+    if let Some(detail) = db_error.detail() {
+        message.push_str(&format!(" ({})", detail));
+    }
+    if let Some(hint) = db_error.hint() {
+        message.push_str(&format!(", hint: {}", hint));
+    }
+    message
+}
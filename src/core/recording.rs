@@ -0,0 +1,264 @@
+use std::{
+    collections::HashMap,
+    fs::{self, DirBuilder, File, OpenOptions, Permissions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime},
+};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+use super::selector::LabelSelector;
+
+// `[recording]` in the config file, sessions are only recorded once enabled
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RecordingConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    pub selector: Option<LabelSelector>,
+}
+
+impl RecordingConfig {
+    // Resources without labels (eg. SSH nodes) are only recorded when no selector is set
+    pub fn applies_to(&self, labels: Option<&HashMap<String, String>>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        match (&self.selector, labels) {
+            (None, _) => true,
+            (Some(selector), Some(labels)) => selector.matches(labels),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+pub type SharedRecording = Arc<Mutex<Recording>>;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CastHeader {
+    pub version: u8,
+    pub width: u16,
+    pub height: u16,
+    #[serde(default)]
+    pub timestamp: Option<i64>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+}
+
+// `[time, code, data]`, `o` for output and `r` for `COLSxROWS` resizes
+pub struct CastEvent {
+    pub time: f64,
+    pub code: String,
+    pub data: String,
+}
+
+pub struct Cast {
+    pub header: CastHeader,
+    pub events: Vec<CastEvent>,
+}
+
+impl Cast {
+    pub fn duration(&self) -> f64 {
+        self.events.last().map_or(0.0, |event| event.time)
+    }
+}
+
+// A session transcript in the asciicast v2 format, written as the output arrives
+pub struct Recording {
+    pub path: PathBuf,
+    file: BufWriter<File>,
+    started: Instant,
+    // Bytes of a UTF-8 sequence split across two reads
+    pending: Vec<u8>,
+    failed: bool,
+}
+
+impl Recording {
+    pub fn create(instance: &str, cols: u16, rows: u16) -> io::Result<Recording> {
+        let dir = recordings_dir()
+            .ok_or_else(|| io::Error::other("no data directory available"))?;
+        // Recordings hold whatever the session showed and was typed, only the user may read them
+        DirBuilder::new().recursive(true).mode(0o700).create(&dir)?;
+        fs::set_permissions(&dir, Permissions::from_mode(0o700))?;
+
+        let (path, file) = create_file(&dir, instance)?;
+        event!(Level::INFO, "recording session {} to {}", instance, path.display());
+
+        let header = CastHeader {
+            version: 2,
+            width: cols,
+            height: rows,
+            timestamp: Some(Utc::now().timestamp()),
+            title: Some(instance.to_string()),
+            env: HashMap::from([("TERM".to_string(), "xterm-256color".to_string())]),
+        };
+        let mut file = BufWriter::new(file);
+        writeln!(file, "{}", serde_json::to_string(&header)?)?;
+        file.flush()?;
+
+        Ok(Recording {
+            path,
+            file,
+            started: Instant::now(),
+            pending: vec![],
+            failed: false,
+        })
+    }
+
+    pub fn output(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+        // Keep an incomplete trailing sequence for the next read, anything else invalid is replaced
+        let complete = match std::str::from_utf8(&self.pending) {
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            _ => self.pending.len(),
+        };
+        if complete == 0 {
+            return;
+        }
+        let data: Vec<u8> = self.pending.drain(..complete).collect();
+        self.write_event("o", &String::from_utf8_lossy(&data));
+    }
+
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        self.write_event("r", &format!("{}x{}", cols, rows));
+    }
+
+    // Flushed per event so a crash still leaves a usable transcript
+    fn write_event(&mut self, code: &str, data: &str) {
+        let time = (self.started.elapsed().as_secs_f64() * 1_000_000.0).round() / 1_000_000.0;
+        let result = serde_json::to_string(&(time, code, data))
+            .map_err(io::Error::from)
+            .and_then(|line| writeln!(self.file, "{}", line))
+            .and_then(|_| self.file.flush());
+
+        if let Err(err) = result {
+            // Logged once, a full disk would otherwise flood the log
+            if !self.failed {
+                event!(Level::ERROR, "failed to write recording {}: {}", self.path.display(), err);
+                self.failed = true;
+            }
+        }
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        if !self.pending.is_empty() {
+            let data = std::mem::take(&mut self.pending);
+            self.write_event("o", &String::from_utf8_lossy(&data));
+        }
+    }
+}
+
+pub struct RecordingFile {
+    pub path: PathBuf,
+    pub name: String,
+    pub modified: Option<SystemTime>,
+    pub size: u64,
+}
+
+impl RecordingFile {
+    pub fn modified_label(&self) -> String {
+        self.modified
+            .map(|modified| DateTime::<Local>::from(modified).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or("-".to_string())
+    }
+
+    pub fn size_label(&self) -> String {
+        match self.size {
+            size if size >= 1024 * 1024 => format!("{:.1} MiB", size as f64 / (1024.0 * 1024.0)),
+            size if size >= 1024 => format!("{:.1} KiB", size as f64 / 1024.0),
+            size => format!("{} B", size),
+        }
+    }
+}
+
+pub fn recordings_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("kith").join("recordings"))
+}
+
+// Newest first
+pub fn list_recordings() -> io::Result<Vec<RecordingFile>> {
+    let Some(dir) = recordings_dir() else {
+        return Ok(vec![]);
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+
+    let mut recordings = vec![];
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_none_or(|extension| extension != "cast") {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        recordings.push(RecordingFile {
+            name: entry.file_name().to_string_lossy().to_string(),
+            modified: metadata.modified().ok(),
+            size: metadata.len(),
+            path,
+        });
+    }
+    recordings.sort_by_key(|recording| std::cmp::Reverse(recording.modified));
+    Ok(recordings)
+}
+
+// Lines that fail to parse are skipped, a transcript cut short by a crash still replays
+pub fn read_cast(path: &Path) -> io::Result<Cast> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let header_line = lines
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "empty recording"))??;
+    let header: CastHeader = serde_json::from_str(&header_line)?;
+    if header.version != 2 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported asciicast version {}", header.version),
+        ));
+    }
+
+    let mut events = vec![];
+    for (index, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<(f64, String, String)>(&line) {
+            Ok((time, code, data)) => events.push(CastEvent { time, code, data }),
+            Err(err) => event!(Level::ERROR, "skipping recording line {}: {}", index + 2, err),
+        }
+    }
+    Ok(Cast { header, events })
+}
+
+// `<instance>-<local time>.cast`, suffixed when two sessions start within the same second
+fn create_file(dir: &Path, instance: &str) -> io::Result<(PathBuf, File)> {
+    let instance: String = instance
+        .chars()
+        .map(|character| if character.is_ascii_alphanumeric() || "-_.".contains(character) { character } else { '_' })
+        .collect();
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+
+    let mut attempt = 1;
+    loop {
+        let name = match attempt {
+            1 => format!("{}-{}.cast", instance, stamp),
+            _ => format!("{}-{}-{}.cast", instance, stamp, attempt),
+        };
+        let path = dir.join(name);
+        match OpenOptions::new().write(true).create_new(true).mode(0o600).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+            Err(err) => return Err(err),
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use std::{collections::HashMap, process::{Command, Stdio}};
+use serde::Deserialize;
+use tracing::{event, Level};
+
+use super::tsh::{command_exit_code, read_json_output, Tsh};
+
+// Shared shape of the entries listed in the resource tabs
+pub trait Resource {
+    fn name(&self) -> &str;
+    fn details(&self) -> Vec<DetailSection>;
+}
+
+// A titled group of key/value rows rendered in the details pane
+#[derive(Debug, Clone)]
+pub struct DetailSection {
+    pub title: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl DetailSection {
+    pub fn new(title: &str, fields: Vec<(String, String)>) -> DetailSection {
+        DetailSection {
+            title: title.to_string(),
+            fields,
+        }
+    }
+
+    // Labels are sorted so rows don't shuffle between renders
+    pub fn from_labels(labels: &HashMap<String, String>) -> DetailSection {
+        let mut fields: Vec<(String, String)> = labels
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        fields.sort();
+
+        DetailSection::new("Labels", fields)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Application {
+    pub metadata: ResourceMetadata,
+    pub spec: ApplicationSpec,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Node {
+    pub metadata: ResourceMetadata,
+    pub spec: NodeSpec,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct KubeCluster {
+    pub kube_cluster_name: String,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    #[serde(default)]
+    pub selected: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResourceMetadata {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApplicationSpec {
+    #[serde(default)]
+    pub uri: String,
+    #[serde(default)]
+    pub public_addr: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeSpec {
+    #[serde(default)]
+    pub hostname: String,
+    #[serde(default)]
+    pub addr: String,
+}
+
+pub struct SshArgs {
+    pub login: String,
+    pub host: String,
+}
+
+impl Application {
+    pub fn url(&self) -> String {
+        format!("https://{}", self.spec.public_addr)
+    }
+}
+
+impl Resource for Application {
+    fn name(&self) -> &str {
+        &self.metadata.name
+    }
+
+    fn details(&self) -> Vec<DetailSection> {
+        vec![
+            DetailSection::new("Metadata", vec![
+                ("Name".to_string(), self.metadata.name.clone()),
+                ("Description".to_string(), self.metadata.description.clone()),
+            ]),
+            DetailSection::new("Spec", vec![
+                ("URL".to_string(), self.url()),
+                ("Target URI".to_string(), self.spec.uri.clone()),
+            ]),
+            DetailSection::from_labels(&self.metadata.labels),
+        ]
+    }
+}
+
+impl Resource for Node {
+    fn name(&self) -> &str {
+        &self.spec.hostname
+    }
+
+    fn details(&self) -> Vec<DetailSection> {
+        vec![
+            DetailSection::new("Metadata", vec![
+                ("Hostname".to_string(), self.spec.hostname.clone()),
+                ("ID".to_string(), self.metadata.name.clone()),
+            ]),
+            DetailSection::new("Spec", vec![
+                ("Address".to_string(), self.spec.addr.clone()),
+            ]),
+            DetailSection::from_labels(&self.metadata.labels),
+        ]
+    }
+}
+
+impl Resource for KubeCluster {
+    fn name(&self) -> &str {
+        &self.kube_cluster_name
+    }
+
+    fn details(&self) -> Vec<DetailSection> {
+        let selected = if self.selected { "yes" } else { "no" };
+        vec![
+            DetailSection::new("Metadata", vec![
+                ("Name".to_string(), self.kube_cluster_name.clone()),
+                ("Current Context".to_string(), selected.to_string()),
+            ]),
+            DetailSection::from_labels(&self.labels),
+        ]
+    }
+}
+
+impl Tsh {
+    pub fn read_apps(&mut self, search: &str) {
+        event!(Level::DEBUG, "reading teleport applications");
+
+        let search = format!("--search={}", search);
+
+        let teleport_cmd = Command::new("tsh")
+            .args(["apps", "ls", &search, "--format=json"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        self.apps = read_json_output("teleport apps ls", teleport_cmd);
+    }
+
+    pub fn read_nodes(&mut self, search: &str) {
+        event!(Level::DEBUG, "reading teleport ssh nodes");
+
+        let search = format!("--search={}", search);
+
+        let teleport_cmd = Command::new("tsh")
+            .args(["ls", &search, "--format=json"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        self.nodes = read_json_output("teleport ls", teleport_cmd);
+    }
+
+    pub fn read_kube_clusters(&mut self, search: &str) {
+        event!(Level::DEBUG, "reading teleport kubernetes clusters");
+
+        let search = format!("--search={}", search);
+
+        let teleport_cmd = Command::new("tsh")
+            .args(["kube", "ls", &search, "--format=json"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        self.kube_clusters = read_json_output("teleport kube ls", teleport_cmd);
+    }
+
+    pub fn app_login(&mut self, app: &str) -> Option<i32> {
+        event!(Level::INFO, "logging into application: {}", app);
+
+        let teleport_cmd = Command::new("tsh")
+            .args(["apps", "login", app])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        let exit_status = command_exit_code("teleport apps login", teleport_cmd);
+        if exit_status == Some(0) && !self.active_apps.iter().any(|name| name == app) {
+            self.active_apps.push(app.to_string());
+        }
+        exit_status
+    }
+
+    // Switches the kubeconfig context to the given cluster
+    pub fn kube_login(&mut self, cluster: &str) -> Option<i32> {
+        event!(Level::INFO, "logging into kubernetes cluster: {}", cluster);
+
+        let teleport_cmd = Command::new("tsh")
+            .args(["kube", "login", cluster])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        let exit_status = command_exit_code("teleport kube login", teleport_cmd);
+        if exit_status == Some(0) {
+            for kube_cluster in self.kube_clusters.iter_mut() {
+                kube_cluster.selected = kube_cluster.kube_cluster_name == cluster;
+            }
+        }
+        exit_status
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use super::{db_config::expand_home, query::QueryResult};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ResultFormat {
+    Csv,
+    Ndjson,
+    JsonArray,
+    Markdown,
+}
+
+impl ResultFormat {
+    pub const ALL: [ResultFormat; 4] = [
+        ResultFormat::Csv,
+        ResultFormat::Ndjson,
+        ResultFormat::JsonArray,
+        ResultFormat::Markdown,
+    ];
+
+    pub fn to_string(self) -> &'static str {
+        match self {
+            ResultFormat::Csv => "CSV",
+            ResultFormat::Ndjson => "NDJSON (one object per line)",
+            ResultFormat::JsonArray => "JSON array",
+            ResultFormat::Markdown => "Markdown table",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ResultFormat> {
+        match name {
+            "csv" => Some(ResultFormat::Csv),
+            "ndjson" => Some(ResultFormat::Ndjson),
+            "json" => Some(ResultFormat::JsonArray),
+            "markdown" | "md" => Some(ResultFormat::Markdown),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ResultFormat::Csv => "csv",
+            ResultFormat::Ndjson => "ndjson",
+            ResultFormat::JsonArray => "json",
+            ResultFormat::Markdown => "md",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ResultDestination {
+    File,
+    Clipboard,
+}
+
+impl ResultDestination {
+    pub const ALL: [ResultDestination; 2] = [ResultDestination::File, ResultDestination::Clipboard];
+
+    pub fn to_string(self) -> &'static str {
+        match self {
+            ResultDestination::File => "File (runs the query again, streaming every row)",
+            ResultDestination::Clipboard => "Clipboard (rows shown in the console)",
+        }
+    }
+}
+
+pub struct ExportedFile {
+    pub path: PathBuf,
+    pub rows: u64,
+}
+
+// Writes rows as they come so exports never hold more than a row in memory, NULL is
+// an empty CSV field, a JSON null and an empty Markdown cell
+pub struct ResultWriter<W: Write> {
+    out: W,
+    format: ResultFormat,
+    columns: Vec<String>,
+    rows: u64,
+}
+
+impl<W: Write> ResultWriter<W> {
+    pub fn new(mut out: W, format: ResultFormat, columns: Vec<String>) -> io::Result<ResultWriter<W>> {
+        match format {
+            ResultFormat::Csv => {
+                let header: Vec<String> = columns.iter().map(|column| csv_field(column)).collect();
+                writeln!(out, "{}", header.join(","))?;
+            },
+            ResultFormat::Markdown => {
+                let header: Vec<String> = columns.iter().map(|column| markdown_cell(column)).collect();
+                writeln!(out, "| {} |", header.join(" | "))?;
+                writeln!(out, "|{}", " --- |".repeat(columns.len()))?;
+            },
+            ResultFormat::JsonArray => write!(out, "[")?,
+            ResultFormat::Ndjson => {},
+        }
+
+        Ok(ResultWriter { out, format, columns, rows: 0 })
+    }
+
+    pub fn write_row(&mut self, row: &[Option<String>]) -> io::Result<()> {
+        match self.format {
+            ResultFormat::Csv => {
+                let fields: Vec<String> = row
+                    .iter()
+                    .map(|value| value.as_deref().map(csv_field).unwrap_or_default())
+                    .collect();
+                writeln!(self.out, "{}", fields.join(","))?;
+            },
+            ResultFormat::Markdown => {
+                let cells: Vec<String> = row
+                    .iter()
+                    .map(|value| value.as_deref().map(markdown_cell).unwrap_or_default())
+                    .collect();
+                writeln!(self.out, "| {} |", cells.join(" | "))?;
+            },
+            ResultFormat::Ndjson => writeln!(self.out, "{}", json_object(&self.columns, row))?,
+            ResultFormat::JsonArray => {
+                let separator = if self.rows == 0 { "\n  " } else { ",\n  " };
+                write!(self.out, "{}{}", separator, json_object(&self.columns, row))?;
+            },
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<(W, u64)> {
+        if let ResultFormat::JsonArray = self.format {
+            let closing = if self.rows == 0 { "]\n" } else { "\n]\n" };
+            write!(self.out, "{}", closing)?;
+        }
+        self.out.flush()?;
+        Ok((self.out, self.rows))
+    }
+}
+
+// Rows held by a console result, used for the clipboard
+pub fn render(format: ResultFormat, result: &QueryResult) -> String {
+    let render = || -> io::Result<Vec<u8>> {
+        let mut writer = ResultWriter::new(vec![], format, result.columns.clone())?;
+        for row in &result.rows {
+            writer.write_row(row)?;
+        }
+        writer.finish().map(|(out, _)| out)
+    };
+    render()
+        .map(|out| String::from_utf8_lossy(&out).to_string())
+        .unwrap_or_default()
+}
+
+// What creating `path` would replace, if anything
+pub fn existing_file(path: &str) -> Option<String> {
+    let path = expand_home(path);
+    path.exists().then(|| format!("{} already exists", path.display()))
+}
+
+// An existing file is only replaced when `overwrite` is set
+pub fn create_file(path: &str, overwrite: bool) -> io::Result<(PathBuf, BufWriter<File>)> {
+    let path = expand_home(path);
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    let file = if overwrite {
+        File::create(&path)?
+    } else {
+        OpenOptions::new().write(true).create_new(true).open(&path)?
+    };
+    Ok((path, BufWriter::new(file)))
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn markdown_cell(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+// Built by hand to keep the column order, duplicate column names are kept as is
+fn json_object(columns: &[String], row: &[Option<String>]) -> String {
+    let fields: Vec<String> = columns
+        .iter()
+        .zip(row)
+        .map(|(column, value)| {
+            let value = match value {
+                Some(value) => serde_json::Value::String(value.clone()),
+                None => serde_json::Value::Null,
+            };
+            format!("{}:{}", serde_json::Value::String(column.clone()), value)
+        })
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
@@ -0,0 +1,320 @@
+use postgres::types::ToSql;
+
+use super::query::{mysql_value, postgres_error};
+
+// Where a lazily loaded node sits in the tree, also what gets fetched when it is expanded
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaPath {
+    Root,
+    Database(String),
+    Schema { database: String, schema: String },
+    Table { database: String, schema: String, table: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SchemaKind {
+    Database,
+    Schema,
+    Table,
+    View,
+    Group,
+    Column,
+    Index,
+    Constraint,
+}
+
+pub struct SchemaNode {
+    pub kind: SchemaKind,
+    pub name: String,
+    // Shown after the name, eg. a column's type
+    pub detail: String,
+    pub properties: Vec<(String, String)>,
+    // Set on nodes whose children are fetched on first expand
+    pub path: Option<SchemaPath>,
+    pub children: Option<Vec<SchemaNode>>,
+    pub expanded: bool,
+    pub loading: bool,
+    pub error: Option<String>,
+}
+
+impl SchemaNode {
+    fn new(kind: SchemaKind, name: &str) -> SchemaNode {
+        SchemaNode {
+            kind,
+            name: name.to_string(),
+            detail: String::new(),
+            properties: vec![],
+            path: None,
+            children: None,
+            expanded: false,
+            loading: false,
+            error: None,
+        }
+    }
+
+    fn lazy(kind: SchemaKind, name: &str, path: SchemaPath) -> SchemaNode {
+        let mut node = SchemaNode::new(kind, name);
+        node.path = Some(path);
+        node
+    }
+
+    fn group(name: &str, children: Vec<SchemaNode>) -> SchemaNode {
+        let mut node = SchemaNode::new(SchemaKind::Group, &format!("{} ({})", name, children.len()));
+        node.children = Some(children);
+        node
+    }
+
+    fn with_properties(mut self, properties: &[(&str, &str)]) -> SchemaNode {
+        self.properties = properties
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        self
+    }
+
+    pub fn is_expandable(&self) -> bool {
+        self.path.is_some() || self.children.is_some()
+    }
+}
+
+type Rows = Vec<Vec<Option<String>>>;
+
+fn text(row: &[Option<String>], index: usize) -> &str {
+    row.get(index).and_then(|value| value.as_deref()).unwrap_or_default()
+}
+
+// Postgres: databases, then schemas of the connected database, tables, and columns/indexes/constraints
+pub fn postgres_children(client: &mut postgres::Client, path: &SchemaPath) -> Result<Vec<SchemaNode>, String> {
+    match path {
+        SchemaPath::Root => {
+            let current = postgres_rows(client, "SELECT current_database()::text", &[])?;
+            let current = current.first().map(|row| text(row, 0).to_string()).unwrap_or_default();
+            let rows = postgres_rows(
+                client,
+                "SELECT datname::text FROM pg_database WHERE NOT datistemplate ORDER BY datname",
+                &[],
+            )?;
+            Ok(rows.iter().map(|row| {
+                let name = text(row, 0);
+                let connected = if name == current { "yes" } else { "no" };
+                SchemaNode::lazy(SchemaKind::Database, name, SchemaPath::Database(name.to_string()))
+                    .with_properties(&[("Database", name), ("Connected", connected)])
+            }).collect())
+        },
+        SchemaPath::Database(database) => {
+            let current = postgres_rows(client, "SELECT current_database()::text", &[])?;
+            if current.first().map(|row| text(row, 0)) != Some(database.as_str()) {
+                return Err(format!("connect with database name `{}` to browse it", database));
+            }
+            let rows = postgres_rows(
+                client,
+                "SELECT schema_name::text FROM information_schema.schemata \
+                 WHERE schema_name NOT IN ('pg_catalog', 'information_schema') \
+                 AND schema_name NOT LIKE 'pg\\_toast%' AND schema_name NOT LIKE 'pg\\_temp%' \
+                 ORDER BY schema_name",
+                &[],
+            )?;
+            Ok(rows.iter().map(|row| {
+                let schema = text(row, 0);
+                let path = SchemaPath::Schema { database: database.clone(), schema: schema.to_string() };
+                SchemaNode::lazy(SchemaKind::Schema, schema, path)
+                    .with_properties(&[("Schema", schema), ("Database", database)])
+            }).collect())
+        },
+        SchemaPath::Schema { database, schema } => {
+            let rows = postgres_rows(
+                client,
+                "SELECT c.relname::text, \
+                 CASE c.relkind WHEN 'v' THEN 'VIEW' WHEN 'm' THEN 'MATERIALIZED VIEW' \
+                 WHEN 'f' THEN 'FOREIGN TABLE' WHEN 'p' THEN 'PARTITIONED TABLE' ELSE 'TABLE' END, \
+                 CASE WHEN c.reltuples < 0 THEN NULL ELSE c.reltuples::bigint::text END, obj_description(c.oid, 'pg_class') \
+                 FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+                 WHERE n.nspname = $1 AND c.relkind IN ('r', 'p', 'v', 'm', 'f') \
+                 ORDER BY c.relname",
+                &[schema],
+            )?;
+            Ok(rows.iter().map(|row| table_node(database, schema, row)).collect())
+        },
+        SchemaPath::Table { schema, table, .. } => {
+            let columns = postgres_rows(
+                client,
+                "SELECT a.attname::text, format_type(a.atttypid, a.atttypmod), \
+                 CASE WHEN a.attnotnull THEN 'NO' ELSE 'YES' END, pg_get_expr(d.adbin, d.adrelid), \
+                 col_description(a.attrelid, a.attnum) \
+                 FROM pg_attribute a \
+                 JOIN pg_class c ON c.oid = a.attrelid JOIN pg_namespace n ON n.oid = c.relnamespace \
+                 LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum \
+                 WHERE n.nspname = $1 AND c.relname = $2 AND a.attnum > 0 AND NOT a.attisdropped \
+                 ORDER BY a.attnum",
+                &[schema, table],
+            )?;
+            let indexes = postgres_rows(
+                client,
+                "SELECT indexname::text, indexdef FROM pg_indexes \
+                 WHERE schemaname = $1 AND tablename = $2 ORDER BY indexname",
+                &[schema, table],
+            )?;
+            let constraints = postgres_rows(
+                client,
+                "SELECT con.conname::text, \
+                 CASE con.contype WHEN 'p' THEN 'PRIMARY KEY' WHEN 'f' THEN 'FOREIGN KEY' WHEN 'u' THEN 'UNIQUE' \
+                 WHEN 'c' THEN 'CHECK' WHEN 'x' THEN 'EXCLUDE' ELSE con.contype::text END, \
+                 pg_get_constraintdef(con.oid) \
+                 FROM pg_constraint con \
+                 JOIN pg_class c ON c.oid = con.conrelid JOIN pg_namespace n ON n.oid = c.relnamespace \
+                 WHERE n.nspname = $1 AND c.relname = $2 ORDER BY con.conname",
+                &[schema, table],
+            )?;
+            Ok(table_children(&columns, &indexes, &constraints))
+        },
+    }
+}
+
+// MySQL has no schema level, databases hold the tables directly
+pub fn mysql_children(conn: &mut mysql::Conn, path: &SchemaPath) -> Result<Vec<SchemaNode>, String> {
+    match path {
+        SchemaPath::Root => {
+            let rows = mysql_rows(
+                conn,
+                "SELECT schema_name FROM information_schema.schemata \
+                 WHERE schema_name NOT IN ('information_schema', 'mysql', 'performance_schema', 'sys') \
+                 ORDER BY schema_name",
+                vec![],
+            )?;
+            Ok(rows.iter().map(|row| {
+                let name = text(row, 0);
+                let path = SchemaPath::Schema { database: name.to_string(), schema: name.to_string() };
+                SchemaNode::lazy(SchemaKind::Database, name, path)
+                    .with_properties(&[("Database", name)])
+            }).collect())
+        },
+        SchemaPath::Database(database) => mysql_children(
+            conn,
+            &SchemaPath::Schema { database: database.clone(), schema: database.clone() },
+        ),
+        SchemaPath::Schema { database, schema } => {
+            let rows = mysql_rows(
+                conn,
+                "SELECT table_name, table_type, CAST(table_rows AS CHAR), table_comment \
+                 FROM information_schema.tables WHERE table_schema = ? ORDER BY table_name",
+                vec![schema.clone()],
+            )?;
+            Ok(rows.iter().map(|row| table_node(database, schema, row)).collect())
+        },
+        SchemaPath::Table { schema, table, .. } => {
+            let params = vec![schema.clone(), table.clone()];
+            let columns = mysql_rows(
+                conn,
+                "SELECT column_name, column_type, is_nullable, column_default, column_comment \
+                 FROM information_schema.columns WHERE table_schema = ? AND table_name = ? \
+                 ORDER BY ordinal_position",
+                params.clone(),
+            )?;
+            let indexes = mysql_rows(
+                conn,
+                "SELECT index_name, CONCAT(IF(MAX(non_unique) = 0, 'UNIQUE ', ''), index_type, ' (', \
+                 GROUP_CONCAT(column_name ORDER BY seq_in_index SEPARATOR ', '), ')') \
+                 FROM information_schema.statistics WHERE table_schema = ? AND table_name = ? \
+                 GROUP BY index_name, index_type ORDER BY index_name",
+                params.clone(),
+            )?;
+            let constraints = mysql_rows(
+                conn,
+                "SELECT tc.constraint_name, tc.constraint_type, CONCAT('(', \
+                 GROUP_CONCAT(k.column_name ORDER BY k.ordinal_position SEPARATOR ', '), ')', \
+                 IFNULL(CONCAT(' REFERENCES ', MAX(k.referenced_table_name), '(', \
+                 GROUP_CONCAT(k.referenced_column_name ORDER BY k.ordinal_position SEPARATOR ', '), ')'), '')) \
+                 FROM information_schema.table_constraints tc \
+                 LEFT JOIN information_schema.key_column_usage k ON k.constraint_schema = tc.constraint_schema \
+                 AND k.constraint_name = tc.constraint_name AND k.table_name = tc.table_name \
+                 WHERE tc.table_schema = ? AND tc.table_name = ? \
+                 GROUP BY tc.constraint_name, tc.constraint_type ORDER BY tc.constraint_name",
+                params,
+            )?;
+            Ok(table_children(&columns, &indexes, &constraints))
+        },
+    }
+}
+
+// Rows are (name, type, estimated rows, comment)
+fn table_node(database: &str, schema: &str, row: &[Option<String>]) -> SchemaNode {
+    let (table, table_type) = (text(row, 0), text(row, 1));
+    let kind = if table_type.contains("VIEW") { SchemaKind::View } else { SchemaKind::Table };
+    let path = SchemaPath::Table {
+        database: database.to_string(),
+        schema: schema.to_string(),
+        table: table.to_string(),
+    };
+    let mut node = SchemaNode::lazy(kind, table, path).with_properties(&[
+        ("Name", table),
+        ("Schema", schema),
+        ("Type", table_type),
+        ("Estimated rows", text(row, 2)),
+        ("Comment", text(row, 3)),
+    ]);
+    if kind == SchemaKind::View {
+        node.detail = table_type.to_lowercase();
+    }
+    node
+}
+
+// Columns are (name, type, nullable, default, comment), indexes (name, definition)
+// and constraints (name, type, definition)
+fn table_children(columns: &Rows, indexes: &Rows, constraints: &Rows) -> Vec<SchemaNode> {
+    let columns = columns.iter().map(|row| {
+        let mut node = SchemaNode::new(SchemaKind::Column, text(row, 0)).with_properties(&[
+            ("Column", text(row, 0)),
+            ("Type", text(row, 1)),
+            ("Nullable", text(row, 2)),
+            ("Default", text(row, 3)),
+            ("Comment", text(row, 4)),
+        ]);
+        node.detail = text(row, 1).to_string();
+        node
+    }).collect();
+    let indexes = indexes.iter().map(|row| {
+        SchemaNode::new(SchemaKind::Index, text(row, 0))
+            .with_properties(&[("Index", text(row, 0)), ("Definition", text(row, 1))])
+    }).collect();
+    let constraints = constraints.iter().map(|row| {
+        let mut node = SchemaNode::new(SchemaKind::Constraint, text(row, 0)).with_properties(&[
+            ("Constraint", text(row, 0)),
+            ("Type", text(row, 1)),
+            ("Definition", text(row, 2)),
+        ]);
+        node.detail = text(row, 1).to_lowercase();
+        node
+    }).collect();
+
+    vec![
+        SchemaNode::group("Columns", columns),
+        SchemaNode::group("Indexes", indexes),
+        SchemaNode::group("Constraints", constraints),
+    ]
+}
+
+// Every selected column is cast to text so values come back as optional strings
+pub(crate) fn postgres_rows(client: &mut postgres::Client, sql: &str, params: &[&String]) -> Result<Rows, String> {
+    let params: Vec<&(dyn ToSql + Sync)> = params.iter().map(|param| *param as &(dyn ToSql + Sync)).collect();
+    let rows = client.query(sql, &params).map_err(|err| postgres_error(&err))?;
+    Ok(rows
+        .iter()
+        .map(|row| (0..row.len()).map(|index| row.get::<_, Option<String>>(index)).collect())
+        .collect())
+}
+
+pub(crate) fn mysql_rows(conn: &mut mysql::Conn, sql: &str, params: Vec<String>) -> Result<Rows, String> {
+    use mysql::prelude::Queryable;
+
+    let params = if params.is_empty() {
+        mysql::Params::Empty
+    } else {
+        mysql::Params::Positional(params.into_iter().map(mysql::Value::from).collect())
+    };
+    let rows: Vec<mysql::Row> = conn.exec(sql, params).map_err(|err| err.to_string())?;
+    Ok(rows
+        .into_iter()
+        .map(|row| row.unwrap_raw().into_iter().map(|value| value.and_then(mysql_value)).collect())
+        .collect())
+}
@@ -0,0 +1,378 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::mpsc::{channel, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{event, Level};
+
+use super::{
+    fanout::FanoutTarget,
+    hooks::{run_hooks, Hook, HookContext, HookStage},
+    query::{connect, Client},
+    schema::{mysql_rows, postgres_rows},
+    tsh::ConnectionArgs,
+};
+
+// Relations outside the system schemas, shared by every PostgreSQL catalog query
+const POSTGRES_RELATION_FILTER: &str = "c.relkind IN ('r', 'p', 'v', 'm', 'f') \
+    AND n.nspname NOT IN ('pg_catalog', 'information_schema') \
+    AND n.nspname NOT LIKE 'pg\\_toast%' AND n.nspname NOT LIKE 'pg\\_temp%'";
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MemberKind {
+    Column,
+    Index,
+    Constraint,
+}
+
+impl MemberKind {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            MemberKind::Column => "column",
+            MemberKind::Index => "index",
+            MemberKind::Constraint => "constraint",
+        }
+    }
+}
+
+// A table or view with its columns, indexes and constraints keyed by kind and name,
+// values are the definitions that get compared
+#[derive(Default)]
+pub struct TableSchema {
+    pub kind: String,
+    pub members: BTreeMap<(MemberKind, String), String>,
+}
+
+// Tables of the connected database, keyed by `schema.table` on PostgreSQL and by table
+// name on MySQL where the schema is the database itself
+#[derive(Default)]
+pub struct SchemaCatalog {
+    pub tables: BTreeMap<String, TableSchema>,
+}
+
+impl SchemaCatalog {
+    fn member(&mut self, table: &str, kind: MemberKind, name: &str, definition: &str) {
+        if let Some(schema) = self.tables.get_mut(table) {
+            schema.members.insert((kind, name.to_string()), definition.to_string());
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DiffKind {
+    Same,
+    // Only on the right side
+    Added,
+    // Only on the left side
+    Removed,
+    Changed,
+}
+
+impl DiffKind {
+    pub fn marker(self) -> &'static str {
+        match self {
+            DiffKind::Same => " ",
+            DiffKind::Added => "+",
+            DiffKind::Removed => "-",
+            DiffKind::Changed => "~",
+        }
+    }
+}
+
+// One table or member, with its definition on either side
+pub struct DiffLine {
+    pub depth: usize,
+    pub kind: DiffKind,
+    pub label: String,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+pub struct SchemaDiff {
+    pub lines: Vec<DiffLine>,
+}
+
+impl SchemaDiff {
+    // Tables present on both sides are changed when any of their members differs
+    pub fn compute(left: &SchemaCatalog, right: &SchemaCatalog) -> SchemaDiff {
+        let empty = TableSchema::default();
+        let names: BTreeSet<&String> = left.tables.keys().chain(right.tables.keys()).collect();
+
+        let mut lines = vec![];
+        for name in names {
+            let (left_table, right_table) = (left.tables.get(name), right.tables.get(name));
+            let members: BTreeSet<&(MemberKind, String)> = left_table
+                .unwrap_or(&empty)
+                .members
+                .keys()
+                .chain(right_table.unwrap_or(&empty).members.keys())
+                .collect();
+
+            let mut member_lines: Vec<DiffLine> = members
+                .into_iter()
+                .map(|key| {
+                    let left = left_table.and_then(|table| table.members.get(key)).cloned();
+                    let right = right_table.and_then(|table| table.members.get(key)).cloned();
+                    DiffLine {
+                        depth: 1,
+                        kind: diff_kind(&left, &right),
+                        label: format!("{} {}", key.0.to_string(), key.1),
+                        left,
+                        right,
+                    }
+                })
+                .collect();
+
+            let left_kind = left_table.map(|table| table.kind.clone());
+            let right_kind = right_table.map(|table| table.kind.clone());
+            let kind = match diff_kind(&left_kind, &right_kind) {
+                DiffKind::Same if member_lines.iter().any(|line| line.kind != DiffKind::Same) => DiffKind::Changed,
+                kind => kind,
+            };
+            lines.push(DiffLine {
+                depth: 0,
+                kind,
+                label: name.clone(),
+                left: left_kind,
+                right: right_kind,
+            });
+            lines.append(&mut member_lines);
+        }
+        SchemaDiff { lines }
+    }
+
+    pub fn is_identical(&self) -> bool {
+        self.lines.iter().all(|line| line.kind == DiffKind::Same)
+    }
+
+    // Members are only counted for tables found on both sides
+    pub fn summary(&self) -> String {
+        let tables: Vec<&DiffLine> = self.lines.iter().filter(|line| line.depth == 0).collect();
+        let members: Vec<&DiffLine> = self.changed_members().collect();
+        let count = |lines: &[&DiffLine], kind: DiffKind| lines.iter().filter(|line| line.kind == kind).count();
+        if self.is_identical() {
+            return format!("{} tables, no differences", tables.len());
+        }
+        format!(
+            "tables: {} added, {} removed, {} changed · members: {} added, {} removed, {} changed",
+            count(&tables, DiffKind::Added),
+            count(&tables, DiffKind::Removed),
+            count(&tables, DiffKind::Changed),
+            count(&members, DiffKind::Added),
+            count(&members, DiffKind::Removed),
+            count(&members, DiffKind::Changed),
+        )
+    }
+
+    // Lines of tables that differ, without the members of tables found on one side only
+    pub fn differences(&self) -> impl Iterator<Item = &DiffLine> {
+        let mut table_kind = DiffKind::Same;
+        self.lines.iter().filter(move |line| {
+            if line.depth == 0 {
+                table_kind = line.kind;
+            }
+            line.kind != DiffKind::Same && (line.depth == 0 || table_kind == DiffKind::Changed)
+        })
+    }
+
+    fn changed_members(&self) -> impl Iterator<Item = &DiffLine> {
+        self.differences().filter(|line| line.depth > 0)
+    }
+
+    // Plain text with one marker per line, only what differs between both sides
+    pub fn report(&self, left: &str, right: &str, generated: &str) -> String {
+        let mut report = format!("Schema diff\n- left:  {}\n+ right: {}\ngenerated {}\n{}\n\n", left, right, generated, self.summary());
+
+        for line in self.differences() {
+            let indent = "    ".repeat(line.depth);
+            let (marker, label) = (line.kind.marker(), &line.label);
+            let text = match (line.kind, &line.left, &line.right) {
+                (DiffKind::Changed, Some(left), Some(right)) if left != right => {
+                    format!("{}{} {}\n{}    - {}\n{}    + {}", indent, marker, label, indent, left, indent, right)
+                },
+                (_, Some(definition), None) | (_, None, Some(definition)) => {
+                    format!("{}{} {}: {}", indent, marker, label, definition)
+                },
+                _ => format!("{}{} {}", indent, marker, label),
+            };
+            report.push_str(&text);
+            report.push('\n');
+        }
+        report
+    }
+}
+
+fn diff_kind(left: &Option<String>, right: &Option<String>) -> DiffKind {
+    match (left, right) {
+        (Some(left), Some(right)) if left == right => DiffKind::Same,
+        (Some(_), Some(_)) => DiffKind::Changed,
+        (Some(_), None) => DiffKind::Removed,
+        (None, Some(_)) => DiffKind::Added,
+        (None, None) => DiffKind::Same,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DiffSide {
+    Left,
+    Right,
+}
+
+pub struct CatalogOutcome {
+    pub side: DiffSide,
+    pub args: ConnectionArgs,
+    pub result: Result<SchemaCatalog, String>,
+    pub elapsed: Duration,
+}
+
+// Reads both catalogs at the same time, each through its own tunnel,
+// dropping the run only discards the results
+pub struct SchemaDiffRun {
+    events: Receiver<CatalogOutcome>,
+}
+
+impl SchemaDiffRun {
+    pub fn start(left: FanoutTarget, right: FanoutTarget, hooks: Vec<Hook>) -> SchemaDiffRun {
+        let (sender, events) = channel();
+        for (side, target) in [(DiffSide::Left, left), (DiffSide::Right, right)] {
+            let (sender, hooks) = (sender.clone(), hooks.clone());
+            thread::spawn(move || {
+                let _ = sender.send(read_target(side, &target, &hooks));
+            });
+        }
+        SchemaDiffRun { events }
+    }
+
+    pub fn try_event(&self) -> Option<CatalogOutcome> {
+        self.events.try_recv().ok()
+    }
+}
+
+fn read_target(side: DiffSide, target: &FanoutTarget, hooks: &[Hook]) -> CatalogOutcome {
+    let started = Instant::now();
+    let mut context = HookContext {
+        args: &target.args,
+        database: Some(&target.database),
+        method: "schema_diff",
+        exit_status: None,
+    };
+
+    let result = match run_hooks(hooks, HookStage::Pre, &context) {
+        Ok(()) => {
+            let result = read_catalog(target);
+            context.exit_status = Some(if result.is_ok() { 0 } else { 1 });
+            if let Err(err) = run_hooks(hooks, HookStage::Post, &context) {
+                event!(Level::ERROR, "post-connect hook failed for {}: {}", target.args.instance, err);
+            }
+            result
+        },
+        Err(err) => Err(format!("pre-connect hook failed: {}", err)),
+    };
+
+    CatalogOutcome {
+        side,
+        args: target.args.clone(),
+        result,
+        elapsed: started.elapsed(),
+    }
+}
+
+fn read_catalog(target: &FanoutTarget) -> Result<SchemaCatalog, String> {
+    // The tunnel is kept until the catalog is read
+    let (client, _tunnel) = connect(&target.args, target.kind)?;
+    match client {
+        Client::Postgres(mut client) => postgres_catalog(&mut client),
+        Client::MySql(mut conn) => mysql_catalog(&mut conn),
+    }
+}
+
+fn postgres_catalog(client: &mut postgres::Client) -> Result<SchemaCatalog, String> {
+    let relation = "n.nspname || '.' || c.relname";
+    let tables = postgres_rows(client, &format!(
+        "SELECT {}, CASE c.relkind WHEN 'v' THEN 'view' WHEN 'm' THEN 'materialized view' \
+         WHEN 'f' THEN 'foreign table' ELSE 'table' END \
+         FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace WHERE {}",
+        relation, POSTGRES_RELATION_FILTER,
+    ), &[])?;
+    let columns = postgres_rows(client, &format!(
+        "SELECT {}, a.attname::text, format_type(a.atttypid, a.atttypmod) \
+         || CASE WHEN a.attnotnull THEN ' NOT NULL' ELSE '' END \
+         || coalesce(' DEFAULT ' || pg_get_expr(d.adbin, d.adrelid), '') \
+         FROM pg_attribute a JOIN pg_class c ON c.oid = a.attrelid JOIN pg_namespace n ON n.oid = c.relnamespace \
+         LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum \
+         WHERE a.attnum > 0 AND NOT a.attisdropped AND {}",
+        relation, POSTGRES_RELATION_FILTER,
+    ), &[])?;
+    // The index definition without its name and table, eg. `UNIQUE btree (email)`
+    let indexes = postgres_rows(client, &format!(
+        "SELECT {}, i.relname::text, CASE WHEN x.indisunique THEN 'UNIQUE ' ELSE '' END \
+         || substring(pg_get_indexdef(x.indexrelid) from ' USING (.*)$') \
+         FROM pg_index x JOIN pg_class i ON i.oid = x.indexrelid JOIN pg_class c ON c.oid = x.indrelid \
+         JOIN pg_namespace n ON n.oid = c.relnamespace WHERE {}",
+        relation, POSTGRES_RELATION_FILTER,
+    ), &[])?;
+    let constraints = postgres_rows(client, &format!(
+        "SELECT {}, con.conname::text, pg_get_constraintdef(con.oid) \
+         FROM pg_constraint con JOIN pg_class c ON c.oid = con.conrelid \
+         JOIN pg_namespace n ON n.oid = c.relnamespace WHERE {}",
+        relation, POSTGRES_RELATION_FILTER,
+    ), &[])?;
+    Ok(build_catalog(tables, columns, indexes, constraints))
+}
+
+fn mysql_catalog(conn: &mut mysql::Conn) -> Result<SchemaCatalog, String> {
+    let tables = mysql_rows(
+        conn,
+        "SELECT table_name, IF(table_type = 'VIEW', 'view', 'table') \
+         FROM information_schema.tables WHERE table_schema = DATABASE()",
+        vec![],
+    )?;
+    let columns = mysql_rows(
+        conn,
+        "SELECT table_name, column_name, CONCAT(column_type, IF(is_nullable = 'NO', ' NOT NULL', ''), \
+         IFNULL(CONCAT(' DEFAULT ', column_default), '')) \
+         FROM information_schema.columns WHERE table_schema = DATABASE()",
+        vec![],
+    )?;
+    let indexes = mysql_rows(
+        conn,
+        "SELECT table_name, index_name, CONCAT(IF(non_unique = 0, 'UNIQUE ', ''), index_type, ' (', \
+         GROUP_CONCAT(column_name ORDER BY seq_in_index SEPARATOR ', '), ')') \
+         FROM information_schema.statistics WHERE table_schema = DATABASE() \
+         GROUP BY table_name, index_name, non_unique, index_type",
+        vec![],
+    )?;
+    let constraints = mysql_rows(
+        conn,
+        "SELECT tc.table_name, tc.constraint_name, CONCAT(tc.constraint_type, ' (', \
+         IFNULL(GROUP_CONCAT(k.column_name ORDER BY k.ordinal_position SEPARATOR ', '), ''), ')', \
+         IFNULL(CONCAT(' REFERENCES ', MAX(k.referenced_table_name)), '')) \
+         FROM information_schema.table_constraints tc \
+         LEFT JOIN information_schema.key_column_usage k ON k.constraint_schema = tc.constraint_schema \
+         AND k.constraint_name = tc.constraint_name AND k.table_name = tc.table_name \
+         WHERE tc.table_schema = DATABASE() \
+         GROUP BY tc.table_name, tc.constraint_name, tc.constraint_type",
+        vec![],
+    )?;
+    Ok(build_catalog(tables, columns, indexes, constraints))
+}
+
+type Rows = Vec<Vec<Option<String>>>;
+
+// Every query returns the table first, then the name and definition of the member
+fn build_catalog(tables: Rows, columns: Rows, indexes: Rows, constraints: Rows) -> SchemaCatalog {
+    let text = |row: &[Option<String>], index: usize| row.get(index).cloned().flatten().unwrap_or_default();
+
+    let mut catalog = SchemaCatalog::default();
+    for row in &tables {
+        let table = TableSchema { kind: text(row, 1), members: BTreeMap::new() };
+        catalog.tables.insert(text(row, 0), table);
+    }
+    for (kind, rows) in [(MemberKind::Column, columns), (MemberKind::Index, indexes), (MemberKind::Constraint, constraints)] {
+        for row in &rows {
+            catalog.member(&text(row, 0), kind, &text(row, 1), &text(row, 2));
+        }
+    }
+    catalog
+}
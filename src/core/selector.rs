@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Deserializer};
+
+#[derive(Clone)]
+enum Requirement {
+    Equals(String, String),
+    NotEquals(String, String),
+    Exists(String),
+}
+
+// Comma separated label requirements, eg. `env=prod,team!=billing,tier`
+#[derive(Clone)]
+pub struct LabelSelector {
+    raw: String,
+    requirements: Vec<Requirement>,
+}
+
+impl LabelSelector {
+    pub fn parse(raw: &str) -> Result<LabelSelector, String> {
+        let mut requirements = vec![];
+        for part in raw.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            let requirement = if let Some((key, value)) = part.split_once("!=") {
+                Requirement::NotEquals(key.trim().to_string(), value.trim().to_string())
+            } else if let Some((key, value)) = part.split_once('=') {
+                Requirement::Equals(key.trim().to_string(), value.trim().to_string())
+            } else {
+                Requirement::Exists(part.to_string())
+            };
+
+            let key = match &requirement {
+                Requirement::Equals(key, _) | Requirement::NotEquals(key, _) | Requirement::Exists(key) => key,
+            };
+            if key.is_empty() {
+                return Err(format!("invalid label selector `{}`", raw));
+            }
+            requirements.push(requirement);
+        }
+
+        Ok(LabelSelector {
+            raw: raw.to_string(),
+            requirements,
+        })
+    }
+
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        self.requirements.iter().all(|requirement| match requirement {
+            Requirement::Equals(key, value) => labels.get(key) == Some(value),
+            Requirement::NotEquals(key, value) => labels.get(key) != Some(value),
+            Requirement::Exists(key) => labels.contains_key(key),
+        })
+    }
+
+    pub fn to_string(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl std::fmt::Debug for LabelSelector {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "LabelSelector({})", self.raw)
+    }
+}
+
+impl<'de> Deserialize<'de> for LabelSelector {
+    fn deserialize<D>(deserializer: D) -> Result<LabelSelector, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+        LabelSelector::parse(&raw).map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    io,
+    process::{Child, Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+use chrono::Local;
+use serde::Deserialize;
+use tracing::{event, Level};
+
+use super::{selector::LabelSelector, tsh::Database};
+
+// Used when a limit does not set `warn_before`
+const DEFAULT_WARN_BEFORE: Duration = Duration::from_secs(5 * 60);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+// Maximum duration of kith-managed sessions to instances matching a label selector
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionLimit {
+    pub selector: LabelSelector,
+    pub max_duration: String,
+    pub warn_before: Option<String>,
+}
+
+impl SessionLimit {
+    pub fn validate(&self) -> Result<(), String> {
+        let max_duration = parse_duration(&self.max_duration)?;
+        if let Some(warn_before) = &self.warn_before {
+            if parse_duration(warn_before)? >= max_duration {
+                return Err("warn_before must be shorter than max_duration".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn max_duration(&self) -> Duration {
+        parse_duration(&self.max_duration).unwrap_or_default()
+    }
+
+    // Never warns before the session even started, short limits warn at half time
+    pub fn warn_before(&self) -> Duration {
+        match self.warn_before.as_deref().map(parse_duration) {
+            Some(Ok(warn_before)) => warn_before,
+            _ => DEFAULT_WARN_BEFORE.min(self.max_duration() / 2),
+        }
+    }
+}
+
+pub fn find_session_limit<'a>(limits: &'a [SessionLimit], database: &Database) -> Option<&'a SessionLimit> {
+    limits
+        .iter()
+        .find(|limit| limit.selector.matches(&database.metadata.labels))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SessionDeadline {
+    pub max_duration: Duration,
+    started_at: Instant,
+    warn_before: Duration,
+}
+
+impl SessionDeadline {
+    pub fn start(limit: &SessionLimit) -> SessionDeadline {
+        SessionDeadline {
+            max_duration: limit.max_duration(),
+            started_at: Instant::now(),
+            warn_before: limit.warn_before(),
+        }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.max_duration.saturating_sub(self.started_at.elapsed())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    pub fn in_warning(&self) -> bool {
+        self.remaining() <= self.warn_before
+    }
+
+    // Wall clock time for display, eg. in a prompt
+    pub fn ends_at(&self) -> String {
+        let remaining = chrono::Duration::from_std(self.remaining()).unwrap_or_default();
+        (Local::now() + remaining).format("%H:%M").to_string()
+    }
+}
+
+pub struct SessionOutcome {
+    pub status: ExitStatus,
+    pub expired: bool,
+}
+
+// Waits on a session that owns the terminal, warnings are written straight to it
+pub fn wait_with_deadline(mut child: Child, deadline: Option<SessionDeadline>) -> io::Result<SessionOutcome> {
+    let Some(deadline) = deadline else {
+        let status = child.wait()?;
+        return Ok(SessionOutcome { status, expired: false });
+    };
+
+    eprintln!(
+        "kith: this session is limited to {} and ends at {}.",
+        format_duration(deadline.max_duration),
+        deadline.ends_at(),
+    );
+
+    let mut warned = false;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(SessionOutcome { status, expired: false });
+        }
+        if deadline.is_expired() {
+            eprintln!("\r\nkith: session time limit reached, terminating.\r");
+            event!(Level::INFO, "session limit reached, terminating pid {}", child.id());
+            terminate_descendants(child.id());
+            let _ = child.kill();
+            let status = child.wait()?;
+            return Ok(SessionOutcome { status, expired: true });
+        }
+        if !warned && deadline.in_warning() {
+            eprintln!("\r\nkith: session ends in {}.\r", format_duration(deadline.remaining()));
+            warned = true;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// The session's shell or client spawns `tsh` and database clients, those are stopped first
+pub fn terminate_descendants(pid: u32) {
+    for child_pid in child_pids(pid) {
+        terminate_descendants(child_pid);
+        let result = Command::new("kill")
+            .args(["-TERM", &child_pid.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        if let Err(err) = result {
+            event!(Level::ERROR, "failed to terminate pid {}: {}", child_pid, err);
+        }
+    }
+}
+
+fn child_pids(pid: u32) -> Vec<u32> {
+    let output = Command::new("pgrep")
+        .args(["-P", &pid.to_string()])
+        .stderr(Stdio::null())
+        .output();
+
+    match output {
+        Ok(output) => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .collect(),
+        Err(err) => {
+            event!(Level::ERROR, "failed to list child processes of {}: {}", pid, err);
+            vec![]
+        },
+    }
+}
+
+// Accepts `90s`, `30m`, `1h` and combinations such as `1h30m`
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let mut total: u64 = 0;
+    let mut digits = String::new();
+
+    for character in value.trim().chars() {
+        if character.is_ascii_digit() {
+            digits.push(character);
+            continue;
+        }
+        let amount: u64 = digits.parse().map_err(|_| format!("invalid duration `{}`", value))?;
+        let unit = match character {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(format!("invalid duration unit `{}` in `{}`", character, value)),
+        };
+        total = amount
+            .checked_mul(unit)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or(format!("duration `{}` is too long", value))?;
+        digits.clear();
+    }
+
+    if !digits.is_empty() || total == 0 {
+        return Err(format!("invalid duration `{}`, use eg. `30m` or `1h30m`", value));
+    }
+    Ok(Duration::from_secs(total))
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        3600.. => format!("{}h{:02}m", seconds / 3600, (seconds % 3600) / 60),
+        60.. => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}s", seconds),
+    }
+}
@@ -0,0 +1,242 @@
+use std::{collections::HashMap, fs, io, path::PathBuf};
+use serde::Deserialize;
+
+use super::{query::DatabaseKind, selector::LabelSelector, tsh::Database};
+use crate::config::config_file_path;
+
+// A named SQL statement, `${name}` and `${name:default}` placeholders are asked for before it runs
+#[derive(Debug, Clone, Deserialize)]
+pub struct Snippet {
+    pub name: String,
+    pub description: Option<String>,
+    pub selector: Option<LabelSelector>,
+    pub instance: Option<String>,
+    pub sql: String,
+    // Declared types of the parameters, undeclared ones are text
+    #[serde(default)]
+    pub params: HashMap<String, ParameterType>,
+}
+
+// How a value is written into the statement, it is always a single literal or identifier
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParameterType {
+    #[default]
+    Text,
+    Integer,
+    Number,
+    Boolean,
+    Identifier,
+}
+
+impl ParameterType {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            ParameterType::Text => "text",
+            ParameterType::Integer => "integer",
+            ParameterType::Number => "number",
+            ParameterType::Boolean => "boolean",
+            ParameterType::Identifier => "identifier",
+        }
+    }
+
+    pub fn literal(self, value: &str, kind: DatabaseKind) -> Result<String, String> {
+        let trimmed = value.trim();
+        match self {
+            ParameterType::Text => text_literal(value, kind),
+            ParameterType::Integer => trimmed
+                .parse::<i64>()
+                .map(|number| number.to_string())
+                .map_err(|_| format!("`{}` is not an integer", value)),
+            // Checked character by character too, Rust also parses `inf` and `NaN`
+            ParameterType::Number => {
+                let plain = trimmed.chars().all(|character| character.is_ascii_digit() || "+-.eE".contains(character));
+                match trimmed.parse::<f64>() {
+                    Ok(number) if plain && number.is_finite() => Ok(trimmed.to_string()),
+                    _ => Err(format!("`{}` is not a number", value)),
+                }
+            },
+            ParameterType::Boolean => match trimmed.to_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Ok("TRUE".to_string()),
+                "false" | "no" | "off" | "0" => Ok("FALSE".to_string()),
+                _ => Err(format!("`{}` is not a boolean", value)),
+            },
+            ParameterType::Identifier => identifier(value, kind),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnippetParameter {
+    pub name: String,
+    pub default: Option<String>,
+    pub parameter_type: ParameterType,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SnippetFile {
+    #[serde(default)]
+    snippets: Vec<Snippet>,
+}
+
+impl Snippet {
+    // Snippets without an instance or a selector apply everywhere
+    pub fn applies_to(&self, database: &Database) -> bool {
+        if let Some(instance) = &self.instance {
+            if *instance != database.metadata.name {
+                return false;
+            }
+        }
+        match &self.selector {
+            Some(selector) => selector.matches(&database.metadata.labels),
+            None => true,
+        }
+    }
+
+    pub fn scope(&self) -> String {
+        match (&self.instance, &self.selector) {
+            (Some(instance), _) => format!("instance {}", instance),
+            (None, Some(selector)) => format!("selector {}", selector.to_string()),
+            (None, None) => "global".to_string(),
+        }
+    }
+
+    // Each parameter once, in order of first use
+    pub fn parameters(&self) -> Vec<SnippetParameter> {
+        let mut parameters: Vec<SnippetParameter> = vec![];
+        for (_, parameter) in self.placeholders() {
+            if !parameters.iter().any(|existing| existing.name == parameter.name) {
+                parameters.push(parameter);
+            }
+        }
+        parameters
+    }
+
+    // Values become literals of their declared type, escaped for the database they run on,
+    // so a placeholder must not be quoted in the snippet itself
+    pub fn render(&self, values: &HashMap<String, String>, kind: DatabaseKind) -> Result<String, String> {
+        let mut rendered = String::new();
+        let mut rest = self.sql.as_str();
+        for (range, parameter) in self.placeholders() {
+            let offset = self.sql.len() - rest.len();
+            rendered.push_str(&rest[..range.0 - offset]);
+            let value = values
+                .get(&parameter.name)
+                .or(parameter.default.as_ref())
+                .ok_or(format!("missing value for `{}`", parameter.name))?;
+            let literal = parameter.parameter_type
+                .literal(value, kind)
+                .map_err(|err| format!("invalid value for `{}`: {}", parameter.name, err))?;
+            rendered.push_str(&literal);
+            rest = &self.sql[range.1..];
+        }
+        rendered.push_str(rest);
+        Ok(rendered)
+    }
+
+    fn placeholders(&self) -> Vec<((usize, usize), SnippetParameter)> {
+        let mut found = placeholders(&self.sql);
+        for (_, parameter) in found.iter_mut() {
+            parameter.parameter_type = self.params.get(&parameter.name).copied().unwrap_or_default();
+        }
+        found
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() || self.sql.trim().is_empty() {
+            return Err("snippets need a name and sql".to_string());
+        }
+        let placeholders = placeholders(&self.sql);
+        for name in self.params.keys() {
+            if !placeholders.iter().any(|(_, parameter)| parameter.name == *name) {
+                return Err(format!("snippet `{}` declares a type for `{}`, which it does not use", self.name, name));
+            }
+        }
+        Ok(())
+    }
+}
+
+// PostgreSQL reads backslashes literally in plain strings and as escapes in `E''` strings,
+// MySQL reads them as escapes unless NO_BACKSLASH_ESCAPES is set, doubled quotes work in both
+fn text_literal(value: &str, kind: DatabaseKind) -> Result<String, String> {
+    if value.contains('\0') {
+        return Err("text values cannot contain NUL characters".to_string());
+    }
+    let quoted = value.replace('\'', "''");
+    Ok(match kind {
+        DatabaseKind::Postgres if value.contains('\\') => format!("E'{}'", quoted.replace('\\', "\\\\")),
+        DatabaseKind::Postgres => format!("'{}'", quoted),
+        DatabaseKind::MySql => format!("'{}'", quoted.replace('\\', "\\\\")),
+    })
+}
+
+fn identifier(value: &str, kind: DatabaseKind) -> Result<String, String> {
+    if value.is_empty() || value.contains('\0') {
+        return Err("identifiers cannot be empty or contain NUL characters".to_string());
+    }
+    Ok(match kind {
+        DatabaseKind::Postgres => format!("\"{}\"", value.replace('"', "\"\"")),
+        DatabaseKind::MySql => format!("`{}`", value.replace('`', "``")),
+    })
+}
+
+// Byte ranges of `${...}` placeholders with a valid name, anything else is left as SQL
+fn placeholders(sql: &str) -> Vec<((usize, usize), SnippetParameter)> {
+    let mut found = vec![];
+    let mut search_from = 0;
+    while let Some(start) = sql[search_from..].find("${").map(|index| index + search_from) {
+        let Some(end) = sql[start..].find('}').map(|index| index + start) else {
+            break;
+        };
+        let inner = &sql[start + 2..end];
+        let (name, default) = match inner.split_once(':') {
+            Some((name, default)) => (name, Some(default.to_string())),
+            None => (inner, None),
+        };
+        if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            let parameter = SnippetParameter {
+                name: name.to_string(),
+                default,
+                parameter_type: ParameterType::default(),
+            };
+            found.push(((start, end + 1), parameter));
+            search_from = end + 1;
+        } else {
+            search_from = start + 2;
+        }
+    }
+    found
+}
+
+// Kept next to `config.toml` so snippets can be shared along with it
+pub fn snippets_path() -> Option<PathBuf> {
+    config_file_path()?
+        .parent()
+        .map(|dir| dir.join("snippets.toml"))
+}
+
+// A missing file is an empty library
+pub fn load_snippets() -> Result<Vec<Snippet>, String> {
+    let Some(path) = snippets_path() else {
+        return Ok(vec![]);
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(format!("failed to read {}: {}", path.display(), err)),
+    };
+    let file = toml::from_str::<SnippetFile>(&contents)
+        .map_err(|err| format!("invalid snippets file {}: {}", path.display(), err.message()))?;
+
+    for snippet in &file.snippets {
+        snippet.validate().map_err(|err| format!("invalid snippets file {}: {}", path.display(), err))?;
+    }
+    Ok(file.snippets)
+}
+
+pub fn snippets_for<'a>(snippets: &'a [Snippet], database: &Database) -> Vec<&'a Snippet> {
+    snippets
+        .iter()
+        .filter(|snippet| snippet.applies_to(database))
+        .collect()
+}
@@ -0,0 +1,187 @@
+use std::{
+    env,
+    fs,
+    io,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+use tracing::{event, Level};
+
+use super::{
+    audit::AuditEntry,
+    db_config::DbConfig,
+    pty_session::run_recorded,
+    recording::Recording,
+    session_limit::{wait_with_deadline, SessionDeadline, SessionOutcome},
+    tsh::{ConnectionArgs, Tsh},
+};
+
+impl Tsh {
+    // Prefers `tsh db env`, falling back to the variables derived from `tsh db config`
+    pub fn read_db_env(&self, args: &ConnectionArgs, protocol: &str, login: &AuditEntry) -> Option<Vec<(String, String)>> {
+        event!(Level::DEBUG, "reading database environment for: {}", args.instance);
+
+        if !Tsh::audited_db_login(args, login) {
+            return None;
+        }
+
+        let db_user = format!("--db-user={}", args.db_user);
+        let db_name = format!("--db-name={}", args.db_name);
+
+        let teleport_cmd = Command::new("tsh")
+            .args(["db", "env", &db_user, &db_name, &args.instance])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        if let Ok(output) = teleport_cmd {
+            if output.status.success() {
+                let variables = parse_exports(&String::from_utf8_lossy(&output.stdout));
+                if !variables.is_empty() {
+                    return Some(variables);
+                }
+            } else {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                event!(Level::DEBUG, "teleport db env failed, deriving from db config: {}", stderr);
+            }
+        }
+
+        let config = Tsh::fetch_db_config(args, None)?;
+        Some(config_variables(&config, protocol))
+    }
+}
+
+// Variables the protocol's own client reads, the MySQL client has none for the user, database or certificates
+fn config_variables(config: &DbConfig, protocol: &str) -> Vec<(String, String)> {
+    let variables = match protocol {
+        "postgres" | "cockroachdb" => vec![
+            ("PGHOST", config.host.clone()),
+            ("PGPORT", config.port.to_string()),
+            ("PGUSER", config.user.clone()),
+            ("PGDATABASE", config.database.clone()),
+            ("PGSSLMODE", "verify-full".to_string()),
+            ("PGSSLROOTCERT", config.ca.clone()),
+            ("PGSSLCERT", config.cert.clone()),
+            ("PGSSLKEY", config.key.clone()),
+        ],
+        "mysql" => vec![
+            ("MYSQL_HOST", config.host.clone()),
+            ("MYSQL_TCP_PORT", config.port.to_string()),
+        ],
+        _ => {
+            event!(Level::DEBUG, "no database environment known for protocol {}", protocol);
+            vec![]
+        },
+    };
+    variables
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect()
+}
+
+// Reads `export KEY=VALUE` and `KEY=VALUE` lines
+fn parse_exports(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .map(|line| line.trim())
+        .map(|line| line.strip_prefix("export ").unwrap_or(line))
+        .filter_map(|line| line.split_once('='))
+        .filter(|(key, _)| !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        .map(|(key, value)| (key.to_string(), unquote(value)))
+        .collect()
+}
+
+// Single quoted values are literal, double quoted ones may hold backslash escapes
+fn unquote(value: &str) -> String {
+    if let Some(inner) = value.strip_prefix('\'').and_then(|value| value.strip_suffix('\'')) {
+        return inner.to_string();
+    }
+    let Some(inner) = value.strip_prefix('"').and_then(|value| value.strip_suffix('"')) else {
+        return value.to_string();
+    };
+    let mut unquoted = String::new();
+    let mut escaped = false;
+    for character in inner.chars() {
+        match (escaped, character) {
+            (false, '\\') => escaped = true,
+            (true, 'n') => {
+                unquoted.push('\n');
+                escaped = false;
+            },
+            (_, character) => {
+                unquoted.push(character);
+                escaped = false;
+            },
+        }
+    }
+    unquoted
+}
+
+// Runs the user's shell with the database variables exported until it exits
+pub fn spawn_subshell(
+    instance: &str,
+    variables: &[(String, String)],
+    deadline: Option<SessionDeadline>,
+    recording: Option<Recording>,
+) -> io::Result<SessionOutcome> {
+    let shell = env::var("SHELL").unwrap_or("/bin/sh".to_string());
+    let shell_name = PathBuf::from(&shell)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let marker = match &deadline {
+        Some(deadline) => format!("(kith:{} until {}) ", instance, deadline.ends_at()),
+        None => format!("(kith:{}) ", instance),
+    };
+
+    event!(Level::INFO, "spawning {} with database environment for {}", shell, instance);
+
+    let rc_dir = env::temp_dir().join(format!("kith-subshell-{}", std::process::id()));
+    fs::create_dir_all(&rc_dir)?;
+
+    let mut command = Command::new(&shell);
+    command
+        .envs(variables.iter().map(|(key, value)| (key, value)))
+        .env("KITH_DB_INSTANCE", instance);
+
+    // Prefix the prompt after the user's own rc files had a chance to set it
+    match shell_name.as_str() {
+        "bash" => {
+            let rc_file = rc_dir.join("bashrc");
+            fs::write(&rc_file, format!(
+                "[ -f ~/.bashrc ] && . ~/.bashrc\nPS1=\"{}$PS1\"\n",
+                marker,
+            ))?;
+            command.arg("--rcfile").arg(&rc_file);
+        },
+        "zsh" => {
+            let original_zdotdir = env::var("ZDOTDIR")
+                .ok()
+                .or_else(|| dirs::home_dir().map(|home| home.to_string_lossy().to_string()))
+                .unwrap_or_default();
+            fs::write(rc_dir.join(".zshrc"), format!(
+                "ZDOTDIR=\"{}\"\n[ -f \"$ZDOTDIR/.zshrc\" ] && . \"$ZDOTDIR/.zshrc\"\nPROMPT=\"{}$PROMPT\"\n",
+                original_zdotdir,
+                marker,
+            ))?;
+            command.env("ZDOTDIR", &rc_dir);
+        },
+        _ => {
+            let prompt = env::var("PS1").unwrap_or("$ ".to_string());
+            command.env("PS1", format!("{}{}", marker, prompt));
+        },
+    }
+
+    println!("Entering a subshell for {}, exit it to return to kith.", instance);
+    let outcome = match recording {
+        Some(recording) => run_recorded(&command, deadline, recording),
+        None => command
+            .spawn()
+            .and_then(|child| wait_with_deadline(child, deadline)),
+    };
+
+    if let Err(err) = fs::remove_dir_all(&rc_dir) {
+        event!(Level::DEBUG, "failed to clean up subshell rc files: {}", err);
+    }
+    outcome
+}
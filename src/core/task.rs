@@ -0,0 +1,32 @@
+use std::{
+    sync::mpsc::{channel, Receiver, TryRecvError},
+    thread,
+};
+
+// One-off background job whose result is picked up from the render loop
+pub struct Task<T> {
+    receiver: Receiver<T>,
+}
+
+impl<T: Send + 'static> Task<T> {
+    pub fn spawn<F>(job: F) -> Task<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            let _ = sender.send(job());
+        });
+
+        Task { receiver }
+    }
+
+    // Returns the result once the job is done, `Err` when it panicked
+    pub fn try_take(&self) -> Option<Result<T, ()>> {
+        match self.receiver.try_recv() {
+            Ok(value) => Some(Ok(value)),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(())),
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use std::{io, process::Command};
+use serde::Deserialize;
+use tracing::{event, Level};
+
+use super::{
+    audit::AuditEntry,
+    pty_session::run_recorded,
+    recording::Recording,
+    selector::LabelSelector,
+    session_limit::{wait_with_deadline, SessionDeadline, SessionOutcome},
+    tsh::{ConnectionArgs, Database, Tsh},
+    tunnel::{pick_free_port, TUNNEL_HOST},
+};
+
+const PLACEHOLDERS: [&str; 9] = [
+    "instance",
+    "db_user",
+    "db_name",
+    "protocol",
+    "tunnel_host",
+    "tunnel_port",
+    "ca",
+    "cert",
+    "key",
+];
+
+// A configured client command, eg. `pgcli postgres://{db_user}@{tunnel_host}:{tunnel_port}/{db_name}`
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommandTemplate {
+    pub name: String,
+    pub command: String,
+    pub protocol: Option<String>,
+    pub selector: Option<LabelSelector>,
+}
+
+// Values substituted into a template, tunnel and certificate values are filled in on demand
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    pub instance: String,
+    pub db_user: String,
+    pub db_name: String,
+    pub protocol: String,
+    pub tunnel_host: String,
+    pub tunnel_port: Option<u16>,
+    pub ca: String,
+    pub cert: String,
+    pub key: String,
+}
+
+// A rendered template, the tunnel is only started once the user confirms
+#[derive(Debug, Clone)]
+pub struct PreparedCommand {
+    pub command: String,
+    pub tunnel_port: Option<u16>,
+}
+
+// Shell quotes open at a point of the command
+#[derive(Clone, Copy, PartialEq)]
+enum Quoting {
+    None,
+    Single,
+    Double,
+}
+
+impl Quoting {
+    fn after(self, text: &str) -> Quoting {
+        let mut quoting = self;
+        let mut escaped = false;
+        for character in text.chars() {
+            if escaped {
+                escaped = false;
+                continue;
+            }
+            quoting = match (quoting, character) {
+                (Quoting::Single, '\'') => Quoting::None,
+                (Quoting::Single, _) => Quoting::Single,
+                (quoting, '\\') => {
+                    escaped = true;
+                    quoting
+                },
+                (Quoting::None, '\'') => Quoting::Single,
+                (Quoting::None, '"') => Quoting::Double,
+                (Quoting::Double, '"') => Quoting::None,
+                (quoting, _) => quoting,
+            };
+        }
+        quoting
+    }
+
+    fn quote(self, value: &str) -> String {
+        match self {
+            Quoting::None if !value.is_empty() && value
+                .chars()
+                .all(|character| character.is_ascii_alphanumeric() || "-_./:@+,=".contains(character)) => value.to_string(),
+            Quoting::None => format!("'{}'", value.replace('\'', "'\\''")),
+            // Closes the quotes around an escaped quote and opens them again
+            Quoting::Single => value.replace('\'', "'\\''"),
+            Quoting::Double => value
+                .chars()
+                .flat_map(|character| match character {
+                    '\\' | '"' | '$' | '`' => vec!['\\', character],
+                    character => vec![character],
+                })
+                .collect(),
+        }
+    }
+}
+
+enum Segment {
+    Text(String),
+    Placeholder(String),
+}
+
+impl CommandTemplate {
+    pub fn applies_to(&self, database: &Database) -> bool {
+        let protocol_matches = self.protocol
+            .as_ref()
+            .is_none_or(|protocol| *protocol == database.spec.protocol);
+        let labels_match = self.selector
+            .as_ref()
+            .is_none_or(|selector| selector.matches(&database.metadata.labels));
+
+        protocol_matches && labels_match
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        parse(&self.command).map(|_| ())
+    }
+
+    pub fn needs_tunnel(&self) -> bool {
+        self.uses(&["tunnel_host", "tunnel_port"])
+    }
+
+    pub fn needs_certificates(&self) -> bool {
+        self.uses(&["ca", "cert", "key"])
+    }
+
+    // Values are quoted for where they land in the shell command, so they are never parsed as shell syntax
+    pub fn render(&self, context: &TemplateContext) -> Result<String, String> {
+        let segments = parse(&self.command)?;
+
+        let mut rendered = String::new();
+        let mut quoting = Quoting::None;
+        for segment in segments {
+            match segment {
+                Segment::Text(text) => {
+                    quoting = quoting.after(&text);
+                    rendered.push_str(&text);
+                },
+                Segment::Placeholder(name) => {
+                    let value = match name.as_str() {
+                        "instance" => context.instance.clone(),
+                        "db_user" => context.db_user.clone(),
+                        "db_name" => context.db_name.clone(),
+                        "protocol" => context.protocol.clone(),
+                        "tunnel_host" => context.tunnel_host.clone(),
+                        "tunnel_port" => context.tunnel_port
+                            .map(|port| port.to_string())
+                            .unwrap_or_default(),
+                        "ca" => context.ca.clone(),
+                        "cert" => context.cert.clone(),
+                        _ => context.key.clone(),
+                    };
+                    rendered.push_str(&quoting.quote(&value));
+                },
+            }
+        }
+        Ok(rendered)
+    }
+
+    fn uses(&self, names: &[&str]) -> bool {
+        match parse(&self.command) {
+            Ok(segments) => segments.iter().any(|segment| match segment {
+                Segment::Placeholder(name) => names.contains(&name.as_str()),
+                Segment::Text(_) => false,
+            }),
+            Err(_) => false,
+        }
+    }
+}
+
+impl Tsh {
+    // Resolves the values a template needs, certificate paths require a `tsh db config` round trip
+    // Associated so it can run off the render thread
+    pub fn prepare_command(
+        template: &CommandTemplate,
+        args: &ConnectionArgs,
+        protocol: &str,
+        login: Option<&AuditEntry>,
+    ) -> Result<PreparedCommand, String> {
+        let mut context = TemplateContext {
+            instance: args.instance.clone(),
+            db_user: args.db_user.clone(),
+            db_name: args.db_name.clone(),
+            protocol: protocol.to_string(),
+            ..TemplateContext::default()
+        };
+
+        if template.needs_tunnel() {
+            let port = pick_free_port()
+                .map_err(|err| format!("no free local port for the tunnel: {}", err))?;
+            context.tunnel_host = TUNNEL_HOST.to_string();
+            context.tunnel_port = Some(port);
+        }
+
+        if template.needs_certificates() {
+            let config = Tsh::fetch_db_config(args, login)
+                .ok_or("failed to read certificate paths from tsh db config")?;
+            context.ca = config.ca;
+            context.cert = config.cert;
+            context.key = config.key;
+        }
+
+        let command = template.render(&context)?;
+        Ok(PreparedCommand {
+            command,
+            tunnel_port: context.tunnel_port,
+        })
+    }
+}
+
+pub fn run_command(
+    prepared: &PreparedCommand,
+    deadline: Option<SessionDeadline>,
+    recording: Option<Recording>,
+) -> io::Result<SessionOutcome> {
+    event!(Level::INFO, "running command template: {}", prepared.command);
+
+    let mut command = Command::new("sh");
+    command.args(["-c", &prepared.command]);
+    match recording {
+        Some(recording) => run_recorded(&command, deadline, recording),
+        None => wait_with_deadline(command.spawn()?, deadline),
+    }
+}
+
+// Splits on `{placeholder}`, `{{` and `}}` escape literal braces
+fn parse(command: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = vec![];
+    let mut text = String::new();
+    let mut chars = command.chars().peekable();
+
+    while let Some(character) = chars.next() {
+        match character {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            },
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(character) => name.push(character),
+                        None => return Err(format!("unclosed placeholder `{{{}`", name)),
+                    }
+                }
+                if !PLACEHOLDERS.contains(&name.as_str()) {
+                    return Err(format!("unknown placeholder `{{{}}}`", name));
+                }
+                if !text.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut text)));
+                }
+                segments.push(Segment::Placeholder(name));
+            },
+            '}' => return Err("unmatched `}`, use `}}` for a literal brace".to_string()),
+            _ => text.push(character),
+        }
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
@@ -1,22 +1,63 @@
 #![allow(unused)]
-use std::{collections::HashMap, fmt::format, io::{BufRead, BufReader}, iter::Map, process::{Command, Stdio}};
+use std::{collections::HashMap, fmt::format, io::{BufRead, BufReader}, iter::Map, process::{Command, Output, Stdio}};
 use tracing::{event, Level};
 use serde::Deserialize;
 
+use super::resources::{Application, DetailSection, KubeCluster, Node, Resource};
+
 #[derive(Debug, Default)]
 pub struct Tsh {
     pub databases: Vec<Database>,
+    pub active_databases: Vec<String>,
+    pub apps: Vec<Application>,
+    pub active_apps: Vec<String>,
+    pub nodes: Vec<Node>,
+    pub kube_clusters: Vec<KubeCluster>,
+    pub logins: Vec<String>,
+    pub username: String,
+    pub cluster: String,
 }
 
+#[derive(Clone)]
 pub struct ConnectionArgs {
     pub instance: String,
     pub db_user: String,
     pub db_name: String,
 }
 
+impl ConnectionArgs {
+    pub fn connect_command(&self) -> String {
+        format!(
+            "tsh db connect --db-user={} --db-name={} {}",
+            self.db_user,
+            self.db_name,
+            self.instance,
+        )
+    }
+
+    pub fn tunnel_command(&self) -> String {
+        format!(
+            "tsh proxy db --tunnel --db-user={} --db-name={} {}",
+            self.db_user,
+            self.db_name,
+            self.instance,
+        )
+    }
+}
+
 impl Tsh {
     pub fn new() -> Tsh {
-        Tsh { databases: vec![] }
+        Tsh {
+            databases: vec![],
+            active_databases: vec![],
+            apps: vec![],
+            active_apps: vec![],
+            nodes: vec![],
+            kube_clusters: vec![],
+            logins: vec![],
+            username: String::new(),
+            cluster: String::new(),
+        }
     }
 
     pub fn login(&self, proxy_name: &str, cluster: &str) {
@@ -44,81 +85,165 @@ impl Tsh {
         }
     }
 
-    // Database connection currently spawns a new terminal via AppleScript.
-    // This is done to avoid the complexity of spawning an interactive child of psql
-    // in the current terminal session. Also ensures that we don't have any zombie processes.
-    // This should eventually be refactored into an OS agnostic approach.
-    pub fn connect(&self, args: ConnectionArgs) {
-        event!(Level::INFO, "Connecting...");
+    pub fn db_login(&self, instance: &str) -> Option<i32> {
+        event!(Level::INFO, "logging into database instance: {}", instance);
 
-        let connection_command = format!(
-            "tsh db connect --db-user={} --db-name={} {}",
-            args.db_user,
-            args.db_name,
-            &args.instance,
-        );
-
-        let script = format!(
-            r#"
-            tell application "Terminal"
-                activate
-                do script "{}"
-            end tell
-            "#,
-            connection_command,
-        );
-
-        event!(Level::INFO, script);
-
-        let status = Command::new("osascript")
-            .arg("-e")
-            .arg(script)
-            .status()
-            .expect("failed to execute AppleScript");
-
-        if !status.success() {
-            event!(Level::ERROR, "teleport database connection failed with status: {}", status);
-        }
+        let teleport_cmd = Command::new("tsh")
+            .args(["db", "login", instance])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        command_exit_code("teleport db login", teleport_cmd)
     }
 
-    fn disconnect(&self) {
-        Command::new("tsh")
-            .args(["db", "logout"])
+    pub fn db_logout(&self, instance: &str) -> Option<i32> {
+        event!(Level::INFO, "logging out of database instance: {}", instance);
+
+        // Always scope the logout to a single instance, a bare `tsh db logout`
+        // revokes every database certificate and kills unrelated sessions.
+        let teleport_cmd = Command::new("tsh")
+            .args(["db", "logout", instance])
             .stdout(Stdio::piped())
-            .output()
-            .expect("failed to disconnect from the teleport database");
+            .stderr(Stdio::piped())
+            .output();
+
+        command_exit_code("teleport db logout", teleport_cmd)
     }
 
-    pub fn read_databases(&mut self, database_name: &str) {
-        event!(Level::DEBUG, "reading teleport databases");
+    pub fn read_status(&mut self) {
+        event!(Level::DEBUG, "reading teleport status");
+
+        let teleport_cmd = Command::new("tsh")
+            .args(["status", "--format=json"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        let output = match teleport_cmd {
+            Ok(output) => output,
+            Err(err) => {
+                event!(Level::ERROR, "failed to get output from teleport status: {}", err);
+                return;
+            }
+        };
+
+        match serde_json::from_slice::<Status>(&output.stdout) {
+            Ok(status) => {
+                let profile = status.active.unwrap_or_default();
+                self.active_databases = profile.databases;
+                self.active_apps = profile.apps;
+                self.logins = profile.logins;
+                self.username = profile.username;
+                self.cluster = profile.cluster;
+                event!(Level::DEBUG, "active database certificates: {:?}", self.active_databases);
+            },
+            Err(err) => {
+                event!(Level::ERROR, "failed to deserialize teleport status: {}", err);
+            }
+        }
+    }
 
-        // Ensure we are disconnected from any instances
-        self.disconnect();
+    // The previous list is kept when tsh fails, eg. when it is missing or the login expired
+    pub fn read_databases(&mut self, database_name: &str) -> Result<(), String> {
+        event!(Level::DEBUG, "reading teleport databases");
 
         let search = format!("--search={}", database_name);
         let format = format!("--format={}", "json");
-    
-        let teleport_cmd = Command::new("tsh")
+
+        let output = Command::new("tsh")
             .args(["db", "ls", &search, &format])
             .stdout(Stdio::piped())
-            .spawn()
-            .expect("failed to list teleport databases");
-
-        let out = teleport_cmd.stdout.expect("failed to open stdout");
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|err| format!("failed to run tsh db ls: {}", err))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            event!(Level::ERROR, "teleport db ls failed with status {}: {}", output.status, stderr);
+            return Err(match stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+                Some(line) => format!("tsh db ls failed: {}", line.trim()),
+                None => format!("tsh db ls exited with {}", output.status),
+            });
+        }
 
-        let mut reader = BufReader::new(out);
-        let db_list: Vec<Database> = serde_json::
-            from_reader(&mut reader)
-            .expect("failed to deserialize database list");
+        let db_list: Vec<Database> = serde_json::from_slice(&output.stdout)
+            .map_err(|err| format!("failed to read the tsh db ls output: {}", err))?;
 
+        self.databases.clear();
         for db in db_list {
             let db_name = db.metadata.name.clone();
             event!(Level::DEBUG, "database: {}", db_name);
             self.databases.push(db);
         }
+        Ok(())
+    }
+}
+
+pub(crate) fn read_json_output<T>(name: &str, result: std::io::Result<Output>) -> Vec<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    match result {
+        Ok(output) if output.status.success() => {
+            match serde_json::from_slice::<Option<Vec<T>>>(&output.stdout) {
+                Ok(items) => items.unwrap_or_default(),
+                Err(err) => {
+                    event!(Level::ERROR, "failed to deserialize {} output: {}", name, err);
+                    vec![]
+                }
+            }
+        }
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            event!(Level::ERROR, "{} failed with status {}: {}", name, output.status, stderr);
+            vec![]
+        }
+        Err(err) => {
+            event!(Level::ERROR, "failed to get output from {}: {}", name, err);
+            vec![]
+        }
     }
 }
 
+// `None` when the command could not be spawned or was killed by a signal
+pub(crate) fn command_exit_code(name: &str, result: std::io::Result<Output>) -> Option<i32> {
+    match result {
+        Ok(output) if output.status.success() => {
+            event!(Level::DEBUG, "{} succeeded", name);
+            output.status.code()
+        }
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            event!(Level::ERROR, "{} failed with status {}: {}", name, output.status, stderr);
+            output.status.code()
+        }
+        Err(err) => {
+            event!(Level::ERROR, "failed to get output from {}: {}", name, err);
+            None
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Status {
+    active: Option<Profile>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Profile {
+    #[serde(default)]
+    databases: Vec<String>,
+    #[serde(default)]
+    apps: Vec<String>,
+    #[serde(default)]
+    logins: Vec<String>,
+    #[serde(default)]
+    username: String,
+    #[serde(default)]
+    cluster: String,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Database {
     pub metadata: Metadata,
@@ -161,13 +286,13 @@ pub struct GcpSpec {
 enum Fields {
     Name,
     Description,
+    Revision,
     Protocol,
     Uri,
     AwsRegion,
     GcpProject,
     GcpInstance,
-    AllowedUsers,
-    Labels,
+    AllowedUser,
 }
 
 impl Fields {
@@ -175,13 +300,13 @@ impl Fields {
         match self {
             Fields::Name => "Name",
             Fields::Description => "Description",
+            Fields::Revision => "Revision",
             Fields::Protocol => "Protocol",
             Fields::Uri => "URI",
             Fields::AwsRegion => "AWS Region",
             Fields::GcpProject => "GCP Project",
             Fields::GcpInstance => "GCP Instance",
-            Fields::AllowedUsers => "Allowed Users",
-            Fields::Labels => "Labels",
+            Fields::AllowedUser => "Allowed User",
         }
     }
 }
@@ -210,102 +335,57 @@ impl Database {
             event!(Level::ERROR, "tsh connection command failed");
         }
     }
+}
 
-    pub fn format_details(&self) -> String {
-        let mut details = String::new();
-
-        let name = format!(
-            "{}: {}\n", 
-            Fields::Name.to_string(), 
-            self.metadata.name
-        );
-        details.push_str(name.as_str());
-
-        let description = format!(
-            "{}: {}\n", 
-            Fields::Description.to_string(), 
-            self.metadata.name
-        );
-        details.push_str(description.as_str());
-
-        let protocol = format!(
-            "{}: {}\n",
-            Fields::Protocol.to_string(),
-            self.spec.protocol
-        );
-        details.push_str(protocol.as_str());
-
-        let uri = format!(
-            "{}: {}\n", 
-            Fields::Uri.to_string(), 
-            self.spec.uri
-        );
-        details.push_str(uri.as_str());
-
-        let is_aws = self.spec.aws.region.is_some();
-        if is_aws {
-            let aws_region = format!(
-                "{}: {}\n",
-                Fields::AwsRegion.to_string(),
-                self.spec.aws.region
-                    .clone()
-                    .unwrap_or("unknown".to_string()),
-            );
-            details.push_str(aws_region.as_str());
+impl Resource for Database {
+    fn name(&self) -> &str {
+        &self.metadata.name
+    }
+
+    fn details(&self) -> Vec<DetailSection> {
+        let mut sections = vec![];
+
+        sections.push(DetailSection::new("Metadata", vec![
+            (Fields::Name.to_string().into(), self.metadata.name.clone()),
+            (Fields::Description.to_string().into(), self.metadata.description.clone()),
+            (Fields::Revision.to_string().into(), self.metadata.revision.clone()),
+        ]));
+
+        sections.push(DetailSection::new("Spec", vec![
+            (Fields::Protocol.to_string().into(), self.spec.protocol.clone()),
+            (Fields::Uri.to_string().into(), self.spec.uri.clone()),
+        ]));
+
+        let mut cloud = vec![];
+        if let Some(region) = &self.spec.aws.region {
+            cloud.push((Fields::AwsRegion.to_string().into(), region.clone()));
         }
-        let is_gcp = self.spec.gcp.project_id.is_some();
-        if is_gcp {
-            let gcp_project = format!(
-                "{}: {}\n",
-                Fields::GcpProject.to_string(),
+        if self.spec.gcp.project_id.is_some() {
+            cloud.push((
+                Fields::GcpProject.to_string().into(),
                 self.spec.gcp.project_id
                     .clone()
                     .unwrap_or("unknown".to_string()),
-            );
-            details.push_str(gcp_project.as_str());
-
-            let gcp_instance = format!(
-                "{}: {}\n",
-                Fields::GcpInstance.to_string(),
+            ));
+            cloud.push((
+                Fields::GcpInstance.to_string().into(),
                 self.spec.gcp.instance_id
                     .clone()
                     .unwrap_or("unknown".to_string()),
-            );
-            details.push_str(gcp_instance.as_str());
+            ));
+        }
+        if !cloud.is_empty() {
+            sections.push(DetailSection::new("Cloud", cloud));
         }
 
-        let allowed_users_list = self.users.allowed
-            .iter()
-            .fold(String::new(), |mut accumulator, element| {
-                accumulator.push_str(
-                    format!("  - {}\n", element).as_str()
-                );
-                accumulator
-            });
-
-        let allowed_users = format!(
-            "{}:\n{}",
-            Fields::AllowedUsers.to_string(),
-            allowed_users_list
-        );
-        details.push_str(allowed_users.as_str());
-
-        let labels_list = self.metadata.labels
+        let users = self.users.allowed
             .iter()
-            .fold(String::new(), |mut accumulator, element| {
-                accumulator.push_str(
-                    format!("  - {}: {}\n", element.0, element.1).as_str()
-                );
-                accumulator
-            });
+            .map(|user| (Fields::AllowedUser.to_string().into(), user.clone()))
+            .collect();
+        sections.push(DetailSection::new("Users", users));
 
-        let labels  = format!(
-            "{}:\n{}",
-            Fields::Labels.to_string(),
-            labels_list
-        );
-        details.push_str(labels.as_str());
+        sections.push(DetailSection::from_labels(&self.metadata.labels));
 
-        details
+        sections
     }
 }
@@ -0,0 +1,97 @@
+use std::{
+    io::{self, BufRead, BufReader},
+    net::{TcpListener, TcpStream},
+    process::{Child, ChildStderr, Command, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use tracing::{event, Level};
+
+use super::tsh::ConnectionArgs;
+
+pub const TUNNEL_HOST: &str = "localhost";
+
+// How long to wait for `tsh proxy db` to start accepting connections
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(15);
+
+// A `tsh proxy db --tunnel` child process, killed when dropped
+pub struct Tunnel {
+    pub host: String,
+    pub port: u16,
+    child: Child,
+    // Last line tsh wrote to stderr, read in the background so the pipe never fills up
+    stderr: Option<JoinHandle<String>>,
+}
+
+impl Tunnel {
+    pub fn start(args: &ConnectionArgs, port: u16) -> io::Result<Tunnel> {
+        event!(Level::INFO, "starting tunnel to {} on port {}", args.instance, port);
+
+        let port_arg = format!("--port={}", port);
+        let db_user = format!("--db-user={}", args.db_user);
+        let db_name = format!("--db-name={}", args.db_name);
+
+        let mut child = Command::new("tsh")
+            .args(["proxy", "db", "--tunnel", &port_arg, &db_user, &db_name, &args.instance])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let mut tunnel = Tunnel {
+            host: TUNNEL_HOST.to_string(),
+            port,
+            stderr: child.stderr.take().map(last_line),
+            child,
+        };
+        tunnel.wait_until_ready()?;
+        Ok(tunnel)
+    }
+
+    fn wait_until_ready(&mut self) -> io::Result<()> {
+        let started = Instant::now();
+        while started.elapsed() < STARTUP_TIMEOUT {
+            if let Some(status) = self.child.try_wait()? {
+                let message = self.stderr
+                    .take()
+                    .and_then(|reader| reader.join().ok())
+                    .filter(|line| !line.is_empty());
+                return Err(io::Error::other(match message {
+                    Some(message) => format!("tsh proxy db failed with {}: {}", status, message),
+                    None => format!("tsh proxy db failed with {}", status),
+                }));
+            }
+            if TcpStream::connect((self.host.as_str(), self.port)).is_ok() {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(200));
+        }
+        Err(io::Error::new(io::ErrorKind::TimedOut, "timed out waiting for the tunnel"))
+    }
+}
+
+impl Drop for Tunnel {
+    fn drop(&mut self) {
+        event!(Level::INFO, "stopping tunnel on port {}", self.port);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn last_line(stderr: ChildStderr) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut last = String::new();
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            if !line.trim().is_empty() {
+                last = line.trim().to_string();
+            }
+        }
+        last
+    })
+}
+
+// Asks the OS for a currently unused local port
+pub fn pick_free_port() -> io::Result<u16> {
+    let listener = TcpListener::bind((TUNNEL_HOST, 0))?;
+    Ok(listener.local_addr()?.port())
+}
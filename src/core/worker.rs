@@ -0,0 +1,120 @@
+use std::{
+    sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{event, Level};
+
+use super::{session_limit::SessionDeadline, tsh::ConnectionArgs};
+
+// A thread owning a client and its tunnel, requests are sent over a channel and answered
+// with events. Dropping the worker ends the thread, then the client and the tunnel
+pub struct Worker<R, E> {
+    requests: Sender<R>,
+    events: Receiver<E>,
+    closed: fn(String) -> E,
+}
+
+impl<R: Send + 'static, E: Send + 'static> Worker<R, E> {
+    // `connect` runs on the thread and returns the client with the event announcing it,
+    // a failure to connect is sent as the `closed` event
+    pub fn spawn<C>(
+        label: &'static str,
+        args: ConnectionArgs,
+        connect: impl FnOnce(&ConnectionArgs) -> Result<(C, E), String> + Send + 'static,
+        mut handle: impl FnMut(&mut C, &ConnectionArgs, R) -> E + Send + 'static,
+        closed: fn(String) -> E,
+    ) -> Worker<R, E> {
+        let (requests, request_receiver) = channel::<R>();
+        let (event_sender, events) = channel();
+
+        thread::spawn(move || {
+            let mut client = match connect(&args) {
+                Ok((client, connected)) => {
+                    if event_sender.send(connected).is_err() {
+                        return;
+                    }
+                    client
+                },
+                Err(err) => {
+                    event!(Level::ERROR, "failed to open {} connection to {}: {}", label, args.instance, err);
+                    let _ = event_sender.send(closed(err));
+                    return;
+                },
+            };
+
+            while let Ok(request) = request_receiver.recv() {
+                if event_sender.send(handle(&mut client, &args, request)).is_err() {
+                    break;
+                }
+            }
+            event!(Level::INFO, "closing {} connection to {}", label, args.instance);
+        });
+
+        Worker { requests, events, closed }
+    }
+
+    pub fn send(&self, request: R) -> bool {
+        self.requests.send(request).is_ok()
+    }
+
+    pub fn try_event(&self) -> Option<E> {
+        match self.events.try_recv() {
+            Ok(event) => Some(event),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some((self.closed)("Connection closed".to_string())),
+        }
+    }
+
+    // Blocks up to `timeout`, for callers running off the render loop
+    pub fn wait_event(&self, timeout: Duration) -> Option<E> {
+        match self.events.recv_timeout(timeout) {
+            Ok(event) => Some(event),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => Some((self.closed)("Connection closed".to_string())),
+        }
+    }
+}
+
+pub enum DeadlineState {
+    Open,
+    Ending(Duration),
+    Expired,
+}
+
+// A connection a view keeps open until it is closed, it counts as one connection in the
+// audit log however many statements ran through it
+pub struct HeldConnection {
+    pub args: ConnectionArgs,
+    pub reason: Option<String>,
+    pub started: Instant,
+    pub deadline: Option<SessionDeadline>,
+    warned: bool,
+}
+
+impl HeldConnection {
+    pub fn new(args: ConnectionArgs, reason: Option<String>, deadline: Option<SessionDeadline>) -> HeldConnection {
+        HeldConnection {
+            args,
+            reason,
+            started: Instant::now(),
+            deadline,
+            warned: false,
+        }
+    }
+
+    // The warning is only reported once
+    pub fn check_deadline(&mut self) -> DeadlineState {
+        let Some(deadline) = &self.deadline else {
+            return DeadlineState::Open;
+        };
+        if deadline.is_expired() {
+            DeadlineState::Expired
+        } else if deadline.in_warning() && !self.warned {
+            self.warned = true;
+            DeadlineState::Ending(deadline.remaining())
+        } else {
+            DeadlineState::Open
+        }
+    }
+}
@@ -7,6 +7,7 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
 mod tui;
 mod app;
+mod cli;
 mod config;
 mod core;
 mod widgets;
@@ -14,6 +15,12 @@ mod widgets;
 fn main() -> Result<()> {
     dotenv().ok();
 
+    // Subcommands skip the log file so they never truncate a running session's log
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if cli::run(&args)? {
+        return Ok(());
+    }
+
     let log_dir = get_log_dir();
     create_dir_all(&log_dir)?;
     let log_path = log_dir.join("cli.log");
@@ -44,10 +51,6 @@ fn main() -> Result<()> {
 
     tui::restore()?;
 
-    if application.initiate_connection {
-        application.connect_to_database();
-    }
-
     Ok(())
 }
 
@@ -17,3 +17,10 @@ pub fn restore() -> io::Result<()> {
     disable_raw_mode()?;
     Ok(())
 }
+
+// Hands the terminal back to kith after a child process used it
+pub fn resume(terminal: &mut Tui) -> io::Result<()> {
+    execute!(stdout(), EnterAlternateScreen)?;
+    enable_raw_mode()?;
+    terminal.clear()
+}
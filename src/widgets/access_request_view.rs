@@ -0,0 +1,295 @@
+use std::time::Duration;
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Padding, Paragraph, StatefulWidget, Widget, Wrap},
+    Frame
+};
+
+use crate::{
+    core::{
+        access_request::{format_timestamp, AccessRequest, RequestState, RequestableResource},
+        poller::Poller,
+        tsh::Tsh,
+    },
+    widgets::{dialog::get_dialog_layout, request_dialog::RequestDialog, styles, text_input::TextInput},
+};
+
+// How often the status of our own requests is polled while the view is open
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(PartialEq)]
+pub enum RequestFocus {
+    Requestable,
+    Requests,
+}
+
+pub enum RequestMode {
+    Browsing,
+    Searching,
+    Composing,
+}
+
+pub struct AccessRequestView {
+    pub requestable: Vec<RequestableResource>,
+    pub requestable_state: ListState,
+    pub requests: Vec<AccessRequest>,
+    pub requests_state: ListState,
+    pub focus: RequestFocus,
+    pub mode: RequestMode,
+    pub search_input: TextInput,
+    pub request_dialog: RequestDialog,
+
+    poller: Poller<Vec<AccessRequest>>,
+}
+
+impl Widget for &AccessRequestView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let horizontal = Layout::horizontal([
+            Constraint::Percentage(40),
+            Constraint::Percentage(60),
+        ]);
+        let [left_area, right_area] = horizontal.areas(area);
+
+        let vertical = Layout::vertical([
+            Constraint::Percentage(50),
+            Constraint::Percentage(50),
+        ]);
+        let [requests_area, details_area] = vertical.areas(right_area);
+
+        self.render_requestable(left_area, buf);
+        self.render_requests(requests_area, buf);
+        self.render_request_details(details_area, buf);
+
+        match self.mode {
+            RequestMode::Searching => self.render_search(area, buf),
+            RequestMode::Composing => self.request_dialog.render(area, buf),
+            RequestMode::Browsing => {},
+        }
+    }
+}
+
+impl AccessRequestView {
+    pub fn new() -> AccessRequestView {
+        AccessRequestView {
+            requestable: vec![],
+            requestable_state: ListState::default(),
+            requests: vec![],
+            requests_state: ListState::default(),
+            focus: RequestFocus::Requestable,
+            mode: RequestMode::Browsing,
+            search_input: TextInput::new(),
+            request_dialog: RequestDialog::new(),
+            poller: Poller::new(REFRESH_INTERVAL),
+        }
+    }
+
+    pub fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            RequestFocus::Requestable => RequestFocus::Requests,
+            RequestFocus::Requests => RequestFocus::Requestable,
+        };
+    }
+
+    pub fn select_next(&mut self) {
+        match self.focus {
+            RequestFocus::Requestable => self.requestable_state.select_next(),
+            RequestFocus::Requests => self.requests_state.select_next(),
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        match self.focus {
+            RequestFocus::Requestable => self.requestable_state.select_previous(),
+            RequestFocus::Requests => self.requests_state.select_previous(),
+        }
+    }
+
+    pub fn selected_requestable(&self) -> Option<&RequestableResource> {
+        self.requestable_state.selected().and_then(|index| self.requestable.get(index))
+    }
+
+    pub fn selected_request(&self) -> Option<&AccessRequest> {
+        self.requests_state.selected().and_then(|index| self.requests.get(index))
+    }
+
+    pub fn search(&mut self) {
+        self.requestable = Tsh::search_requestable_databases(&self.search_input.value);
+        self.requestable_state = ListState::default();
+        if !self.requestable.is_empty() {
+            self.requestable_state.select(Some(0));
+        }
+        self.focus = RequestFocus::Requestable;
+        self.exit_mode();
+    }
+
+    pub fn start_search(&mut self) {
+        self.mode = RequestMode::Searching;
+    }
+
+    pub fn start_compose(&mut self) {
+        if let Some(resource) = self.selected_requestable() {
+            self.request_dialog.selected_resource = Some(resource.clone());
+            self.mode = RequestMode::Composing;
+        }
+    }
+
+    pub fn exit_mode(&mut self) {
+        self.search_input.reset();
+        self.request_dialog.reset();
+        self.mode = RequestMode::Browsing;
+    }
+
+    pub fn refresh_now(&mut self) {
+        self.poller.refresh_now();
+    }
+
+    pub fn poll_requests(&mut self) {
+        if let Some(requests) = self.poller.poll(Tsh::read_access_requests) {
+            self.with_requests(requests);
+        }
+    }
+
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        match self.mode {
+            RequestMode::Searching => {
+                self.search_input.set_cursor(frame, get_dialog_layout(40, 10, area));
+            },
+            RequestMode::Composing => self.request_dialog.set_cursor(frame, area),
+            RequestMode::Browsing => {},
+        }
+    }
+
+    fn with_requests(&mut self, requests: Vec<AccessRequest>) {
+        self.requests = requests;
+        clamp_selection(&mut self.requests_state, self.requests.len());
+    }
+
+    fn focused_block(&self, title: String, focus: RequestFocus) -> Block<'static> {
+        let block = Block::new()
+            .title(Line::raw(title).centered())
+            .borders(Borders::ALL)
+            .padding(Padding::new(2, 2, 1, 1));
+
+        if self.focus == focus {
+            block.border_style(Style::new().bold())
+        } else {
+            block.border_style(Style::new().dark_gray())
+        }
+    }
+
+    fn render_requestable(&self, area: Rect, buf: &mut Buffer) {
+        let title = format!(" Requestable Databases ({}) ", self.requestable.len());
+        let block = self.focused_block(title, RequestFocus::Requestable);
+
+        let items: Vec<ListItem> = self
+            .requestable
+            .iter()
+            .map(|resource| ListItem::from(resource.name().to_string()))
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(styles::SELECTED_STYLE)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(list, area, buf, &mut self.requestable_state.clone());
+    }
+
+    fn render_requests(&self, area: Rect, buf: &mut Buffer) {
+        let title = format!(" My Requests ({}) ", self.requests.len());
+        let block = self.focused_block(title, RequestFocus::Requests);
+
+        let items: Vec<ListItem> = self
+            .requests
+            .iter()
+            .map(|request| {
+                let state = &request.spec.state;
+                ListItem::from(Line::from(vec![
+                    Span::styled(format!("{:<9}", state.to_string()), state_style(state)),
+                    Span::raw(format!(" {}", request.resource_names().join(", "))),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(styles::SELECTED_STYLE)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(list, area, buf, &mut self.requests_state.clone());
+    }
+
+    fn render_request_details(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .title(Line::raw(" Request Details ").centered())
+            .borders(Borders::ALL)
+            .padding(Padding::new(2, 2, 1, 1));
+
+        let details = match self.selected_request() {
+            Some(request) => format_request_details(request),
+            None => String::new(),
+        };
+
+        Paragraph::new(details)
+            .wrap(Wrap { trim: true })
+            .block(block)
+            .render(area, buf);
+    }
+
+    fn render_search(&self, area: Rect, buf: &mut Buffer) {
+        let search_dialog_area = get_dialog_layout(40, 10, area);
+
+        let block = Block::new()
+            .title(" Search Requestable Databases ")
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        Widget::render(Clear, search_dialog_area, buf);
+        Widget::render(block, search_dialog_area, buf);
+
+        self.search_input.render(search_dialog_area, buf);
+    }
+}
+
+// Keeps a list selection valid after its items were replaced
+pub fn clamp_selection(state: &mut ListState, len: usize) {
+    match state.selected() {
+        Some(index) if index >= len => state.select(len.checked_sub(1)),
+        None if len > 0 => state.select(Some(0)),
+        _ => {},
+    }
+}
+
+pub fn state_style(state: &RequestState) -> Style {
+    match state {
+        RequestState::Pending => Style::new().fg(Color::Yellow),
+        RequestState::Approved => Style::new().fg(Color::Green),
+        RequestState::Denied => Style::new().fg(Color::Red),
+        RequestState::Promoted => Style::new().fg(Color::Cyan),
+        RequestState::Unknown => Style::new(),
+    }
+}
+
+pub fn format_request_details(request: &AccessRequest) -> String {
+    let mut details = String::new();
+
+    details.push_str(format!("ID: {}\n", request.id()).as_str());
+    details.push_str(format!("State: {}\n", request.spec.state.to_string()).as_str());
+    details.push_str(format!("User: {}\n", request.spec.user).as_str());
+    details.push_str(format!("Roles: {}\n", request.spec.roles.join(", ")).as_str());
+    details.push_str(format!("Resources: {}\n", request.resource_names().join(", ")).as_str());
+    details.push_str(format!("Reason: {}\n", request.spec.request_reason).as_str());
+    if !request.spec.resolve_reason.is_empty() {
+        details.push_str(format!("Resolve Reason: {}\n", request.spec.resolve_reason).as_str());
+    }
+    details.push_str(format!("Created: {}\n", format_timestamp(&request.spec.created)).as_str());
+    details.push_str(format!("Expires: {}\n", format_timestamp(&request.spec.expires)).as_str());
+
+    details
+}
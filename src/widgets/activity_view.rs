@@ -0,0 +1,474 @@
+use std::time::{Duration, Instant};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Clear, HighlightSpacing, Padding, Paragraph, Row, StatefulWidget, Table, TableState, Widget, Wrap},
+};
+
+use crate::{
+    core::{
+        activity::{ActivityEvent, ActivityMonitor, ActivitySnapshot, Backend, BackendAction},
+        session_limit::{format_duration, SessionDeadline},
+        tsh::ConnectionArgs,
+        worker::HeldConnection,
+    },
+    widgets::{dialog::get_dialog_layout, styles},
+};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+const PAGE_ROWS: usize = 20;
+
+enum ActivityStatus {
+    Connecting,
+    Ready,
+    Closed(String),
+}
+
+// A cancel or terminate sent to a backend, handed back to the app for the audit log
+pub struct SignalOutcome {
+    pub pid: i32,
+    pub action: BackendAction,
+    pub result: Result<bool, String>,
+}
+
+// Live `pg_stat_activity` of one PostgreSQL database, refreshed over its own tunnel
+pub struct ActivityView {
+    pub session: Option<HeldConnection>,
+    pub show_idle: bool,
+    pub paused: bool,
+    // Signal waiting for confirmation, for the backend selected when it was asked
+    pub pending: Option<(i32, BackendAction)>,
+    state: TableState,
+    selected_pid: Option<i32>,
+    monitor: Option<ActivityMonitor>,
+    status: ActivityStatus,
+    snapshot: Option<ActivitySnapshot>,
+    error: Option<String>,
+    refreshing: bool,
+    last_refresh: Option<Instant>,
+}
+
+impl Widget for &ActivityView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [status_area, table_area, bottom_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(12),
+        ]).areas(area);
+        let [chains_area, details_area] = Layout::horizontal([
+            Constraint::Percentage(45),
+            Constraint::Min(0),
+        ]).areas(bottom_area);
+
+        self.render_status(status_area, buf);
+        self.render_table(table_area, buf);
+        self.render_chains(chains_area, buf);
+        self.render_details(details_area, buf);
+        if self.pending.is_some() {
+            self.render_confirmation(area, buf);
+        }
+    }
+}
+
+impl ActivityView {
+    pub fn new() -> ActivityView {
+        ActivityView {
+            session: None,
+            show_idle: false,
+            paused: false,
+            pending: None,
+            state: TableState::default(),
+            selected_pid: None,
+            monitor: None,
+            status: ActivityStatus::Connecting,
+            snapshot: None,
+            error: None,
+            refreshing: false,
+            last_refresh: None,
+        }
+    }
+
+    pub fn open(&mut self, args: ConnectionArgs, reason: Option<String>, deadline: Option<SessionDeadline>) {
+        self.reset();
+        self.monitor = Some(ActivityMonitor::open(args.clone()));
+        self.session = Some(HeldConnection::new(args, reason, deadline));
+    }
+
+    // Dropping the monitor stops its worker and tunnel
+    pub fn reset(&mut self) {
+        self.monitor = None;
+        self.session = None;
+        self.paused = false;
+        self.pending = None;
+        self.state = TableState::default();
+        self.selected_pid = None;
+        self.status = ActivityStatus::Connecting;
+        self.snapshot = None;
+        self.error = None;
+        self.refreshing = false;
+        self.last_refresh = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.session.is_some()
+    }
+
+    // Applies the worker's events and asks for the next snapshot once the interval passed
+    pub fn poll(&mut self) -> Vec<SignalOutcome> {
+        let Some(monitor) = &self.monitor else {
+            return vec![];
+        };
+        let mut events = vec![];
+        while let Some(event) = monitor.try_event() {
+            events.push(event);
+        }
+
+        let mut outcomes = vec![];
+        for event in events {
+            match event {
+                ActivityEvent::Connected => self.status = ActivityStatus::Ready,
+                ActivityEvent::Snapshot(Ok(snapshot)) => {
+                    self.snapshot = Some(snapshot);
+                    self.error = None;
+                    self.refreshing = false;
+                    self.restore_selection();
+                },
+                ActivityEvent::Snapshot(Err(err)) => {
+                    self.error = Some(err);
+                    self.refreshing = false;
+                },
+                ActivityEvent::Signalled { pid, action, result } => {
+                    // The change shows up on the next snapshot, no need to wait for the interval
+                    self.last_refresh = None;
+                    outcomes.push(SignalOutcome { pid, action, result });
+                },
+                ActivityEvent::Closed(reason) => {
+                    self.status = ActivityStatus::Closed(reason);
+                    self.monitor = None;
+                    self.refreshing = false;
+                    return outcomes;
+                },
+            }
+        }
+
+        let due = self.last_refresh.is_none_or(|last| last.elapsed() >= REFRESH_INTERVAL);
+        if matches!(self.status, ActivityStatus::Ready) && !self.refreshing && !self.paused && due {
+            self.refresh_now();
+        }
+        outcomes
+    }
+
+    pub fn refresh_now(&mut self) {
+        let Some(monitor) = &self.monitor else {
+            return;
+        };
+        if !matches!(self.status, ActivityStatus::Ready) || self.refreshing {
+            return;
+        }
+        if monitor.refresh() {
+            self.refreshing = true;
+            self.last_refresh = Some(Instant::now());
+        }
+    }
+
+    pub fn toggle_paused(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn toggle_idle(&mut self) {
+        self.show_idle = !self.show_idle;
+        self.restore_selection();
+    }
+
+    pub fn select_next(&mut self) {
+        self.move_selection(1);
+    }
+
+    pub fn select_previous(&mut self) {
+        self.move_selection(-1);
+    }
+
+    pub fn page_down(&mut self) {
+        self.move_selection(PAGE_ROWS as isize);
+    }
+
+    pub fn page_up(&mut self) {
+        self.move_selection(-(PAGE_ROWS as isize));
+    }
+
+    pub fn request(&mut self, action: BackendAction) {
+        if let Some(backend) = self.selected_backend() {
+            self.pending = Some((backend.pid, action));
+        }
+    }
+
+    // Sends the confirmed signal, `false` when the connection is gone
+    pub fn confirm(&mut self) -> bool {
+        let (Some((pid, action)), Some(monitor)) = (self.pending.take(), &self.monitor) else {
+            return false;
+        };
+        monitor.signal(pid, action)
+    }
+
+    pub fn abort(&mut self) {
+        self.pending = None;
+    }
+
+    // Idle sessions are hidden unless asked for, or when they hold up another backend
+    fn visible_backends(&self) -> Vec<&Backend> {
+        let Some(snapshot) = &self.snapshot else {
+            return vec![];
+        };
+        snapshot.backends
+            .iter()
+            .filter(|backend| self.show_idle || !backend.is_idle() || snapshot.is_blocking(backend.pid))
+            .collect()
+    }
+
+    fn selected_backend(&self) -> Option<&Backend> {
+        let pid = self.selected_pid?;
+        self.snapshot.as_ref()?.find(pid)
+    }
+
+    // Keeps following the same backend across refreshes, the row index is only a fallback
+    fn restore_selection(&mut self) {
+        let visible: Vec<i32> = self.visible_backends().iter().map(|backend| backend.pid).collect();
+        if visible.is_empty() {
+            self.state.select(None);
+            self.selected_pid = None;
+            return;
+        }
+        let index = self.selected_pid
+            .and_then(|pid| visible.iter().position(|visible_pid| *visible_pid == pid))
+            .unwrap_or(self.state.selected().unwrap_or(0).min(visible.len() - 1));
+        self.state.select(Some(index));
+        self.selected_pid = Some(visible[index]);
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let visible: Vec<i32> = self.visible_backends().iter().map(|backend| backend.pid).collect();
+        if visible.is_empty() {
+            return;
+        }
+        let current = self.state.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, visible.len() as isize - 1) as usize;
+        self.state.select(Some(next));
+        self.selected_pid = Some(visible[next]);
+    }
+
+    fn render_status(&self, area: Rect, buf: &mut Buffer) {
+        let Some(session) = &self.session else {
+            return;
+        };
+        let args = &session.args;
+        let status = match (&self.status, &self.snapshot) {
+            (ActivityStatus::Connecting, _) => "connecting through a tunnel...".to_string(),
+            (ActivityStatus::Closed(reason), _) => format!("disconnected: {}", reason),
+            (ActivityStatus::Ready, None) => "loading...".to_string(),
+            (ActivityStatus::Ready, Some(snapshot)) => format!(
+                "{} backends · {} waiting · refreshed {}s ago in {:.0} ms{}",
+                snapshot.backends.len(),
+                snapshot.waiting_count(),
+                snapshot.taken_at.elapsed().as_secs(),
+                snapshot.elapsed.as_secs_f64() * 1000.0,
+                if self.paused { " (paused)" } else { "" },
+            ),
+        };
+        let mut line = format!("Activity {}@{}/{} · {}", args.db_user, args.instance, args.db_name, status);
+        if let Some(deadline) = &session.deadline {
+            line.push_str(&format!(" · {} left", format_duration(deadline.remaining())));
+        }
+
+        let style = match &self.status {
+            ActivityStatus::Closed(_) => Style::new().fg(Color::Red),
+            _ if self.paused => Style::new().fg(Color::Yellow),
+            _ => Style::new(),
+        };
+        Paragraph::new(line).style(style).centered().render(area, buf);
+    }
+
+    fn render_table(&self, area: Rect, buf: &mut Buffer) {
+        let title = if self.show_idle { " Backends (all) " } else { " Backends (active) " };
+        let block = Block::new()
+            .title(Line::raw(title).centered())
+            .borders(Borders::ALL);
+
+        if let Some(err) = &self.error {
+            Paragraph::new(err.clone())
+                .block(block.title(Line::raw(" Error ").centered()))
+                .style(Style::new().fg(Color::Red))
+                .wrap(Wrap { trim: false })
+                .render(area, buf);
+            return;
+        }
+        let Some(snapshot) = &self.snapshot else {
+            block.render(area, buf);
+            return;
+        };
+
+        let header = Row::new(["pid", "user", "database", "state", "wait event", "query age", "xact age", "blocked by", "query"]).bold();
+        let rows: Vec<Row> = self.visible_backends()
+            .into_iter()
+            .map(|backend| {
+                let style = if snapshot.is_blocking(backend.pid) {
+                    Style::new().fg(Color::Red)
+                } else if !backend.blocked_by.is_empty() {
+                    Style::new().fg(Color::Yellow)
+                } else {
+                    Style::new()
+                };
+                Row::new([
+                    Cell::from(backend.pid.to_string()),
+                    Cell::from(backend.user.clone().unwrap_or_default()),
+                    Cell::from(backend.database.clone().unwrap_or_default()),
+                    Cell::from(backend.state.clone().or(backend.backend_type.clone()).unwrap_or_default()),
+                    Cell::from(backend.wait_event.clone().unwrap_or_default()),
+                    Cell::from(age_label(backend.query_age)),
+                    Cell::from(age_label(backend.transaction_age)),
+                    Cell::from(pid_list(&backend.blocked_by)),
+                    Cell::from(backend.query.as_deref().map(single_line).unwrap_or_default()),
+                ]).style(style)
+            })
+            .collect();
+        let widths = [
+            Constraint::Length(7),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(19),
+            Constraint::Length(18),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(10),
+            Constraint::Min(20),
+        ];
+
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .highlight_style(styles::SELECTED_STYLE)
+            .highlight_spacing(HighlightSpacing::Always)
+            .highlight_symbol(">");
+        StatefulWidget::render(table, area, buf, &mut self.state.clone());
+    }
+
+    fn render_chains(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .title(" Blocking ")
+            .borders(Borders::ALL);
+        let Some(snapshot) = &self.snapshot else {
+            block.render(area, buf);
+            return;
+        };
+
+        let chains = snapshot.blocking_chains();
+        let lines: Vec<Line> = if chains.is_empty() {
+            vec![Line::raw("No backend is waiting on a lock").dark_gray()]
+        } else {
+            chains
+                .into_iter()
+                .filter_map(|(depth, pid)| snapshot.find(pid).map(|backend| (depth, backend)))
+                .map(|(depth, backend)| {
+                    let (prefix, style) = if depth == 0 {
+                        (String::new(), Style::new().fg(Color::Red))
+                    } else {
+                        (format!("{}└ ", "  ".repeat(depth - 1)), Style::new().fg(Color::Yellow))
+                    };
+                    let detail = match &backend.waiting_for {
+                        Some(lock) => format!("waits for {}", lock),
+                        None => format!("{} for {}", backend.state.as_deref().unwrap_or("-"), age_label(backend.query_age)),
+                    };
+                    Line::from(vec![
+                        Span::raw(prefix),
+                        Span::styled(backend.pid.to_string(), style),
+                        Span::raw(format!(" {} ", backend.user.as_deref().unwrap_or("-"))),
+                        Span::raw(detail).dark_gray(),
+                    ])
+                })
+                .collect()
+        };
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+
+    fn render_details(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .title(" Details ")
+            .borders(Borders::ALL);
+        let Some(backend) = self.selected_backend() else {
+            block.render(area, buf);
+            return;
+        };
+
+        let text = |value: &Option<String>| value.clone().unwrap_or("-".to_string());
+        let properties = [
+            ("Application", text(&backend.application)),
+            ("Client", text(&backend.client)),
+            ("Backend type", text(&backend.backend_type)),
+            ("Locks held", backend.locks_held.to_string()),
+            ("Waiting for", text(&backend.waiting_for)),
+        ];
+        let mut lines: Vec<Line> = properties
+            .into_iter()
+            .map(|(key, value)| Line::from(vec![
+                Span::raw(format!("{}: ", key)).bold(),
+                Span::raw(value),
+            ]))
+            .collect();
+        lines.push(Line::raw(""));
+        lines.push(Line::raw(backend.query.clone().unwrap_or_default()));
+
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false })
+            .render(area, buf);
+    }
+
+    fn render_confirmation(&self, area: Rect, buf: &mut Buffer) {
+        let Some((pid, action)) = self.pending else {
+            return;
+        };
+        let dialog_area = get_dialog_layout(50, 30, area);
+        let (title, question) = match action {
+            BackendAction::Cancel => (" Cancel Query ", format!("Cancel the running query of backend {}?", pid)),
+            BackendAction::Terminate => (" Terminate Backend ", format!("Terminate backend {} and close its connection?", pid)),
+        };
+        let mut lines = vec![Line::raw(question).bold()];
+        if let Some(backend) = self.snapshot.as_ref().and_then(|snapshot| snapshot.find(pid)) {
+            lines.push(Line::raw(""));
+            lines.push(Line::raw(format!(
+                "{} on {} · {}",
+                backend.user.as_deref().unwrap_or("-"),
+                backend.database.as_deref().unwrap_or("-"),
+                backend.state.as_deref().unwrap_or("-"),
+            )));
+            lines.push(Line::raw(backend.query.as_deref().map(single_line).unwrap_or_default()).dark_gray());
+        }
+        lines.push(Line::raw(""));
+        lines.push(Line::raw("<y> Confirm, <n>/<esc> Abort"));
+
+        Widget::render(Clear, dialog_area, buf);
+        Paragraph::new(lines)
+            .block(Block::new()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::new().fg(Color::Red))
+                .padding(Padding::new(2, 2, 1, 1)))
+            .wrap(Wrap { trim: true })
+            .render(dialog_area, buf);
+    }
+}
+
+fn age_label(age: Option<Duration>) -> String {
+    age.map(format_duration).unwrap_or("-".to_string())
+}
+
+fn pid_list(pids: &[i32]) -> String {
+    pids.iter().map(|pid| pid.to_string()).collect::<Vec<_>>().join(",")
+}
+
+// Statements are shown on one line in the table, with their whitespace collapsed
+fn single_line(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,179 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Cell, HighlightSpacing, Padding, Row, StatefulWidget, Table, TableState, Widget},
+    Frame
+};
+use tracing::{event, Level};
+
+use crate::{
+    core::audit::{self, AuditEntry},
+    widgets::{styles, text_input::TextInput},
+};
+
+// Read-only view over the local audit log, newest entries first
+pub struct AuditView {
+    pub filter_input: TextInput,
+    pub state: TableState,
+    entries: Vec<AuditEntry>,
+}
+
+impl Widget for &AuditView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [filter_area, table_area] = audit_areas(area);
+
+        self.render_filter(filter_area, buf);
+        self.render_entries(table_area, buf);
+    }
+}
+
+impl AuditView {
+    pub fn new() -> AuditView {
+        AuditView {
+            filter_input: TextInput::new(),
+            state: TableState::default(),
+            entries: vec![],
+        }
+    }
+
+    pub fn reload(&mut self) {
+        match audit::read_entries() {
+            Ok(mut entries) => {
+                entries.reverse();
+                self.entries = entries;
+            },
+            Err(err) => {
+                event!(Level::ERROR, "failed to read the audit log: {}", err);
+                self.entries.clear();
+            },
+        }
+        self.clamp_selection();
+    }
+
+    pub fn reset(&mut self) {
+        self.filter_input.reset();
+        self.state = TableState::default();
+        self.entries.clear();
+    }
+
+    pub fn filtered(&self) -> Vec<&AuditEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.matches(&self.filter_input.value))
+            .collect()
+    }
+
+    pub fn enter_char(&mut self, character: char) {
+        self.filter_input.enter_char(character);
+        self.clamp_selection();
+    }
+
+    pub fn delete_char(&mut self) {
+        self.filter_input.delete_char();
+        self.clamp_selection();
+    }
+
+    pub fn select_next(&mut self) {
+        let next = self.state.selected().map_or(0, |index| index + 1);
+        self.state.select(Some(next));
+        self.clamp_selection();
+    }
+
+    pub fn select_previous(&mut self) {
+        let previous = self.state.selected().map_or(0, |index| index.saturating_sub(1));
+        self.state.select(Some(previous));
+    }
+
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        let [filter_area, _] = audit_areas(area);
+        self.filter_input.set_cursor(frame, filter_area);
+    }
+
+    fn clamp_selection(&mut self) {
+        let count = self.filtered().len();
+        let selected = match (self.state.selected(), count) {
+            (_, 0) => None,
+            (Some(index), _) => Some(index.min(count - 1)),
+            (None, _) => Some(0),
+        };
+        self.state.select(selected);
+    }
+
+    fn render_filter(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .title(" Filter ")
+            .borders(Borders::ALL);
+
+        Widget::render(block, area, buf);
+        self.filter_input.render(area, buf);
+    }
+
+    fn render_entries(&self, area: Rect, buf: &mut Buffer) {
+        let entries = self.filtered();
+        let title = format!(" Audit Log ({}/{}) ", entries.len(), self.entries.len());
+
+        let block = Block::new()
+            .title(Line::raw(title).centered())
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 0, 0));
+
+        let header = Row::new([
+            "Time", "Action", "Instance", "DB User", "DB Name", "Method", "Exit", "Duration", "Teleport User", "Reason",
+        ])
+        .bold();
+
+        let rows: Vec<Row> = entries
+            .iter()
+            .map(|entry| {
+                let exit_style = match entry.exit_status {
+                    Some(0) => Style::new().fg(Color::Green),
+                    Some(_) => Style::new().fg(Color::Red),
+                    None => Style::new(),
+                };
+                Row::new([
+                    Cell::from(entry.timestamp.replace('T', " ").trim_end_matches('Z').to_string()),
+                    Cell::from(entry.action.to_string()),
+                    Cell::from(entry.instance.clone()),
+                    Cell::from(entry.db_user.clone()),
+                    Cell::from(entry.db_name.clone()),
+                    Cell::from(entry.method.clone()),
+                    Cell::from(entry.exit_status_label()).style(exit_style),
+                    Cell::from(entry.duration_label()),
+                    Cell::from(entry.teleport_user.clone()),
+                    Cell::from(entry.reason.clone().unwrap_or_default()),
+                ])
+            })
+            .collect();
+
+        let widths = [
+            Constraint::Length(19),
+            Constraint::Length(21),
+            Constraint::Fill(2),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(16),
+            Constraint::Length(4),
+            Constraint::Length(8),
+            Constraint::Length(16),
+            Constraint::Fill(1),
+        ];
+
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .highlight_style(styles::SELECTED_STYLE)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(table, area, buf, &mut self.state.clone());
+    }
+}
+
+fn audit_areas(area: Rect) -> [Rect; 2] {
+    Layout::vertical([
+        Constraint::Length(5),
+        Constraint::Min(0),
+    ]).areas(area)
+}
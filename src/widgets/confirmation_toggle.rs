@@ -53,7 +53,7 @@ impl ConfirmationToggle {
         toggle.state.select(
                 Some(ConfirmationOption::Yes.to_index())
         );
-        return toggle
+        toggle
     }
 
     pub fn reset(&mut self) {
@@ -63,19 +63,20 @@ impl ConfirmationToggle {
         );
     }
 
+    pub fn select(&mut self, option: ConfirmationOption) {
+        self.state.select(Some(option.to_index()));
+    }
+
     pub fn toggle(&mut self) {
-        match self.state.selected() {
-            Some(index) => {
-                match self.items[index] {
-                    ConfirmationOption::Yes => self.state.select(
-                        Some(ConfirmationOption::No.to_index())
-                    ),
-                    ConfirmationOption::No => self.state.select(
-                        Some(ConfirmationOption::Yes.to_index())
-                    ),
-                }
-            },
-            None => {},
+        if let Some(index) = self.state.selected() {
+            match self.items[index] {
+                ConfirmationOption::Yes => self.state.select(
+                    Some(ConfirmationOption::No.to_index())
+                ),
+                ConfirmationOption::No => self.state.select(
+                    Some(ConfirmationOption::Yes.to_index())
+                ),
+            }
         }
     }
 
@@ -102,8 +103,7 @@ impl ConfirmationToggle {
         let items: Vec<ListItem> = self
             .items
             .iter()
-            .enumerate()
-            .map(|(_, item)| {
+            .map(|item| {
                 ListItem::from(item.to_string())
             })
             .collect();
@@ -1,12 +1,29 @@
 #![allow(unused)]
-use ratatui::{buffer::Buffer, layout::Rect, widgets::{Block, Borders, Clear, Padding, Widget}};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Padding, Paragraph, StatefulWidget, Widget, Wrap},
+    Frame
+};
 
 use crate::{
-    core::tsh::{self, ConnectionArgs, Database},
+    core::{
+        defaults::{resolve_defaults, AppliedDefault, ConnectionDefault, ResolvedDefaults},
+        guardrail::{find_guardrail, Guardrail},
+        query::DatabaseKind,
+        session_limit::{find_session_limit, format_duration, SessionLimit},
+        template::{CommandTemplate, PreparedCommand},
+        tsh::{self, ConnectionArgs, Database},
+    },
     widgets::{
         confirmation_toggle::{ConfirmationOption, ConfirmationToggle},
         database_name_input::{self, DatabaseNameInput},
         dialog::get_dialog_layout,
+        styles,
+        guardrail_confirmation::{guardrail_dialog_areas, render_guardrail_dialog, GuardrailConfirmation},
+        text_input::TextInput,
         user_list::StatefulUserList,
     }
 };
@@ -14,19 +31,69 @@ use crate::{
 pub enum Step {
     UserSelection,
     DatabaseInput,
+    MethodSelection,
+    InstanceConfirmation,
+    Reason,
     Confirmation,
 }
 
+#[derive(Debug, Clone)]
+pub enum ConnectMethod {
+    Session,
+    Subshell,
+    Query,
+    Activity,
+    HealthCheck,
+    Template(CommandTemplate),
+}
+
+impl ConnectMethod {
+    // Stable identifier handed to hooks, unlike the label shown in the list
+    pub fn name(&self) -> String {
+        match self {
+            ConnectMethod::Session => "session".to_string(),
+            ConnectMethod::Subshell => "subshell".to_string(),
+            ConnectMethod::Query => "query".to_string(),
+            ConnectMethod::Activity => "activity".to_string(),
+            ConnectMethod::HealthCheck => "health_check".to_string(),
+            ConnectMethod::Template(template) => format!("template:{}", template.name),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            ConnectMethod::Session => "tsh db connect in a session tab".to_string(),
+            ConnectMethod::Subshell => "Subshell with database environment".to_string(),
+            ConnectMethod::Query => "SQL query console".to_string(),
+            ConnectMethod::Activity => "Activity monitor".to_string(),
+            ConnectMethod::HealthCheck => "Connection test".to_string(),
+            ConnectMethod::Template(template) => format!("{} (template)", template.name),
+        }
+    }
+}
+
 pub struct ConnectDialog {
     pub user_list: StatefulUserList,
     pub database_name_input: DatabaseNameInput,
     pub confirmation_toggle: ConfirmationToggle,
+    pub method_state: ListState,
+    pub methods: Vec<ConnectMethod>,
+    pub template_preview: Option<Result<PreparedCommand, String>>,
+    pub guardrail: Option<Guardrail>,
+    pub defaults: ResolvedDefaults,
+    pub session_limit: Option<SessionLimit>,
+    pub guardrail_confirmation: GuardrailConfirmation,
+    pub reason_input: TextInput,
     pub ready_to_connect: Option<bool>,
     pub selected_entry: Option<Database>,
     pub db_name: String,
     pub db_user: String,
     pub current_step: Step,
 
+    templates: Vec<CommandTemplate>,
+    guardrails: Vec<Guardrail>,
+    default_rules: Vec<ConnectionDefault>,
+    session_limits: Vec<SessionLimit>,
     cursor_index: usize,
 }
 
@@ -35,6 +102,9 @@ impl Widget for &ConnectDialog {
         match self.current_step {
             Step::UserSelection => self.render_user_selection(area, buf),
             Step::DatabaseInput => self.render_db_name_input(area, buf),
+            Step::MethodSelection => self.render_method_selection(area, buf),
+            Step::InstanceConfirmation => self.guardrail_confirmation.render(area, buf),
+            Step::Reason => self.render_reason_input(area, buf),
             Step::Confirmation => self.render_confirmation(area, buf),
         } 
     }
@@ -42,23 +112,73 @@ impl Widget for &ConnectDialog {
 
 impl ConnectDialog {
     pub fn new() -> ConnectDialog {
-        return ConnectDialog {
+        ConnectDialog {
             user_list: StatefulUserList::new(),
             database_name_input: DatabaseNameInput::new(),
             confirmation_toggle: ConfirmationToggle::new(),
+            method_state: ListState::default().with_selected(Some(0)),
+            methods: vec![],
+            template_preview: None,
+            guardrail: None,
+            defaults: ResolvedDefaults::default(),
+            session_limit: None,
+            guardrail_confirmation: GuardrailConfirmation::new(),
+            reason_input: TextInput::new(),
             ready_to_connect: None,
             selected_entry: None,
             db_name: String::new(),
             db_user: String::new(),
             current_step: Step::UserSelection,
+            templates: vec![],
+            guardrails: vec![],
+            default_rules: vec![],
+            session_limits: vec![],
             cursor_index: 0,
         }
     }
 
+    pub fn with_templates(&mut self, templates: Vec<CommandTemplate>) {
+        self.templates = templates;
+    }
+
+    pub fn with_guardrails(&mut self, guardrails: Vec<Guardrail>) {
+        self.guardrails = guardrails;
+    }
+
+    pub fn with_default_rules(&mut self, rules: Vec<ConnectionDefault>) {
+        self.default_rules = rules;
+    }
+
+    pub fn with_session_limits(&mut self, limits: Vec<SessionLimit>) {
+        self.session_limits = limits;
+    }
+
+    // Preselects the user and prefills the database name from the first matching rules
+    pub fn apply_defaults(&mut self) {
+        let Some(database) = &self.selected_entry else {
+            return;
+        };
+        self.defaults = resolve_defaults(&self.default_rules, database);
+        self.user_list.with_items(database.users.allowed.clone());
+
+        if let Some(db_user) = &self.defaults.db_user {
+            let index = self.user_list.items
+                .iter()
+                .position(|user| *user == db_user.value);
+            self.user_list.state.select(index);
+        }
+        if let Some(db_name) = &self.defaults.db_name {
+            self.database_name_input.set_value(&db_name.value);
+        }
+    }
+
     pub fn next_step(&mut self) {
         match self.current_step {
             Step::UserSelection => self.navigate_to_db_input(),
-            Step::DatabaseInput => self.navigate_to_confirmation(), 
+            Step::DatabaseInput => self.navigate_to_method(), 
+            Step::MethodSelection => self.navigate_to_guardrail(),
+            Step::InstanceConfirmation => self.confirm_instance_name(),
+            Step::Reason => self.confirm_reason(),
             Step::Confirmation => self.connect(),
         }
     }
@@ -71,6 +191,14 @@ impl ConnectDialog {
         self.user_list.reset();
         self.database_name_input.reset();
         self.confirmation_toggle.reset();
+        self.method_state.select(Some(0));
+        self.methods.clear();
+        self.template_preview = None;
+        self.guardrail = None;
+        self.defaults = ResolvedDefaults::default();
+        self.session_limit = None;
+        self.guardrail_confirmation.reset();
+        self.reason_input.reset();
 
         self.current_step = Step::UserSelection;
         self.ready_to_connect = None;
@@ -81,7 +209,7 @@ impl ConnectDialog {
     }
 
     pub fn to_connection_args(&self) -> ConnectionArgs {
-        return ConnectionArgs {
+        ConnectionArgs {
             instance: self.selected_entry.as_ref().unwrap().metadata.name.clone(),
             db_name: self.db_name.clone(),
             db_user: self.db_user.clone()
@@ -89,12 +217,9 @@ impl ConnectDialog {
     }
 
     fn navigate_to_db_input(&mut self) {
-        match self.user_list.state.selected() {
-            Some(index) => {
-                let selected_user = &self.user_list.items[index];
-                self.db_user = selected_user.to_string();
-            },
-            None => {},
+        if let Some(index) = self.user_list.state.selected() {
+            let selected_user = &self.user_list.items[index];
+            self.db_user = selected_user.to_string();
         }
 
         if !self.db_user.is_empty() {
@@ -102,15 +227,131 @@ impl ConnectDialog {
         }
     }
 
+    pub fn selected_method(&self) -> ConnectMethod {
+        self.method_state
+            .selected()
+            .and_then(|index| self.methods.get(index))
+            .cloned()
+            .unwrap_or(ConnectMethod::Session)
+    }
+
+    fn navigate_to_method(&mut self) {
+        if self.db_name.is_empty() {
+            return;
+        }
+
+        self.session_limit = self.selected_entry
+            .as_ref()
+            .and_then(|database| find_session_limit(&self.session_limits, database))
+            .cloned();
+
+        self.methods = vec![ConnectMethod::Session, ConnectMethod::Subshell];
+
+        // Templates are offered only for the protocols and labels they target
+        if let Some(database) = &self.selected_entry {
+            if DatabaseKind::from_protocol(&database.spec.protocol).is_some() {
+                self.methods.push(ConnectMethod::Query);
+            }
+            if DatabaseKind::from_protocol(&database.spec.protocol) == Some(DatabaseKind::Postgres) {
+                self.methods.push(ConnectMethod::Activity);
+            }
+            self.methods.push(ConnectMethod::HealthCheck);
+            self.methods.extend(
+                self.templates
+                    .iter()
+                    .filter(|template| template.applies_to(database))
+                    .cloned()
+                    .map(ConnectMethod::Template),
+            );
+        }
+        self.method_state.select(Some(0));
+        self.current_step = Step::MethodSelection;
+    }
+
+    pub fn current_input(&mut self) -> Option<&mut TextInput> {
+        match self.current_step {
+            Step::InstanceConfirmation => Some(&mut self.guardrail_confirmation.input),
+            Step::Reason => Some(&mut self.reason_input),
+            _ => None,
+        }
+    }
+
+    pub fn reason(&self) -> Option<String> {
+        let reason = self.reason_input.value.trim();
+        if reason.is_empty() {
+            None
+        } else {
+            Some(reason.to_string())
+        }
+    }
+
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        match self.current_step {
+            Step::DatabaseInput => {
+                self.database_name_input.set_cursor(frame, area);
+            },
+            Step::InstanceConfirmation => self.guardrail_confirmation.set_cursor(frame, area),
+            Step::Reason => {
+                let [_, input_area] = guardrail_dialog_areas(area);
+                self.reason_input.set_cursor(frame, input_area);
+            },
+            _ => {},
+        }
+    }
+
+    fn navigate_to_guardrail(&mut self) {
+        self.guardrail = self.selected_entry
+            .as_ref()
+            .and_then(|database| find_guardrail(&self.guardrails, database))
+            .cloned();
+
+        let guarded = match (&self.selected_entry, &self.guardrail) {
+            (Some(database), Some(guardrail)) => vec![(database.metadata.name.clone(), guardrail.clone())],
+            _ => vec![],
+        };
+        if self.guardrail_confirmation.start(guarded) {
+            self.current_step = Step::InstanceConfirmation;
+        } else {
+            self.navigate_to_confirmation();
+        }
+    }
+
+    fn confirm_instance_name(&mut self) {
+        if !self.guardrail_confirmation.submit() {
+            return;
+        }
+
+        match &self.guardrail {
+            Some(guardrail) if guardrail.require_reason => self.current_step = Step::Reason,
+            _ => self.navigate_to_confirmation(),
+        }
+    }
+
+    fn confirm_reason(&mut self) {
+        if self.reason().is_some() {
+            self.navigate_to_confirmation();
+        }
+    }
+
+    // Guarded instances start on No so a stray <enter> never connects
     fn navigate_to_confirmation(&mut self) {
-        if !self.db_name.is_empty() {
-            self.current_step = Step::Confirmation;
+        if self.guardrail.is_some() {
+            self.confirmation_toggle.select(ConfirmationOption::No);
         }
+        self.current_step = Step::Confirmation;
     }
 
     fn connect(&mut self) {
         match self.confirmation_toggle.get_selected() {
-            ConfirmationOption::Yes => self.ready_to_connect = Some(true),
+            ConfirmationOption::Yes => {
+                // An unresolved template cannot be launched, the error stays on screen
+                if let ConnectMethod::Template(_) = self.selected_method() {
+                    if !matches!(self.template_preview, Some(Ok(_))) {
+                        return;
+                    }
+                }
+                self.ready_to_connect = Some(true)
+            },
             ConfirmationOption::No => self.ready_to_connect = Some(false),
         }
     }
@@ -118,7 +359,7 @@ impl ConnectDialog {
     fn render_user_selection(&self, area: Rect, buf: &mut Buffer) {
         let user_select_dialog_area = get_dialog_layout(30, 30, area);
 
-        let block = Block::new()
+        let block = with_default_hint(Block::new(), "Preselected", &self.defaults.db_user)
             .title(" Select User ")
             .borders(Borders::ALL)
             .padding(Padding::new(1, 1, 1, 1));
@@ -132,7 +373,7 @@ impl ConnectDialog {
     fn render_db_name_input(&self, area: Rect, buf: &mut Buffer) {
         let database_input_dialog_area = get_dialog_layout(30, 10, area);
 
-        let block = Block::new()
+        let block = with_default_hint(Block::new(), "Prefilled", &self.defaults.db_name)
             .title(" Input Database Name ")
             .borders(Borders::ALL)
             .padding(Padding::new(1, 1, 1, 1));
@@ -143,11 +384,44 @@ impl ConnectDialog {
         self.database_name_input.render(database_input_dialog_area, buf);
     }
 
+    fn render_method_selection(&self, area: Rect, buf: &mut Buffer) {
+        let method_dialog_area = get_dialog_layout(40, 30, area);
+
+        let block = Block::new()
+            .title(" Connect With ")
+            .borders(Borders::ALL)
+            .padding(Padding::new(5, 5, 2, 1));
+
+        let items: Vec<ListItem> = self.methods
+            .iter()
+            .map(|method| ListItem::from(method.label()))
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(styles::SELECTED_STYLE)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        Widget::render(Clear, method_dialog_area, buf);
+        StatefulWidget::render(list, method_dialog_area, buf, &mut self.method_state.clone());
+    }
+
     fn render_confirmation(&self, area: Rect, buf: &mut Buffer) {
+        if let ConnectMethod::Template(template) = self.selected_method() {
+            self.render_template_confirmation(&template, area, buf);
+            return;
+        }
+
         let confirmation_dialog_area = get_dialog_layout(30, 15, area);
 
-        let block = Block::new()
-            .title(" Connect? ")
+        let block = match &self.guardrail {
+            Some(guardrail) => Block::new()
+                .title(" Connect to protected instance? ")
+                .border_style(Style::new().fg(guardrail.color())),
+            None => Block::new().title(" Connect? "),
+        };
+        let block = with_session_limit_hint(block, &self.session_limit)
             .borders(Borders::ALL)
             .padding(Padding::new(1, 1, 1, 1));
 
@@ -156,4 +430,83 @@ impl ConnectDialog {
 
         self.confirmation_toggle.render(confirmation_dialog_area, buf);
     }
+
+    fn render_template_confirmation(&self, template: &CommandTemplate, area: Rect, buf: &mut Buffer) {
+        let confirmation_dialog_area = get_dialog_layout(60, 35, area);
+
+        let block = Block::new()
+            .title(format!(" Run {}? ", template.name))
+            .border_style(self.guardrail_style())
+            .borders(Borders::ALL)
+            .padding(Padding::new(2, 2, 1, 0));
+        let block = with_session_limit_hint(block, &self.session_limit);
+
+        let inner_area = block.inner(confirmation_dialog_area);
+        let [preview_area, toggle_area] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(5),
+        ])
+        .areas(inner_area);
+
+        let mut lines = vec![];
+        match &self.template_preview {
+            Some(Ok(prepared)) => {
+                lines.push(Line::from("Command:"));
+                lines.push(Line::from(Span::styled(prepared.command.clone(), Style::new().fg(Color::Green))));
+                if let Some(port) = prepared.tunnel_port {
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(format!("Runs through a tsh proxy db tunnel on port {}", port)));
+                }
+            },
+            Some(Err(err)) => {
+                lines.push(Line::from("Template cannot be launched:"));
+                lines.push(Line::from(Span::styled(err.clone(), Style::new().fg(Color::Red))));
+            },
+            None => lines.push(Line::from("Resolving template...")),
+        }
+
+        Widget::render(Clear, confirmation_dialog_area, buf);
+        Widget::render(block, confirmation_dialog_area, buf);
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .render(preview_area, buf);
+
+        self.confirmation_toggle.render(toggle_area, buf);
+    }
+
+    fn render_reason_input(&self, area: Rect, buf: &mut Buffer) {
+        let lines = vec![
+            Line::from("A reason is required to connect to this instance."),
+            Line::from("It is recorded in the local audit log."),
+        ];
+
+        render_guardrail_dialog(" Connection Reason ", lines, &self.reason_input, self.guardrail_style(), area, buf);
+    }
+
+    fn guardrail_style(&self) -> Style {
+        match &self.guardrail {
+            Some(guardrail) => Style::new().fg(guardrail.color()),
+            None => Style::new(),
+        }
+    }
+}
+
+// Shows which default rule picked a value, on the dialog's bottom border
+fn with_default_hint<'a>(block: Block<'a>, action: &str, applied: &Option<AppliedDefault>) -> Block<'a> {
+    match applied {
+        Some(applied) => block.title_bottom(
+            Line::from(format!(" {} {} ", action, applied.hint())).style(Style::new().fg(Color::DarkGray))
+        ),
+        None => block,
+    }
+}
+
+fn with_session_limit_hint<'a>(block: Block<'a>, limit: &Option<SessionLimit>) -> Block<'a> {
+    match limit {
+        Some(limit) => block.title_bottom(
+            Line::from(format!(" Limited to {} ", format_duration(limit.max_duration())))
+                .style(Style::new().fg(Color::Yellow))
+        ),
+        None => block,
+    }
 }
@@ -1,19 +1,38 @@
 #![allow(unused)]
-use std::borrow::Borrow;
+use std::{borrow::Borrow, collections::HashMap};
 
 use ratatui::{
-    buffer::Buffer, layout::{Alignment, Constraint, Layout, Rect}, style::{palette::tailwind::SLATE, Modifier, Style, Stylize}, text::Line, widgets::{
+    buffer::Buffer, layout::{Alignment, Constraint, Layout, Rect}, style::{palette::tailwind::SLATE, Modifier, Style, Stylize}, text::{Line, Span}, widgets::{
         block::{Position, Title}, Block, Borders, HighlightSpacing, List, ListItem, ListState, Padding, Paragraph, StatefulWidget, Widget, Wrap
     }
 };
 use tracing::{event, Level};
-use super::super::core::tsh::Database;
-use crate::widgets::styles;
+use super::super::core::{guardrail::{find_guardrail, Guardrail}, health_check::HealthCheck, resources::Resource, tsh::Database};
+use crate::widgets::{details_table::DetailsTable, health_check_view::status_color, styles};
+
+// Marks instances holding an active database certificate
+const ACTIVE_MARKER: &str = "●";
+
+// Marks instances picked for a fan-out query
+const MARKED_MARKER: &str = "✚";
+
+// Last connection test of an instance, a partial test only reached the tunnel
+const HEALTHY_MARKER: &str = "✓";
+const PARTIAL_MARKER: &str = "~";
+const UNHEALTHY_MARKER: &str = "✗";
+const TESTING_MARKER: &str = "…";
 
-#[derive(Debug, Default)]
 pub struct StatefulDatabaseList {
     pub state: ListState,
     pub items: Vec<Database>,
+    pub active: Vec<String>,
+    // Instance names, kept across searches
+    pub marked: Vec<String>,
+    // Last connection test per instance name, kept across searches
+    pub health: HashMap<String, HealthCheck>,
+    pub testing: Option<String>,
+    pub details: DetailsTable,
+    guardrails: Vec<Guardrail>,
 }
 
 impl StatefulDatabaseList {
@@ -21,16 +40,69 @@ impl StatefulDatabaseList {
         StatefulDatabaseList {
             state: ListState::default(),
             items: vec![],
+            active: vec![],
+            marked: vec![],
+            health: HashMap::new(),
+            testing: None,
+            details: DetailsTable::new(),
+            guardrails: vec![],
         }
     }
 
+    pub fn with_guardrails(&mut self, guardrails: Vec<Guardrail>) {
+        self.guardrails = guardrails;
+    }
+
     pub fn with_items(&mut self, items: Vec<Database>) {
         self.items = items;
+
+        let sections = self.selected_item()
+            .map(|database| database.details())
+            .unwrap_or_default();
+        self.details.with_sections(sections);
+    }
+
+    pub fn select_next(&mut self) {
+        self.state.select_next();
+        self.details.reset();
+    }
+
+    pub fn select_previous(&mut self) {
+        self.state.select_previous();
+        self.details.reset();
+    }
+
+    pub fn with_active(&mut self, active: Vec<String>) {
+        self.active = active;
+    }
+
+    pub fn selected_item(&self) -> Option<&Database> {
+        self.state.selected().and_then(|index| self.items.get(index))
+    }
+
+    pub fn toggle_marked(&mut self) {
+        let Some(name) = self.selected_item().map(|database| database.metadata.name.clone()) else {
+            return;
+        };
+        match self.marked.iter().position(|marked| *marked == name) {
+            Some(position) => {
+                self.marked.remove(position);
+            },
+            None => self.marked.push(name),
+        }
+    }
+
+    pub fn clear_marked(&mut self) {
+        self.marked.clear();
     }
 
     fn render_database_entries(&self, area: Rect, buf: &mut Buffer) {
         let entry_count = self.items.len();
-        let title = [" Databases ", "(", entry_count.to_string().as_str(), ") "].join("");
+        let title = if self.marked.is_empty() {
+            format!(" Databases ({}) ", entry_count)
+        } else {
+            format!(" Databases ({}, {} marked) ", entry_count, self.marked.len())
+        };
 
         let block = Block::new()
             .title(Line::raw(title).centered())
@@ -42,7 +114,29 @@ impl StatefulDatabaseList {
             .iter()
             .enumerate()
             .map(|(i, item)| {
-                ListItem::from(item.metadata.name.clone())
+                let name = &item.metadata.name;
+                let active = self.active.contains(name);
+                let marked = if self.marked.contains(name) { MARKED_MARKER } else { " " };
+                let active_marker = if active { ACTIVE_MARKER } else { " " };
+                let health = match (self.testing.as_ref() == Some(name), self.health.get(name)) {
+                    (true, _) => Span::raw(TESTING_MARKER),
+                    (false, Some(check)) if !check.is_healthy() => Span::raw(UNHEALTHY_MARKER).fg(status_color(check)),
+                    (false, Some(check)) if check.is_partial() => Span::raw(PARTIAL_MARKER).fg(status_color(check)),
+                    (false, Some(check)) => Span::raw(HEALTHY_MARKER).fg(status_color(check)),
+                    (false, None) => Span::raw(" "),
+                };
+                let entry = ListItem::from(Line::from(vec![
+                    Span::raw(format!("{}{}", marked, active_marker)),
+                    health,
+                    Span::raw(format!(" {}", name)),
+                ]));
+
+                // Guarded instances keep their color even with an active certificate
+                match find_guardrail(&self.guardrails, item) {
+                    Some(guardrail) => entry.fg(guardrail.color()),
+                    None if active => entry.green(),
+                    None => entry,
+                }
             })
             .collect();
 
@@ -56,24 +150,8 @@ impl StatefulDatabaseList {
     }
 
     fn render_database_details(&self, area: Rect, buf: &mut Buffer) {
-        let selected = self.state.selected();
-        match selected {
-            Some(index) => {
-                let entry = self.items[index].clone();
-
-                let block = Block::new()
-                    .title(Line::raw(" Details ").centered())
-                    .borders(Borders::ALL)
-                    .padding(Padding::new(5, 5, 1, 1));
-
-                let details = entry.format_details();
-
-                Paragraph::new(details)
-                    .wrap(Wrap { trim: true })
-                    .block(block)
-                    .render(area, buf);
-            },
-            None => {},
+        if self.selected_item().is_some() {
+            self.details.render(area, buf);
         }
     }
 }
@@ -20,7 +20,7 @@ impl Widget for &DatabaseNameInput {
 
 impl DatabaseNameInput {
     pub fn new() -> DatabaseNameInput {
-        return DatabaseNameInput {
+        DatabaseNameInput {
             database_name: String::new(), 
             cursor_index: 0,
         }
@@ -75,6 +75,11 @@ impl DatabaseNameInput {
         self.move_cursor_left();
     }
 
+    pub fn set_value(&mut self, value: &str) {
+        self.database_name = value.to_string();
+        self.cursor_index = self.database_name.chars().count();
+    }
+
     pub fn reset(&mut self) {
         self.database_name.clear();
         self.cursor_index = 0;
@@ -82,6 +87,6 @@ impl DatabaseNameInput {
 
     fn clamp_index(&self, index: usize) -> usize {
         let char_count = self.database_name.chars().count();
-        return index.clamp(0, char_count)
+        index.clamp(0, char_count)
     }
 }
@@ -0,0 +1,138 @@
+use std::cell::RefCell;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::{Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Cell, HighlightSpacing, Padding, Row, StatefulWidget, Table, TableState},
+};
+
+use crate::{core::resources::DetailSection, widgets::styles};
+
+enum DetailRow {
+    Section(String),
+    Field(String, String),
+}
+
+// Key/value table of a resource's details, focusable and scrollable.
+// The state is borrowed while rendering so the scroll offset survives between frames.
+pub struct DetailsTable {
+    state: RefCell<TableState>,
+    pub focused: bool,
+    rows: Vec<DetailRow>,
+}
+
+impl DetailsTable {
+    pub fn new() -> DetailsTable {
+        DetailsTable {
+            state: RefCell::new(TableState::default()),
+            focused: false,
+            rows: vec![],
+        }
+    }
+
+    pub fn with_sections(&mut self, sections: Vec<DetailSection>) {
+        self.rows.clear();
+        for section in sections {
+            self.rows.push(DetailRow::Section(section.title));
+            for (key, value) in section.fields {
+                self.rows.push(DetailRow::Field(key, value));
+            }
+        }
+
+        let selection_valid = self.state
+            .borrow()
+            .selected()
+            .is_some_and(|index| self.is_field(index));
+        if !selection_valid {
+            let first = self.next_field(0);
+            self.state.get_mut().select(first);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self.state.get_mut() = TableState::default();
+    }
+
+    // Section rows are skipped, only fields can be highlighted
+    pub fn select_next(&mut self) {
+        let start = self.state.get_mut().selected().map_or(0, |index| index + 1);
+        if let Some(index) = self.next_field(start) {
+            self.state.get_mut().select(Some(index));
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        let end = self.state.get_mut().selected().unwrap_or(0);
+        let previous = (0..end).rev().find(|index| self.is_field(*index));
+        if let Some(index) = previous {
+            self.state.get_mut().select(Some(index));
+        }
+    }
+
+    pub fn selected_field(&self) -> Option<(&str, &str)> {
+        match self.state.borrow().selected().and_then(|index| self.rows.get(index)) {
+            Some(DetailRow::Field(key, value)) => Some((key, value)),
+            _ => None,
+        }
+    }
+
+    fn is_field(&self, index: usize) -> bool {
+        matches!(self.rows.get(index), Some(DetailRow::Field(_, _)))
+    }
+
+    fn next_field(&self, start: usize) -> Option<usize> {
+        (start..self.rows.len()).find(|index| self.is_field(*index))
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let title = if self.focused { " Details (<y> Copy) " } else { " Details " };
+
+        let block = Block::new()
+            .title(Line::raw(title).centered())
+            .borders(Borders::ALL)
+            .padding(Padding::new(5, 5, 1, 1));
+        let block = if self.focused {
+            block.border_style(Style::new().bold())
+        } else {
+            block
+        };
+
+        let rows: Vec<Row> = self
+            .rows
+            .iter()
+            .map(|row| match row {
+                DetailRow::Section(title) => Row::new(vec![
+                    Cell::from(title.clone()).bold().underlined(),
+                ]),
+                DetailRow::Field(key, value) => Row::new(vec![
+                    Cell::from(format!("  {}", key)).dark_gray(),
+                    Cell::from(value.clone()),
+                ]),
+            })
+            .collect();
+
+        let key_width = self.rows
+            .iter()
+            .map(|row| match row {
+                DetailRow::Section(title) => title.chars().count(),
+                DetailRow::Field(key, _) => key.chars().count() + 2,
+            })
+            .max()
+            .unwrap_or(0) as u16;
+
+        let highlight_style = if self.focused {
+            styles::SELECTED_STYLE
+        } else {
+            Style::new()
+        };
+
+        let table = Table::new(rows, [Constraint::Length(key_width), Constraint::Min(0)])
+            .block(block)
+            .highlight_style(highlight_style)
+            .highlight_spacing(HighlightSpacing::Always)
+            .highlight_symbol(if self.focused { ">" } else { " " });
+
+        StatefulWidget::render(table, area, buf, &mut self.state.borrow_mut());
+    }
+}
@@ -0,0 +1,246 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    widgets::{Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Padding, Paragraph, StatefulWidget, Widget, Wrap},
+    Frame
+};
+
+use crate::{
+    core::{
+        db_config::{DbConfig, ExportFormat},
+        tsh::{ConnectionArgs, Database},
+    },
+    widgets::{dialog::get_dialog_layout, styles, text_input::TextInput, user_list::StatefulUserList},
+};
+
+pub enum ExportStep {
+    UserSelection,
+    DatabaseInput,
+    FormatSelection,
+    PathInput,
+}
+
+pub struct ExportDialog {
+    pub user_list: StatefulUserList,
+    pub database_name_input: TextInput,
+    pub format_state: ListState,
+    pub path_input: TextInput,
+    pub selected_entry: Option<Database>,
+    pub db_config: Option<DbConfig>,
+    // What the export would replace, set once the user was warned about it
+    pub overwrite: Option<String>,
+    pub current_step: ExportStep,
+}
+
+impl Widget for &ExportDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        match self.current_step {
+            ExportStep::UserSelection => self.render_user_selection(area, buf),
+            ExportStep::DatabaseInput => self.render_input(" Input Database Name ", &self.database_name_input, area, buf),
+            ExportStep::FormatSelection => self.render_format_selection(area, buf),
+            ExportStep::PathInput => self.render_path_input(area, buf),
+        }
+    }
+}
+
+impl ExportDialog {
+    pub fn new() -> ExportDialog {
+        ExportDialog {
+            user_list: StatefulUserList::new(),
+            database_name_input: TextInput::new(),
+            format_state: ListState::default(),
+            path_input: TextInput::new(),
+            selected_entry: None,
+            db_config: None,
+            overwrite: None,
+            current_step: ExportStep::UserSelection,
+        }
+    }
+
+    pub fn open(&mut self, database: Database) {
+        self.reset();
+        self.user_list.with_items(database.users.allowed.clone());
+        self.user_list.state.select(Some(0));
+        self.format_state.select(Some(0));
+        self.selected_entry = Some(database);
+    }
+
+    pub fn reset(&mut self) {
+        self.user_list.reset();
+        self.database_name_input.reset();
+        self.path_input.reset();
+        self.format_state = ListState::default();
+        self.selected_entry = None;
+        self.db_config = None;
+        self.overwrite = None;
+        self.current_step = ExportStep::UserSelection;
+    }
+
+    pub fn current_input(&mut self) -> Option<&mut TextInput> {
+        match self.current_step {
+            ExportStep::DatabaseInput => Some(&mut self.database_name_input),
+            ExportStep::PathInput => Some(&mut self.path_input),
+            _ => None,
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        match self.current_step {
+            ExportStep::UserSelection => self.user_list.state.select_next(),
+            ExportStep::FormatSelection => self.format_state.select_next(),
+            _ => {},
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        match self.current_step {
+            ExportStep::UserSelection => self.user_list.state.select_previous(),
+            ExportStep::FormatSelection => self.format_state.select_previous(),
+            _ => {},
+        }
+    }
+
+    pub fn selected_format(&self) -> ExportFormat {
+        self.format_state
+            .selected()
+            .and_then(|index| ExportFormat::ALL.get(index))
+            .copied()
+            .unwrap_or(ExportFormat::Libpq)
+    }
+
+    pub fn selected_user(&self) -> Option<&String> {
+        self.user_list.state
+            .selected()
+            .and_then(|index| self.user_list.items.get(index))
+    }
+
+    pub fn to_connection_args(&self) -> Option<ConnectionArgs> {
+        let database = self.selected_entry.as_ref()?;
+        Some(ConnectionArgs {
+            instance: database.metadata.name.clone(),
+            db_user: self.selected_user()?.clone(),
+            db_name: self.database_name_input.value.clone(),
+        })
+    }
+
+    pub fn navigate_to_format(&mut self, db_config: DbConfig) {
+        self.db_config = Some(db_config);
+        self.current_step = ExportStep::FormatSelection;
+    }
+
+    pub fn navigate_to_path(&mut self) {
+        let instance = self.selected_entry
+            .as_ref()
+            .map(|database| database.metadata.name.clone())
+            .unwrap_or_default();
+        let default_path = self.selected_format().default_path(&instance);
+        self.path_input.set_value(&default_path);
+        self.current_step = ExportStep::PathInput;
+    }
+
+    pub fn rendered_config(&self) -> Option<String> {
+        self.db_config
+            .as_ref()
+            .map(|config| self.selected_format().render(config))
+    }
+
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        match self.current_step {
+            ExportStep::DatabaseInput => {
+                self.database_name_input.set_cursor(frame, get_dialog_layout(30, 10, area));
+            },
+            ExportStep::PathInput => {
+                let [input_area, _] = path_dialog_areas(area);
+                self.path_input.set_cursor(frame, input_area);
+            },
+            _ => {},
+        }
+    }
+
+    fn render_user_selection(&self, area: Rect, buf: &mut Buffer) {
+        let user_select_dialog_area = get_dialog_layout(30, 30, area);
+
+        let block = Block::new()
+            .title(" Export: Select User ")
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        Widget::render(Clear, user_select_dialog_area, buf);
+        Widget::render(block, user_select_dialog_area, buf);
+
+        self.user_list.render(user_select_dialog_area, buf);
+    }
+
+    fn render_input(&self, title: &str, input: &TextInput, area: Rect, buf: &mut Buffer) {
+        let input_dialog_area = get_dialog_layout(30, 10, area);
+
+        let block = Block::new()
+            .title(title)
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        Widget::render(Clear, input_dialog_area, buf);
+        Widget::render(block, input_dialog_area, buf);
+
+        input.render(input_dialog_area, buf);
+    }
+
+    fn render_format_selection(&self, area: Rect, buf: &mut Buffer) {
+        let format_dialog_area = get_dialog_layout(30, 30, area);
+
+        let block = Block::new()
+            .title(" Export Format ")
+            .borders(Borders::ALL)
+            .padding(Padding::new(5, 5, 2, 1));
+
+        let items: Vec<ListItem> = ExportFormat::ALL
+            .iter()
+            .map(|format| ListItem::from(format.to_string()))
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(styles::SELECTED_STYLE)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        Widget::render(Clear, format_dialog_area, buf);
+        StatefulWidget::render(list, format_dialog_area, buf, &mut self.format_state.clone());
+    }
+
+    fn render_path_input(&self, area: Rect, buf: &mut Buffer) {
+        let [input_area, preview_area] = path_dialog_areas(area);
+
+        let title = match &self.overwrite {
+            Some(conflict) => format!(" {}, <enter> to overwrite ", conflict),
+            None => " Export Path ".to_string(),
+        };
+        let input_block = Block::new()
+            .title(title)
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        Widget::render(Clear, input_area, buf);
+        Widget::render(input_block, input_area, buf);
+        self.path_input.render(input_area, buf);
+
+        let preview_block = Block::new()
+            .title(format!(" Preview: {} ", self.selected_format().to_string()))
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        Widget::render(Clear, preview_area, buf);
+        Paragraph::new(self.rendered_config().unwrap_or_default())
+            .wrap(Wrap { trim: false })
+            .block(preview_block)
+            .render(preview_area, buf);
+    }
+}
+
+fn path_dialog_areas(area: Rect) -> [Rect; 2] {
+    let dialog_area = get_dialog_layout(60, 60, area);
+    Layout::vertical([
+        Constraint::Length(5),
+        Constraint::Min(0),
+    ]).areas(dialog_area)
+}
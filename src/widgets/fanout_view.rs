@@ -0,0 +1,465 @@
+use std::time::{Duration, Instant};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, HighlightSpacing, List, ListItem, ListState, Paragraph, StatefulWidget, Widget, Wrap},
+    Frame
+};
+
+use crate::{
+    core::{
+        fanout::{FanoutEvent, FanoutOutcome, FanoutRun},
+        guardrail::Guardrail,
+        query::QueryResult,
+        tsh::ConnectionArgs,
+    },
+    widgets::{
+        guardrail_confirmation::GuardrailConfirmation,
+        results_table::ResultsTable,
+        text_editor::TextEditor,
+        text_input::TextInput,
+    },
+};
+
+// Column added in front of every row of the combined result
+const INSTANCE_COLUMN: &str = "instance";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FanoutFocus {
+    User,
+    Name,
+    Reason,
+    Editor,
+    Instances,
+    Results,
+}
+
+enum InstanceState {
+    Queued,
+    Running,
+    Done(String),
+    Failed(String),
+}
+
+struct InstanceStatus {
+    instance: String,
+    state: InstanceState,
+    // What the instance is queried as, unset when it could not be resolved
+    args: Option<ConnectionArgs>,
+}
+
+// One statement run on every marked database, results merged into one table
+pub struct FanoutView {
+    pub user_input: TextInput,
+    pub name_input: TextInput,
+    pub reason_input: TextInput,
+    pub editor: TextEditor,
+    pub results: ResultsTable,
+    pub state: ListState,
+    pub focus: FanoutFocus,
+    // Guarded instances among the targets, each name is typed before running
+    pub guarded: Vec<(String, Guardrail)>,
+    pub confirmation: GuardrailConfirmation,
+    pub require_reason: bool,
+    instances: Vec<InstanceStatus>,
+    combined: QueryResult,
+    run: Option<FanoutRun>,
+    started: Option<Instant>,
+    elapsed: Duration,
+}
+
+impl Widget for &FanoutView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [status_area, inputs_area, editor_area, output_area] = fanout_areas(area);
+        let [instances_area, results_area] = output_areas(output_area);
+
+        self.render_status(status_area, buf);
+        self.render_inputs(inputs_area, buf);
+        self.render_editor(editor_area, buf);
+        self.render_instances(instances_area, buf);
+
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .border_style(focus_style(self.focus == FanoutFocus::Results));
+        self.results.render(results_area, buf, block);
+
+        if self.confirmation.is_active() {
+            self.confirmation.render(area, buf);
+        }
+    }
+}
+
+impl FanoutView {
+    pub fn new() -> FanoutView {
+        FanoutView {
+            user_input: TextInput::new(),
+            name_input: TextInput::new(),
+            reason_input: TextInput::new(),
+            editor: TextEditor::new(),
+            results: ResultsTable::new(),
+            state: ListState::default(),
+            focus: FanoutFocus::Editor,
+            guarded: vec![],
+            confirmation: GuardrailConfirmation::new(),
+            require_reason: false,
+            instances: vec![],
+            combined: QueryResult::default(),
+            run: None,
+            started: None,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn open(&mut self, instances: Vec<String>, guarded: Vec<(String, Guardrail)>, require_reason: bool) {
+        self.reset();
+        self.instances = instances
+            .into_iter()
+            .map(|instance| InstanceStatus { instance, state: InstanceState::Queued, args: None })
+            .collect();
+        self.state.select(if self.instances.is_empty() { None } else { Some(0) });
+        self.guarded = guarded;
+        self.require_reason = require_reason;
+    }
+
+    // The editor and the user and database name keep their values for the next run
+    pub fn reset(&mut self) {
+        self.run = None;
+        self.instances.clear();
+        self.state = ListState::default();
+        self.results.reset();
+        self.reason_input.reset();
+        self.combined = QueryResult::default();
+        self.guarded.clear();
+        self.confirmation.reset();
+        self.require_reason = false;
+        self.started = None;
+        self.focus = FanoutFocus::Editor;
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.instances.is_empty()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.run.is_some()
+    }
+
+    pub fn instance_names(&self) -> Vec<String> {
+        self.instances.iter().map(|status| status.instance.clone()).collect()
+    }
+
+    // Instances without a target are failed up front, eg. when no user applies
+    pub fn start(&mut self, run: FanoutRun, targets: Vec<ConnectionArgs>, failures: Vec<(String, String)>) {
+        for status in self.instances.iter_mut() {
+            status.args = targets.iter().find(|args| args.instance == status.instance).cloned();
+            status.state = match failures.iter().find(|(instance, _)| *instance == status.instance) {
+                Some((_, err)) => InstanceState::Failed(err.clone()),
+                None => InstanceState::Queued,
+            };
+        }
+        self.combined = QueryResult::default();
+        self.combined.columns = vec![INSTANCE_COLUMN.to_string()];
+        self.results.reset();
+        self.run = Some(run);
+        self.started = Some(Instant::now());
+        self.focus = FanoutFocus::Instances;
+    }
+
+    // Targets still queued or running, with how long the run has been going
+    pub fn unfinished(&self) -> Vec<(ConnectionArgs, Duration)> {
+        if self.run.is_none() {
+            return vec![];
+        }
+        let elapsed = self.started.map(|started| started.elapsed()).unwrap_or_default();
+        self.instances
+            .iter()
+            .filter(|status| matches!(status.state, InstanceState::Queued | InstanceState::Running))
+            .filter_map(|status| status.args.clone())
+            .map(|args| (args, elapsed))
+            .collect()
+    }
+
+    pub fn cancel(&self) {
+        if let Some(run) = &self.run {
+            run.cancel();
+        }
+    }
+
+    // Returns the finished instances so they can be audited
+    pub fn poll(&mut self) -> Vec<FanoutOutcome> {
+        let Some(run) = &self.run else {
+            return vec![];
+        };
+        let mut events = vec![];
+        while let Some(event) = run.try_event() {
+            events.push(event);
+        }
+
+        let mut outcomes = vec![];
+        for event in events {
+            match event {
+                FanoutEvent::Started(instance) => self.set_state(&instance, InstanceState::Running),
+                FanoutEvent::Finished(outcome) => {
+                    let state = match &outcome.result {
+                        Ok(result) => {
+                            self.merge(&outcome.args.instance, result);
+                            InstanceState::Done(format!("{} in {}", result_label(result), elapsed_label(outcome.elapsed)))
+                        },
+                        Err(err) => InstanceState::Failed(err.clone()),
+                    };
+                    self.set_state(&outcome.args.instance, state);
+                    outcomes.push(outcome);
+                },
+            }
+        }
+
+        let pending = self.instances
+            .iter()
+            .any(|status| matches!(status.state, InstanceState::Queued | InstanceState::Running));
+        if !pending {
+            self.run = None;
+            self.elapsed = self.started.map(|started| started.elapsed()).unwrap_or_default();
+        }
+        outcomes
+    }
+
+    pub fn next_focus(&mut self) {
+        self.focus = match self.focus {
+            FanoutFocus::User => FanoutFocus::Name,
+            FanoutFocus::Name if self.require_reason => FanoutFocus::Reason,
+            FanoutFocus::Name | FanoutFocus::Reason => FanoutFocus::Editor,
+            FanoutFocus::Editor => FanoutFocus::Instances,
+            FanoutFocus::Instances => FanoutFocus::Results,
+            FanoutFocus::Results => FanoutFocus::User,
+        };
+    }
+
+    pub fn focused_input(&mut self) -> Option<&mut TextInput> {
+        match self.focus {
+            FanoutFocus::User => Some(&mut self.user_input),
+            FanoutFocus::Name => Some(&mut self.name_input),
+            FanoutFocus::Reason => Some(&mut self.reason_input),
+            _ => None,
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        let next = self.state.selected().map_or(0, |index| (index + 1).min(self.instances.len().saturating_sub(1)));
+        self.state.select(Some(next));
+    }
+
+    pub fn select_previous(&mut self) {
+        let previous = self.state.selected().map_or(0, |index| index.saturating_sub(1));
+        self.state.select(Some(previous));
+    }
+
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        if self.confirmation.is_active() {
+            self.confirmation.set_cursor(frame, area);
+            return;
+        }
+        let [_, inputs_area, editor_area, _] = fanout_areas(area);
+        let [user_area, name_area, reason_area] = input_areas(inputs_area);
+        match self.focus {
+            FanoutFocus::User => self.user_input.set_cursor(frame, user_area),
+            FanoutFocus::Name => self.name_input.set_cursor(frame, name_area),
+            FanoutFocus::Reason => self.reason_input.set_cursor(frame, reason_area),
+            FanoutFocus::Editor => self.editor.set_cursor(frame, editor_block(true).inner(editor_area)),
+            _ => {},
+        }
+    }
+
+    fn set_state(&mut self, instance: &str, state: InstanceState) {
+        if let Some(status) = self.instances.iter_mut().find(|status| status.instance == instance) {
+            status.state = state;
+        }
+    }
+
+    // Columns are matched by name, a column only some instances return is empty for the others
+    fn merge(&mut self, instance: &str, result: &QueryResult) {
+        if result.columns.is_empty() {
+            return;
+        }
+        let mut positions = vec![];
+        for column in &result.columns {
+            let position = match self.combined.columns.iter().skip(1).position(|existing| existing == column) {
+                Some(position) => position + 1,
+                None => {
+                    self.combined.columns.push(column.clone());
+                    for row in self.combined.rows.iter_mut() {
+                        row.push(None);
+                    }
+                    self.combined.columns.len() - 1
+                },
+            };
+            positions.push(position);
+        }
+
+        for values in &result.rows {
+            let mut row = vec![None; self.combined.columns.len()];
+            row[0] = Some(instance.to_string());
+            for (value, position) in values.iter().zip(&positions) {
+                row[*position] = value.clone();
+            }
+            self.combined.push_row(row);
+        }
+        // Rows past the per-instance cap were only counted
+        self.combined.total_rows += result.total_rows - result.rows.len();
+        self.combined.elapsed = self.started.map(|started| started.elapsed()).unwrap_or_default();
+
+        let selected = self.results.state.selected();
+        self.results.set_result(self.combined.clone());
+        if let Some(selected) = selected {
+            self.results.state.select(Some(selected));
+        }
+    }
+
+    fn render_status(&self, area: Rect, buf: &mut Buffer) {
+        let done = self.instances.iter().filter(|status| matches!(status.state, InstanceState::Done(_))).count();
+        let failed = self.instances.iter().filter(|status| matches!(status.state, InstanceState::Failed(_))).count();
+        let total = self.instances.len();
+
+        let (line, style) = if let Some(started) = self.started.filter(|_| self.run.is_some()) {
+            let line = format!(
+                "Running on {} databases · {} done · {} failed · {}s",
+                total, done, failed, started.elapsed().as_secs(),
+            );
+            (line, Style::new().fg(Color::Yellow))
+        } else if self.started.is_some() {
+            let line = format!("Ran on {} databases · {} done · {} failed in {}", total, done, failed, elapsed_label(self.elapsed));
+            let style = if failed > 0 { Style::new().fg(Color::Red) } else { Style::new() };
+            (line, style)
+        } else if !self.guarded.is_empty() {
+            (format!("Fan-out query on {} databases · {} guarded", total, self.guarded.len()), Style::new().fg(Color::Red))
+        } else {
+            (format!("Fan-out query on {} databases", total), Style::new())
+        };
+        Paragraph::new(line).style(style).centered().render(area, buf);
+    }
+
+    fn render_inputs(&self, area: Rect, buf: &mut Buffer) {
+        let [user_area, name_area, reason_area] = input_areas(area);
+        let inputs = [
+            (" Database User (default per instance) ", &self.user_input, FanoutFocus::User, user_area),
+            (" Database Name (default per instance) ", &self.name_input, FanoutFocus::Name, name_area),
+            (" Reason ", &self.reason_input, FanoutFocus::Reason, reason_area),
+        ];
+        for (title, input, focus, input_area) in inputs {
+            if focus == FanoutFocus::Reason && !self.require_reason {
+                continue;
+            }
+            Block::new()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(focus_style(self.focus == focus))
+                .render(input_area, buf);
+            input.render(input_area, buf);
+        }
+    }
+
+    fn render_editor(&self, area: Rect, buf: &mut Buffer) {
+        let block = editor_block(self.focus == FanoutFocus::Editor);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        self.editor.render(inner, buf);
+    }
+
+    fn render_instances(&self, area: Rect, buf: &mut Buffer) {
+        let [list_area, details_area] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(5),
+        ]).areas(area);
+
+        let items: Vec<ListItem> = self.instances
+            .iter()
+            .map(|status| {
+                let (marker, color) = match &status.state {
+                    InstanceState::Queued => ("·", Color::DarkGray),
+                    InstanceState::Running => ("…", Color::Yellow),
+                    InstanceState::Done(_) => ("✓", Color::Green),
+                    InstanceState::Failed(_) => ("✗", Color::Red),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{} ", marker)).fg(color),
+                    Span::raw(status.instance.clone()),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::new()
+                .title(Line::raw(" Instances ").centered())
+                .borders(Borders::ALL)
+                .border_style(focus_style(self.focus == FanoutFocus::Instances)))
+            .highlight_style(Style::new().reversed())
+            .highlight_spacing(HighlightSpacing::Always)
+            .highlight_symbol(">");
+        StatefulWidget::render(list, list_area, buf, &mut self.state.clone());
+
+        let selected = self.state.selected().and_then(|index| self.instances.get(index));
+        let (text, style) = match selected.map(|status| &status.state) {
+            Some(InstanceState::Queued) => ("queued".to_string(), Style::new().dark_gray()),
+            Some(InstanceState::Running) => ("running".to_string(), Style::new().fg(Color::Yellow)),
+            Some(InstanceState::Done(summary)) => (summary.clone(), Style::new()),
+            Some(InstanceState::Failed(err)) => (err.clone(), Style::new().fg(Color::Red)),
+            None => (String::new(), Style::new()),
+        };
+        Paragraph::new(text)
+            .style(style)
+            .wrap(Wrap { trim: false })
+            .block(Block::new().borders(Borders::ALL).border_style(Style::new().dark_gray()))
+            .render(details_area, buf);
+    }
+}
+
+fn result_label(result: &QueryResult) -> String {
+    match (result.columns.is_empty(), result.affected) {
+        (true, Some(affected)) => format!("{} rows affected", affected),
+        (true, None) => "done".to_string(),
+        (false, _) => format!("{} rows", result.total_rows),
+    }
+}
+
+fn elapsed_label(elapsed: Duration) -> String {
+    format!("{:.1}s", elapsed.as_secs_f64())
+}
+
+fn fanout_areas(area: Rect) -> [Rect; 4] {
+    Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(4),
+        Constraint::Percentage(25),
+        Constraint::Min(0),
+    ]).areas(area)
+}
+
+fn input_areas(area: Rect) -> [Rect; 3] {
+    Layout::horizontal([
+        Constraint::Ratio(1, 3),
+        Constraint::Ratio(1, 3),
+        Constraint::Ratio(1, 3),
+    ]).areas(area)
+}
+
+fn output_areas(area: Rect) -> [Rect; 2] {
+    Layout::horizontal([
+        Constraint::Percentage(30),
+        Constraint::Min(0),
+    ]).areas(area)
+}
+
+fn editor_block(focused: bool) -> Block<'static> {
+    Block::new()
+        .title(Line::raw(" SQL ").centered())
+        .borders(Borders::ALL)
+        .border_style(focus_style(focused))
+}
+
+fn focus_style(focused: bool) -> Style {
+    if focused {
+        Style::new().bold()
+    } else {
+        Style::new().dark_gray()
+    }
+}
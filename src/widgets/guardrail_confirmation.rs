@@ -0,0 +1,137 @@
+use std::collections::VecDeque;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Padding, Paragraph, Widget, Wrap},
+    Frame
+};
+
+use crate::{
+    core::guardrail::Guardrail,
+    widgets::{dialog::get_dialog_layout, text_input::TextInput},
+};
+
+// Typed instance names for guarded databases, asked one instance at a time
+pub struct GuardrailConfirmation {
+    pub input: TextInput,
+    pending: VecDeque<(String, Guardrail)>,
+    total: usize,
+    mismatch: bool,
+}
+
+impl Widget for &GuardrailConfirmation {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let Some((instance, guardrail)) = self.pending.front() else {
+            return;
+        };
+        let style = Style::new().fg(guardrail.color());
+
+        let mut lines = vec![
+            Line::from(vec![
+                Span::styled(instance.clone(), style),
+                Span::raw(format!(" matches the `{}` guardrail.", guardrail.selector.to_string())),
+            ]),
+            Line::from(""),
+            Line::from("Type the instance name to continue."),
+        ];
+        if self.mismatch {
+            lines.push(Line::from(Span::styled(
+                "The instance name does not match.",
+                Style::new().fg(Color::Red),
+            )));
+        }
+
+        let title = if self.total > 1 {
+            format!(" Protected Instance {} of {} ", self.total - self.pending.len() + 1, self.total)
+        } else {
+            " Protected Instance ".to_string()
+        };
+        render_guardrail_dialog(&title, lines, &self.input, style, area, buf);
+    }
+}
+
+impl GuardrailConfirmation {
+    pub fn new() -> GuardrailConfirmation {
+        GuardrailConfirmation {
+            input: TextInput::new(),
+            pending: VecDeque::new(),
+            total: 0,
+            mismatch: false,
+        }
+    }
+
+    // Returns false when none of the instances is guarded and there is nothing to confirm
+    pub fn start(&mut self, guarded: Vec<(String, Guardrail)>) -> bool {
+        self.reset();
+        self.total = guarded.len();
+        self.pending = guarded.into();
+        self.is_active()
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn reset(&mut self) {
+        self.input.reset();
+        self.pending.clear();
+        self.total = 0;
+        self.mismatch = false;
+    }
+
+    // Checks the typed name against the instance shown, true once every instance was confirmed
+    pub fn submit(&mut self) -> bool {
+        let Some((instance, _)) = self.pending.front() else {
+            return true;
+        };
+        self.mismatch = self.input.value.trim() != instance;
+        if self.mismatch {
+            return false;
+        }
+        self.pending.pop_front();
+        self.input.reset();
+        self.pending.is_empty()
+    }
+
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        if self.is_active() {
+            let [_, input_area] = guardrail_dialog_areas(area);
+            self.input.set_cursor(frame, input_area);
+        }
+    }
+}
+
+// A message above a single line input, bordered in the guardrail's color
+pub fn render_guardrail_dialog(title: &str, lines: Vec<Line>, input: &TextInput, style: Style, area: Rect, buf: &mut Buffer) {
+    let [message_area, input_area] = guardrail_dialog_areas(area);
+
+    let message_block = Block::new()
+        .title(title)
+        .border_style(style)
+        .borders(Borders::ALL)
+        .padding(Padding::new(2, 2, 1, 0));
+
+    Widget::render(Clear, message_area, buf);
+    Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(message_block)
+        .render(message_area, buf);
+
+    let input_block = Block::new()
+        .border_style(style)
+        .borders(Borders::ALL);
+
+    Widget::render(Clear, input_area, buf);
+    Widget::render(input_block, input_area, buf);
+    input.render(input_area, buf);
+}
+
+pub fn guardrail_dialog_areas(area: Rect) -> [Rect; 2] {
+    let dialog_area = get_dialog_layout(50, 30, area);
+    Layout::vertical([
+        Constraint::Min(0),
+        Constraint::Length(5),
+    ]).areas(dialog_area)
+}
@@ -0,0 +1,119 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Padding, Paragraph, Widget, Wrap},
+};
+
+use crate::{
+    core::{
+        health_check::{HealthCheck, HealthCheckRun},
+        tsh::{ConnectionArgs, Database},
+    },
+    widgets::dialog::get_dialog_layout,
+};
+
+// Shows the running or last connection test, the test keeps running when the popup is closed
+pub struct HealthCheckView {
+    pub args: Option<ConnectionArgs>,
+    pub database: Option<Database>,
+    pub reason: Option<String>,
+    pub result: Option<HealthCheck>,
+    // Set when the target went through the connect dialog, guarded databases are only tested again then
+    pub confirmed: bool,
+    run: Option<HealthCheckRun>,
+}
+
+impl Widget for &HealthCheckView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let dialog_area = get_dialog_layout(60, 50, area);
+        let mut lines = vec![];
+
+        match (&self.run, &self.result) {
+            (Some(_), _) => {
+                if let Some(args) = &self.args {
+                    lines.push(Line::raw(format!(
+                        "Testing {}@{}/{}...",
+                        args.db_user, args.instance, args.db_name,
+                    )));
+                }
+            },
+            (None, Some(result)) => {
+                let width = result.fields().iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+                for (label, value) in result.fields() {
+                    let value = match label {
+                        "Status" => Span::raw(value).fg(status_color(result)),
+                        _ => Span::raw(value),
+                    };
+                    lines.push(Line::from(vec![Span::raw(format!("{:<width$}  ", label, width = width)).bold(), value]));
+                }
+            },
+            (None, None) => {},
+        }
+        lines.push(Line::raw(""));
+        lines.push(Line::raw("<r> Test Again, <y> Copy Report, <esc> Close"));
+
+        Widget::render(Clear, dialog_area, buf);
+        Paragraph::new(lines)
+            .block(Block::new()
+                .title(" Connection Test ")
+                .borders(Borders::ALL)
+                .border_style(Style::new().fg(self.result.as_ref().map(status_color).unwrap_or(Color::Reset)))
+                .padding(Padding::new(2, 2, 1, 1)))
+            .wrap(Wrap { trim: false })
+            .render(dialog_area, buf);
+    }
+}
+
+impl HealthCheckView {
+    pub fn new() -> HealthCheckView {
+        HealthCheckView {
+            args: None,
+            database: None,
+            reason: None,
+            result: None,
+            confirmed: false,
+            run: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.run.is_some()
+    }
+
+    pub fn start(&mut self, run: HealthCheckRun, args: ConnectionArgs, database: Database, reason: Option<String>) {
+        self.run = Some(run);
+        self.args = Some(args);
+        self.database = Some(database);
+        self.reason = reason;
+        self.result = None;
+        self.confirmed = true;
+    }
+
+    // Shows a cached result without testing again
+    pub fn show(&mut self, result: HealthCheck, database: Database) {
+        self.args = Some(result.args.clone());
+        self.database = Some(database);
+        self.reason = None;
+        self.result = Some(result);
+        self.confirmed = false;
+    }
+
+    pub fn poll(&mut self) -> Option<HealthCheck> {
+        let result = self.run.as_ref()?.try_result()?;
+        self.run = None;
+        self.result = Some(result.clone());
+        Some(result)
+    }
+}
+
+pub fn status_color(result: &HealthCheck) -> Color {
+    if !result.is_healthy() {
+        Color::Red
+    } else if result.is_partial() {
+        Color::Yellow
+    } else {
+        Color::Green
+    }
+}
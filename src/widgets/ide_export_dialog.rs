@@ -0,0 +1,132 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    widgets::{Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Padding, StatefulWidget, Widget},
+    Frame
+};
+
+use crate::{
+    core::ide_export::IdeTarget,
+    widgets::{dialog::get_dialog_layout, styles, text_input::TextInput},
+};
+
+pub enum IdeExportStep {
+    TargetSelection,
+    PathInput,
+}
+
+pub struct IdeExportDialog {
+    pub target_state: ListState,
+    pub path_input: TextInput,
+    pub current_step: IdeExportStep,
+}
+
+impl Widget for &IdeExportDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        match self.current_step {
+            IdeExportStep::TargetSelection => {
+                let items = IdeTarget::ALL.map(|target| target.to_string());
+                render_options(" Export Data Sources To ", &items, &self.target_state, area, buf);
+            },
+            IdeExportStep::PathInput => self.render_path_input(area, buf),
+        }
+    }
+}
+
+impl IdeExportDialog {
+    pub fn new() -> IdeExportDialog {
+        IdeExportDialog {
+            target_state: ListState::default(),
+            path_input: TextInput::new(),
+            current_step: IdeExportStep::TargetSelection,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.reset();
+        self.target_state.select(Some(0));
+    }
+
+    pub fn reset(&mut self) {
+        self.target_state = ListState::default();
+        self.path_input.reset();
+        self.current_step = IdeExportStep::TargetSelection;
+    }
+
+    pub fn selected_target(&self) -> IdeTarget {
+        self.target_state
+            .selected()
+            .and_then(|index| IdeTarget::ALL.get(index))
+            .copied()
+            .unwrap_or(IdeTarget::JetBrains)
+    }
+
+    pub fn select_next(&mut self) {
+        match self.current_step {
+            IdeExportStep::TargetSelection => self.target_state.select_next(),
+            IdeExportStep::PathInput => {},
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        match self.current_step {
+            IdeExportStep::TargetSelection => self.target_state.select_previous(),
+            IdeExportStep::PathInput => {},
+        }
+    }
+
+    // Returns true once the path is confirmed and the export can run
+    pub fn next_step(&mut self) -> bool {
+        match self.current_step {
+            IdeExportStep::TargetSelection => {
+                self.path_input.set_value(&self.selected_target().default_path());
+                self.current_step = IdeExportStep::PathInput;
+            },
+            IdeExportStep::PathInput => return !self.path_input.value.is_empty(),
+        }
+        false
+    }
+
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        if let IdeExportStep::PathInput = self.current_step {
+            self.path_input.set_cursor(frame, get_dialog_layout(60, 10, area));
+        }
+    }
+
+    fn render_path_input(&self, area: Rect, buf: &mut Buffer) {
+        let input_dialog_area = get_dialog_layout(60, 10, area);
+
+        let block = Block::new()
+            .title(" Data Sources File ")
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        Widget::render(Clear, input_dialog_area, buf);
+        Widget::render(block, input_dialog_area, buf);
+
+        self.path_input.render(input_dialog_area, buf);
+    }
+}
+
+pub fn render_options(title: &str, options: &[&str], state: &ListState, area: Rect, buf: &mut Buffer) {
+    let options_dialog_area = get_dialog_layout(40, 30, area);
+
+    let block = Block::new()
+        .title(title)
+        .borders(Borders::ALL)
+        .padding(Padding::new(5, 5, 2, 1));
+
+    let items: Vec<ListItem> = options
+        .iter()
+        .map(|option| ListItem::from(option.to_string()))
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(styles::SELECTED_STYLE)
+        .highlight_symbol(">")
+        .highlight_spacing(HighlightSpacing::Always);
+
+    Widget::render(Clear, options_dialog_area, buf);
+    StatefulWidget::render(list, options_dialog_area, buf, &mut state.clone());
+}
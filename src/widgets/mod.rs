@@ -3,6 +3,30 @@ pub mod dialog;
 pub mod database_list;
 pub mod search_dialog;
 pub mod connect_dialog;
+pub mod guardrail_confirmation;
 pub mod user_list;
 pub mod database_name_input;
 pub mod confirmation_toggle;
+pub mod text_input;
+pub mod request_dialog;
+pub mod access_request_view;
+pub mod review_view;
+pub mod resource_list;
+pub mod ssh_dialog;
+pub mod details_table;
+pub mod toast;
+pub mod export_dialog;
+pub mod ide_export_dialog;
+pub mod audit_view;
+pub mod session_view;
+pub mod replay_view;
+pub mod text_editor;
+pub mod results_table;
+pub mod schema_browser;
+pub mod query_console;
+pub mod result_export_dialog;
+pub mod fanout_view;
+pub mod snippet_dialog;
+pub mod activity_view;
+pub mod schema_diff_view;
+pub mod health_check_view;
@@ -0,0 +1,337 @@
+use std::time::Instant;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Widget, Wrap},
+    Frame
+};
+
+use crate::{
+    core::{
+        query::{CancelHandle, DatabaseKind, QueryConnection, QueryEvent},
+        result_export::{ExportedFile, ResultFormat},
+        schema::SchemaPath,
+        session_limit::{format_duration, SessionDeadline},
+        tsh::ConnectionArgs,
+        worker::HeldConnection,
+    },
+    widgets::{results_table::ResultsTable, schema_browser::SchemaBrowser, text_editor::TextEditor},
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ConsoleFocus {
+    Editor,
+    Results,
+    Schema,
+}
+
+enum ConsoleStatus {
+    Connecting,
+    Ready,
+    Running(Instant),
+    Exporting(Instant),
+    Closed(String),
+}
+
+// SQL editor and result table over a query connection, one console at a time
+pub struct QueryConsole {
+    pub editor: TextEditor,
+    pub results: ResultsTable,
+    pub schema: SchemaBrowser,
+    pub focus: ConsoleFocus,
+    pub session: Option<HeldConnection>,
+    kind: Option<DatabaseKind>,
+    status: ConsoleStatus,
+    error: Option<String>,
+    connection: Option<QueryConnection>,
+    cancel: Option<CancelHandle>,
+    // Statement behind the shown result, exporting to a file runs it again
+    last_sql: Option<String>,
+    // Only becomes `last_sql` once it succeeded, a failed statement leaves the previous result on screen
+    running_sql: Option<String>,
+    exported: Option<Result<ExportedFile, String>>,
+}
+
+impl Widget for &QueryConsole {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [status_area, schema_area, editor_area, results_area] = console_areas(area, self.schema.visible);
+
+        self.render_status(status_area, buf);
+        if self.schema.visible {
+            self.schema.render(schema_area, buf, self.focus == ConsoleFocus::Schema);
+        }
+        self.render_editor(editor_area, buf);
+        self.render_results(results_area, buf);
+    }
+}
+
+impl QueryConsole {
+    pub fn new() -> QueryConsole {
+        QueryConsole {
+            editor: TextEditor::new(),
+            results: ResultsTable::new(),
+            schema: SchemaBrowser::new(),
+            focus: ConsoleFocus::Editor,
+            session: None,
+            kind: None,
+            status: ConsoleStatus::Connecting,
+            error: None,
+            connection: None,
+            cancel: None,
+            last_sql: None,
+            running_sql: None,
+            exported: None,
+        }
+    }
+
+    pub fn open(
+        &mut self,
+        args: ConnectionArgs,
+        kind: DatabaseKind,
+        reason: Option<String>,
+        deadline: Option<SessionDeadline>,
+    ) {
+        self.connection = Some(QueryConnection::open(args.clone(), kind));
+        self.session = Some(HeldConnection::new(args, reason, deadline));
+        self.kind = Some(kind);
+        self.status = ConsoleStatus::Connecting;
+        self.error = None;
+        self.cancel = None;
+        self.last_sql = None;
+        self.running_sql = None;
+        self.exported = None;
+        self.focus = ConsoleFocus::Editor;
+        self.results.reset();
+        self.schema.reset();
+    }
+
+    // Dropping the connection stops its worker and tunnel, the editor keeps its text
+    pub fn reset(&mut self) {
+        self.connection = None;
+        self.cancel = None;
+        self.session = None;
+        self.kind = None;
+        self.error = None;
+        self.last_sql = None;
+        self.running_sql = None;
+        self.results.reset();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.session.is_some()
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.status, ConsoleStatus::Running(_) | ConsoleStatus::Exporting(_))
+    }
+
+    pub fn poll(&mut self) {
+        let Some(connection) = &self.connection else {
+            return;
+        };
+        while let Some(event) = connection.try_event() {
+            match event {
+                QueryEvent::Connected(cancel) => {
+                    self.cancel = Some(cancel);
+                    self.status = ConsoleStatus::Ready;
+                    connection.browse(SchemaPath::Root);
+                },
+                QueryEvent::Finished(Ok(result)) => {
+                    self.results.set_result(result);
+                    self.last_sql = self.running_sql.take();
+                    self.status = ConsoleStatus::Ready;
+                },
+                QueryEvent::Finished(Err(err)) => {
+                    self.running_sql = None;
+                    self.error = Some(err);
+                    self.status = ConsoleStatus::Ready;
+                },
+                QueryEvent::Schema(path, result) => self.schema.set_children(path, result),
+                QueryEvent::Exported(result) => {
+                    self.exported = Some(result);
+                    self.status = ConsoleStatus::Ready;
+                },
+                QueryEvent::Closed(reason) => {
+                    self.status = ConsoleStatus::Closed(reason);
+                    self.connection = None;
+                    self.cancel = None;
+                    break;
+                },
+            }
+        }
+    }
+
+    pub fn run(&mut self) {
+        if !matches!(self.status, ConsoleStatus::Ready) || self.editor.is_empty() {
+            return;
+        }
+        let Some(connection) = &self.connection else {
+            return;
+        };
+        let sql = self.editor.value();
+        if connection.execute(&sql) {
+            self.status = ConsoleStatus::Running(Instant::now());
+            self.error = None;
+            self.running_sql = Some(sql);
+        }
+    }
+
+    pub fn export_to_file(&mut self, format: ResultFormat, path: &str, overwrite: bool) -> Result<(), String> {
+        if !matches!(self.status, ConsoleStatus::Ready) {
+            return Err("The console is busy".to_string());
+        }
+        let (Some(sql), Some(connection)) = (&self.last_sql, &self.connection) else {
+            return Err("Run a query before exporting its result".to_string());
+        };
+        if !connection.export(sql, format, path, overwrite) {
+            return Err("The connection is closed".to_string());
+        }
+        self.status = ConsoleStatus::Exporting(Instant::now());
+        Ok(())
+    }
+
+    pub fn take_exported(&mut self) -> Option<Result<ExportedFile, String>> {
+        self.exported.take()
+    }
+
+    pub fn cancel(&self) {
+        if let (true, Some(cancel)) = (self.is_running(), &self.cancel) {
+            cancel.cancel();
+        }
+    }
+
+    pub fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            ConsoleFocus::Editor => ConsoleFocus::Results,
+            ConsoleFocus::Results if self.schema.visible => ConsoleFocus::Schema,
+            ConsoleFocus::Results | ConsoleFocus::Schema => ConsoleFocus::Editor,
+        };
+    }
+
+    pub fn toggle_schema(&mut self) {
+        self.schema.visible = !self.schema.visible;
+        self.focus = if self.schema.visible { ConsoleFocus::Schema } else { ConsoleFocus::Editor };
+    }
+
+    // Expanding a node that was never loaded fetches its children on the connection
+    pub fn expand_schema(&mut self, toggle: bool) {
+        let path = if toggle { self.schema.toggle_selected() } else { self.schema.expand_selected() };
+        self.browse(path);
+    }
+
+    pub fn refresh_schema(&mut self) {
+        let path = self.schema.refresh_selected();
+        self.browse(path);
+    }
+
+    pub fn reload_schema(&mut self) {
+        let path = self.schema.reset();
+        self.browse(Some(path));
+    }
+
+    pub fn insert_schema_name(&mut self) {
+        if let Some(name) = self.schema.selected_name() {
+            self.editor.insert_text(&name);
+            self.focus = ConsoleFocus::Editor;
+        }
+    }
+
+    fn browse(&mut self, path: Option<SchemaPath>) {
+        let (Some(path), Some(connection)) = (path, &self.connection) else {
+            return;
+        };
+        if !connection.browse(path.clone()) {
+            self.schema.set_children(path, Err("Connection closed".to_string()));
+        }
+    }
+
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        if self.focus != ConsoleFocus::Editor {
+            return;
+        }
+        let [_, _, editor_area, _] = console_areas(area, self.schema.visible);
+        self.editor.set_cursor(frame, editor_block(true).inner(editor_area));
+    }
+
+    fn render_status(&self, area: Rect, buf: &mut Buffer) {
+        let (Some(session), Some(kind)) = (&self.session, self.kind) else {
+            return;
+        };
+        let args = &session.args;
+        let status = match &self.status {
+            ConsoleStatus::Connecting => "connecting through a tunnel...".to_string(),
+            ConsoleStatus::Ready => "ready".to_string(),
+            ConsoleStatus::Running(started) => format!("running for {}s", started.elapsed().as_secs()),
+            ConsoleStatus::Exporting(started) => format!("exporting for {}s", started.elapsed().as_secs()),
+            ConsoleStatus::Closed(reason) => format!("disconnected: {}", reason),
+        };
+        let mut line = format!("{} {}@{}/{} · {}", kind.to_string(), args.db_user, args.instance, args.db_name, status);
+        if let Some(deadline) = &session.deadline {
+            line.push_str(&format!(" · {} left", format_duration(deadline.remaining())));
+        }
+
+        let style = match &self.status {
+            ConsoleStatus::Closed(_) => Style::new().fg(Color::Red),
+            ConsoleStatus::Running(_) | ConsoleStatus::Exporting(_) => Style::new().fg(Color::Yellow),
+            _ => Style::new(),
+        };
+        Paragraph::new(line).style(style).centered().render(area, buf);
+    }
+
+    fn render_editor(&self, area: Rect, buf: &mut Buffer) {
+        let block = editor_block(self.focus == ConsoleFocus::Editor);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        self.editor.render(inner, buf);
+    }
+
+    fn render_results(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .border_style(focus_style(self.focus == ConsoleFocus::Results));
+
+        match &self.error {
+            Some(err) => Paragraph::new(err.clone())
+                .block(block.title(Line::raw(" Error ").centered()))
+                .style(Style::new().fg(Color::Red))
+                .wrap(Wrap { trim: false })
+                .render(area, buf),
+            None => self.results.render(area, buf, block),
+        }
+    }
+}
+
+// Status line on top, then the schema browser on the left of the editor and results
+fn console_areas(area: Rect, show_schema: bool) -> [Rect; 4] {
+    let [status_area, body_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+    ]).areas(area);
+    let schema_width = if show_schema { Constraint::Percentage(30) } else { Constraint::Length(0) };
+    let [schema_area, query_area] = Layout::horizontal([
+        schema_width,
+        Constraint::Min(0),
+    ]).areas(body_area);
+    let [editor_area, results_area] = Layout::vertical([
+        Constraint::Percentage(35),
+        Constraint::Min(0),
+    ]).areas(query_area);
+    [status_area, schema_area, editor_area, results_area]
+}
+
+fn editor_block(focused: bool) -> Block<'static> {
+    Block::new()
+        .title(Line::raw(" SQL ").centered())
+        .borders(Borders::ALL)
+        .border_style(focus_style(focused))
+}
+
+fn focus_style(focused: bool) -> Style {
+    if focused {
+        Style::new().bold()
+    } else {
+        Style::new().dark_gray()
+    }
+}
@@ -0,0 +1,291 @@
+use std::time::Instant;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Cell, HighlightSpacing, Padding, Paragraph, Row, StatefulWidget, Table, TableState, Widget},
+};
+use tracing::{event, Level};
+
+use crate::{
+    core::recording::{self, Cast, CastEvent, RecordingFile},
+    widgets::{session_view::render_screen, styles},
+};
+
+// Pauses longer than this are shortened during playback
+const MAX_IDLE_SECONDS: f64 = 2.0;
+
+const SPEEDS: [f64; 7] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+
+// Lists recorded transcripts and plays the selected one back
+pub struct ReplayView {
+    pub state: TableState,
+    recordings: Vec<RecordingFile>,
+    player: Option<Player>,
+}
+
+// Replays a transcript into its own VT100 screen, seeking backwards replays from the start
+struct Player {
+    name: String,
+    cast: Cast,
+    parser: vt100::Parser,
+    next_event: usize,
+    position: f64,
+    speed_index: usize,
+    paused: bool,
+    last_tick: Instant,
+}
+
+impl Widget for &ReplayView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        match &self.player {
+            Some(player) => player.render(area, buf),
+            None => self.render_recordings(area, buf),
+        }
+    }
+}
+
+impl ReplayView {
+    pub fn new() -> ReplayView {
+        ReplayView {
+            state: TableState::default(),
+            recordings: vec![],
+            player: None,
+        }
+    }
+
+    pub fn reload(&mut self) {
+        match recording::list_recordings() {
+            Ok(recordings) => self.recordings = recordings,
+            Err(err) => {
+                event!(Level::ERROR, "failed to list recordings: {}", err);
+                self.recordings.clear();
+            },
+        }
+        let selected = match (self.state.selected(), self.recordings.len()) {
+            (_, 0) => None,
+            (Some(index), count) => Some(index.min(count - 1)),
+            (None, _) => Some(0),
+        };
+        self.state.select(selected);
+    }
+
+    pub fn reset(&mut self) {
+        self.state = TableState::default();
+        self.recordings.clear();
+        self.player = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.player.is_some()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.player.as_ref().is_some_and(|player| !player.paused)
+    }
+
+    pub fn select_next(&mut self) {
+        if self.recordings.is_empty() {
+            return;
+        }
+        let next = self.state.selected().map_or(0, |index| index + 1);
+        self.state.select(Some(next.min(self.recordings.len() - 1)));
+    }
+
+    pub fn select_previous(&mut self) {
+        let previous = self.state.selected().map_or(0, |index| index.saturating_sub(1));
+        self.state.select(Some(previous));
+    }
+
+    pub fn open_selected(&mut self) -> Result<(), String> {
+        let Some(file) = self.state.selected().and_then(|index| self.recordings.get(index)) else {
+            return Ok(());
+        };
+        let cast = recording::read_cast(&file.path)
+            .map_err(|err| format!("Failed to read {}: {}", file.name, err))?;
+        self.player = Some(Player::new(file.name.clone(), cast));
+        Ok(())
+    }
+
+    pub fn close_player(&mut self) {
+        self.player = None;
+    }
+
+    pub fn tick(&mut self) {
+        if let Some(player) = &mut self.player {
+            player.tick();
+        }
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if let Some(player) = &mut self.player {
+            player.toggle_pause();
+        }
+    }
+
+    pub fn seek(&mut self, seconds: f64) {
+        if let Some(player) = &mut self.player {
+            player.seek_to(player.position + seconds);
+        }
+    }
+
+    pub fn restart(&mut self) {
+        if let Some(player) = &mut self.player {
+            player.seek_to(0.0);
+        }
+    }
+
+    pub fn change_speed(&mut self, faster: bool) {
+        if let Some(player) = &mut self.player {
+            player.speed_index = if faster {
+                (player.speed_index + 1).min(SPEEDS.len() - 1)
+            } else {
+                player.speed_index.saturating_sub(1)
+            };
+        }
+    }
+
+    fn render_recordings(&self, area: Rect, buf: &mut Buffer) {
+        let directory = recording::recordings_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default();
+        let block = Block::new()
+            .title(Line::raw(format!(" Recordings ({}) ", self.recordings.len())).centered())
+            .title_bottom(Line::raw(format!(" {} ", directory)).centered())
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 0, 0));
+
+        let header = Row::new(["Recording", "Modified", "Size"]).bold();
+        let rows: Vec<Row> = self.recordings
+            .iter()
+            .map(|file| Row::new([
+                Cell::from(file.name.clone()),
+                Cell::from(file.modified_label()),
+                Cell::from(file.size_label()),
+            ]))
+            .collect();
+        let widths = [
+            Constraint::Fill(1),
+            Constraint::Length(19),
+            Constraint::Length(10),
+        ];
+
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .highlight_style(styles::SELECTED_STYLE)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(table, area, buf, &mut self.state.clone());
+    }
+}
+
+impl Player {
+    fn new(name: String, cast: Cast) -> Player {
+        let parser = vt100::Parser::new(cast.header.height, cast.header.width, 0);
+        Player {
+            name,
+            cast,
+            parser,
+            next_event: 0,
+            position: 0.0,
+            speed_index: 2,
+            paused: false,
+            last_tick: Instant::now(),
+        }
+    }
+
+    fn tick(&mut self) {
+        let now = Instant::now();
+        if !self.paused {
+            self.position += now.duration_since(self.last_tick).as_secs_f64() * SPEEDS[self.speed_index];
+            if let Some(next) = self.cast.events.get(self.next_event) {
+                if next.time - self.position > MAX_IDLE_SECONDS {
+                    self.position = next.time - MAX_IDLE_SECONDS;
+                }
+            }
+        }
+        self.last_tick = now;
+        self.apply_until(self.position);
+
+        if self.next_event >= self.cast.events.len() {
+            self.position = self.cast.duration();
+            self.paused = true;
+        }
+    }
+
+    // Resuming a finished replay starts over
+    fn toggle_pause(&mut self) {
+        if self.paused && self.next_event >= self.cast.events.len() {
+            self.seek_to(0.0);
+        }
+        self.paused = !self.paused;
+        self.last_tick = Instant::now();
+    }
+
+    fn seek_to(&mut self, target: f64) {
+        let target = target.clamp(0.0, self.cast.duration());
+        if target < self.position {
+            self.parser = vt100::Parser::new(self.cast.header.height, self.cast.header.width, 0);
+            self.next_event = 0;
+        }
+        self.position = target;
+        self.apply_until(target);
+    }
+
+    fn apply_until(&mut self, time: f64) {
+        while let Some(event) = self.cast.events.get(self.next_event) {
+            if event.time > time {
+                break;
+            }
+            apply_event(&mut self.parser, event);
+            self.next_event += 1;
+        }
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer) {
+        let [status_area, screen_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ]).areas(area);
+
+        let status = format!(
+            "{}  {} / {}  {}x  {}",
+            self.name,
+            format_position(self.position),
+            format_position(self.cast.duration()),
+            SPEEDS[self.speed_index],
+            if self.paused { "Paused" } else { "Playing" },
+        );
+        Paragraph::new(status).centered().render(status_area, buf);
+
+        let block = Block::new()
+            .title(Line::raw(format!(" {} ", self.cast.header.title.clone().unwrap_or_default())).centered())
+            .borders(Borders::ALL);
+        let inner = block.inner(screen_area);
+        block.render(screen_area, buf);
+        render_screen(self.parser.screen(), inner, buf);
+    }
+}
+
+fn apply_event(parser: &mut vt100::Parser, event: &CastEvent) {
+    match event.code.as_str() {
+        "o" => parser.process(event.data.as_bytes()),
+        "r" => {
+            let size = event.data
+                .split_once('x')
+                .and_then(|(cols, rows)| Some((rows.parse().ok()?, cols.parse().ok()?)));
+            if let Some((rows, cols)) = size {
+                parser.screen_mut().set_size(rows, cols);
+            }
+        },
+        _ => {},
+    }
+}
+
+fn format_position(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
@@ -0,0 +1,141 @@
+use ratatui::{buffer::Buffer, layout::Rect, widgets::{Block, Borders, Clear, Padding, Widget}, Frame};
+
+use crate::{
+    core::access_request::{AccessRequestArgs, RequestableResource},
+    widgets::{
+        confirmation_toggle::{ConfirmationOption, ConfirmationToggle},
+        dialog::get_dialog_layout,
+        text_input::TextInput,
+    }
+};
+
+pub enum RequestStep {
+    Reason,
+    Roles,
+    Confirmation,
+}
+
+pub struct RequestDialog {
+    pub reason_input: TextInput,
+    pub roles_input: TextInput,
+    pub confirmation_toggle: ConfirmationToggle,
+    pub ready_to_submit: Option<bool>,
+    pub selected_resource: Option<RequestableResource>,
+    pub current_step: RequestStep,
+}
+
+impl Widget for &RequestDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        match self.current_step {
+            RequestStep::Reason => self.render_input(" Request Reason ", &self.reason_input, area, buf),
+            RequestStep::Roles => self.render_input(" Roles (comma separated, optional) ", &self.roles_input, area, buf),
+            RequestStep::Confirmation => self.render_confirmation(area, buf),
+        }
+    }
+}
+
+impl RequestDialog {
+    pub fn new() -> RequestDialog {
+        RequestDialog {
+            reason_input: TextInput::new(),
+            roles_input: TextInput::new(),
+            confirmation_toggle: ConfirmationToggle::new(),
+            ready_to_submit: None,
+            selected_resource: None,
+            current_step: RequestStep::Reason,
+        }
+    }
+
+    pub fn next_step(&mut self) {
+        match self.current_step {
+            RequestStep::Reason => self.current_step = RequestStep::Roles,
+            RequestStep::Roles => self.current_step = RequestStep::Confirmation,
+            RequestStep::Confirmation => self.submit(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.reason_input.reset();
+        self.roles_input.reset();
+        self.confirmation_toggle.reset();
+
+        self.current_step = RequestStep::Reason;
+        self.ready_to_submit = None;
+        self.selected_resource = None;
+    }
+
+    pub fn current_input(&mut self) -> Option<&mut TextInput> {
+        match self.current_step {
+            RequestStep::Reason => Some(&mut self.reason_input),
+            RequestStep::Roles => Some(&mut self.roles_input),
+            RequestStep::Confirmation => None,
+        }
+    }
+
+    pub fn to_access_request_args(&self, cluster: &str) -> AccessRequestArgs {
+        let roles = self.roles_input.value
+            .split(',')
+            .map(|role| role.trim().to_string())
+            .filter(|role| !role.is_empty())
+            .collect();
+
+        AccessRequestArgs {
+            resource_ids: self.selected_resource
+                .iter()
+                .map(|resource| resource.resource_id(cluster))
+                .collect(),
+            roles,
+            reason: self.reason_input.value.trim().to_string(),
+        }
+    }
+
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        let input_dialog_area = get_dialog_layout(40, 10, area);
+        match self.current_step {
+            RequestStep::Reason => self.reason_input.set_cursor(frame, input_dialog_area),
+            RequestStep::Roles => self.roles_input.set_cursor(frame, input_dialog_area),
+            RequestStep::Confirmation => {},
+        }
+    }
+
+    fn submit(&mut self) {
+        match self.confirmation_toggle.get_selected() {
+            ConfirmationOption::Yes => self.ready_to_submit = Some(true),
+            ConfirmationOption::No => self.ready_to_submit = Some(false),
+        }
+    }
+
+    fn render_input(&self, title: &str, input: &TextInput, area: Rect, buf: &mut Buffer) {
+        let input_dialog_area = get_dialog_layout(40, 10, area);
+
+        let block = Block::new()
+            .title(title)
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        Widget::render(Clear, input_dialog_area, buf);
+        Widget::render(block, input_dialog_area, buf);
+
+        input.render(input_dialog_area, buf);
+    }
+
+    fn render_confirmation(&self, area: Rect, buf: &mut Buffer) {
+        let confirmation_dialog_area = get_dialog_layout(40, 15, area);
+
+        let resource_name = self.selected_resource
+            .as_ref()
+            .map(|resource| resource.name())
+            .unwrap_or_default();
+        let title = format!(" Request access to {}? ", resource_name);
+
+        let block = Block::new()
+            .title(title)
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        Widget::render(Clear, confirmation_dialog_area, buf);
+        Widget::render(block, confirmation_dialog_area, buf);
+
+        self.confirmation_toggle.render(confirmation_dialog_area, buf);
+    }
+}
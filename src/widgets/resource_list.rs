@@ -0,0 +1,109 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, HighlightSpacing, List, ListItem, ListState, Padding, StatefulWidget, Widget},
+};
+
+use crate::{core::resources::Resource, widgets::{details_table::DetailsTable, styles}};
+
+// Marks logged in applications and the current kubernetes context
+const ACTIVE_MARKER: &str = "●";
+
+// Same list and details layout as the database list, for the other resource tabs
+pub struct StatefulResourceList<T: Resource> {
+    pub state: ListState,
+    pub items: Vec<T>,
+    pub active: Vec<String>,
+    pub details: DetailsTable,
+    title: String,
+}
+
+impl<T: Resource> StatefulResourceList<T> {
+    pub fn new(title: &str) -> StatefulResourceList<T> {
+        StatefulResourceList {
+            state: ListState::default(),
+            items: vec![],
+            active: vec![],
+            details: DetailsTable::new(),
+            title: title.to_string(),
+        }
+    }
+
+    pub fn with_items(&mut self, items: Vec<T>) {
+        self.items = items;
+
+        let sections = self.selected_item()
+            .map(|item| item.details())
+            .unwrap_or_default();
+        self.details.with_sections(sections);
+    }
+
+    pub fn select_next(&mut self) {
+        self.state.select_next();
+        self.details.reset();
+    }
+
+    pub fn select_previous(&mut self) {
+        self.state.select_previous();
+        self.details.reset();
+    }
+
+    pub fn with_active(&mut self, active: Vec<String>) {
+        self.active = active;
+    }
+
+    pub fn selected_item(&self) -> Option<&T> {
+        self.state.selected().and_then(|index| self.items.get(index))
+    }
+
+    fn render_entries(&self, area: Rect, buf: &mut Buffer) {
+        let title = format!(" {} ({}) ", self.title, self.items.len());
+
+        let block = Block::new()
+            .title(Line::raw(title).centered())
+            .borders(Borders::ALL)
+            .padding(Padding::new(5, 5, 1, 1));
+
+        let items: Vec<ListItem> = self
+            .items
+            .iter()
+            .map(|item| {
+                let name = item.name();
+                if self.active.iter().any(|active| active == name) {
+                    ListItem::from(format!("{} {}", ACTIVE_MARKER, name)).green()
+                } else {
+                    ListItem::from(format!("  {}", name))
+                }
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(styles::SELECTED_STYLE)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(list, area, buf, &mut self.state.clone());
+    }
+
+    fn render_details(&self, area: Rect, buf: &mut Buffer) {
+        if self.selected_item().is_some() {
+            self.details.render(area, buf);
+        }
+    }
+}
+
+impl<T: Resource> Widget for &StatefulResourceList<T> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let horizontal = Layout::horizontal([
+            Constraint::Percentage(50),
+            Constraint::Percentage(50),
+        ]);
+        let [left_area, right_area] = horizontal.areas(area);
+
+        self.render_entries(left_area, buf);
+        self.render_details(right_area, buf);
+    }
+}
@@ -0,0 +1,148 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    widgets::{Block, Borders, Clear, ListState, Padding, Widget},
+    Frame
+};
+
+use crate::{
+    core::result_export::{ResultDestination, ResultFormat},
+    widgets::{dialog::get_dialog_layout, ide_export_dialog::render_options, text_input::TextInput},
+};
+
+pub enum ResultExportStep {
+    FormatSelection,
+    DestinationSelection,
+    PathInput,
+}
+
+pub struct ResultExportDialog {
+    pub format_state: ListState,
+    pub destination_state: ListState,
+    pub path_input: TextInput,
+    // What the export would replace, set once the user was warned about it
+    pub overwrite: Option<String>,
+    pub current_step: ResultExportStep,
+    instance: String,
+}
+
+impl Widget for &ResultExportDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        match self.current_step {
+            ResultExportStep::FormatSelection => {
+                let items = ResultFormat::ALL.map(|format| format.to_string());
+                render_options(" Export Results As ", &items, &self.format_state, area, buf);
+            },
+            ResultExportStep::DestinationSelection => {
+                let items = ResultDestination::ALL.map(|destination| destination.to_string());
+                render_options(" Export Results To ", &items, &self.destination_state, area, buf);
+            },
+            ResultExportStep::PathInput => self.render_path_input(area, buf),
+        }
+    }
+}
+
+impl ResultExportDialog {
+    pub fn new() -> ResultExportDialog {
+        ResultExportDialog {
+            format_state: ListState::default(),
+            destination_state: ListState::default(),
+            path_input: TextInput::new(),
+            overwrite: None,
+            current_step: ResultExportStep::FormatSelection,
+            instance: String::new(),
+        }
+    }
+
+    pub fn open(&mut self, instance: &str) {
+        self.reset();
+        self.format_state.select(Some(0));
+        self.destination_state.select(Some(0));
+        self.instance = instance.to_string();
+    }
+
+    pub fn reset(&mut self) {
+        self.format_state = ListState::default();
+        self.destination_state = ListState::default();
+        self.path_input.reset();
+        self.overwrite = None;
+        self.current_step = ResultExportStep::FormatSelection;
+        self.instance.clear();
+    }
+
+    pub fn selected_format(&self) -> ResultFormat {
+        self.format_state
+            .selected()
+            .and_then(|index| ResultFormat::ALL.get(index))
+            .copied()
+            .unwrap_or(ResultFormat::Csv)
+    }
+
+    pub fn selected_destination(&self) -> ResultDestination {
+        self.destination_state
+            .selected()
+            .and_then(|index| ResultDestination::ALL.get(index))
+            .copied()
+            .unwrap_or(ResultDestination::File)
+    }
+
+    pub fn select_next(&mut self) {
+        match self.current_step {
+            ResultExportStep::FormatSelection => self.format_state.select_next(),
+            ResultExportStep::DestinationSelection => self.destination_state.select_next(),
+            ResultExportStep::PathInput => {},
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        match self.current_step {
+            ResultExportStep::FormatSelection => self.format_state.select_previous(),
+            ResultExportStep::DestinationSelection => self.destination_state.select_previous(),
+            ResultExportStep::PathInput => {},
+        }
+    }
+
+    // Returns true once the destination is known, the clipboard needs no path
+    pub fn next_step(&mut self) -> bool {
+        match self.current_step {
+            ResultExportStep::FormatSelection => {
+                self.current_step = ResultExportStep::DestinationSelection;
+            },
+            ResultExportStep::DestinationSelection => {
+                if self.selected_destination() == ResultDestination::Clipboard {
+                    return true;
+                }
+                let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+                let default_path = format!("{}-{}.{}", self.instance, timestamp, self.selected_format().extension());
+                self.path_input.set_value(&default_path);
+                self.current_step = ResultExportStep::PathInput;
+            },
+            ResultExportStep::PathInput => return !self.path_input.value.is_empty(),
+        }
+        false
+    }
+
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        if let ResultExportStep::PathInput = self.current_step {
+            self.path_input.set_cursor(frame, get_dialog_layout(60, 10, area));
+        }
+    }
+
+    fn render_path_input(&self, area: Rect, buf: &mut Buffer) {
+        let input_dialog_area = get_dialog_layout(60, 10, area);
+
+        let title = match &self.overwrite {
+            Some(conflict) => format!(" {}, <enter> to overwrite ", conflict),
+            None => " Results File ".to_string(),
+        };
+        let block = Block::new()
+            .title(title)
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        Widget::render(Clear, input_dialog_area, buf);
+        Widget::render(block, input_dialog_area, buf);
+
+        self.path_input.render(input_dialog_area, buf);
+    }
+}
@@ -0,0 +1,152 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Cell, HighlightSpacing, Row, StatefulWidget, Table, TableState},
+};
+
+use crate::{core::query::QueryResult, widgets::styles};
+
+// Widest a column gets, longer values are cut
+const MAX_COLUMN_WIDTH: usize = 40;
+
+// Rows sampled when measuring column widths
+const WIDTH_SAMPLE_ROWS: usize = 500;
+
+const PAGE_ROWS: usize = 20;
+
+// Scrollable rows of a query result, columns scroll horizontally one at a time
+pub struct ResultsTable {
+    pub result: Option<QueryResult>,
+    pub state: TableState,
+    column_offset: usize,
+    widths: Vec<u16>,
+}
+
+impl ResultsTable {
+    pub fn new() -> ResultsTable {
+        ResultsTable {
+            result: None,
+            state: TableState::default(),
+            column_offset: 0,
+            widths: vec![],
+        }
+    }
+
+    pub fn set_result(&mut self, result: QueryResult) {
+        self.widths = column_widths(&result);
+        self.state.select(if result.rows.is_empty() { None } else { Some(0) });
+        self.column_offset = 0;
+        self.result = Some(result);
+    }
+
+    pub fn reset(&mut self) {
+        self.result = None;
+        self.state = TableState::default();
+        self.column_offset = 0;
+        self.widths.clear();
+    }
+
+    pub fn select_next(&mut self) {
+        self.move_selection(1);
+    }
+
+    pub fn select_previous(&mut self) {
+        self.move_selection(-1);
+    }
+
+    pub fn page_down(&mut self) {
+        self.move_selection(PAGE_ROWS as isize);
+    }
+
+    pub fn page_up(&mut self) {
+        self.move_selection(-(PAGE_ROWS as isize));
+    }
+
+    pub fn scroll_right(&mut self) {
+        if self.column_offset + 1 < self.widths.len() {
+            self.column_offset += 1;
+        }
+    }
+
+    pub fn scroll_left(&mut self) {
+        self.column_offset = self.column_offset.saturating_sub(1);
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer, block: Block) {
+        let Some(result) = &self.result else {
+            ratatui::widgets::Widget::render(block, area, buf);
+            return;
+        };
+
+        let title = if self.column_offset > 0 {
+            format!(" {} (columns {}-{}) ", result.summary(), self.column_offset + 1, result.columns.len())
+        } else {
+            format!(" {} ", result.summary())
+        };
+        let block = block.title(Line::raw(title).centered());
+
+        let header = Row::new(
+            result.columns.iter().skip(self.column_offset).map(|column| Cell::from(column.clone()))
+        ).bold();
+
+        let null_style = Style::new().fg(Color::DarkGray).add_modifier(Modifier::ITALIC);
+        let rows: Vec<Row> = result.rows
+            .iter()
+            .map(|row| Row::new(
+                row.iter().skip(self.column_offset).map(|value| match value {
+                    Some(value) => Cell::from(single_line(value)),
+                    None => Cell::from("NULL").style(null_style),
+                })
+            ))
+            .collect();
+        let widths: Vec<Constraint> = self.widths
+            .iter()
+            .skip(self.column_offset)
+            .map(|width| Constraint::Length(*width))
+            .collect();
+
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(block.borders(Borders::ALL))
+            .column_spacing(2)
+            .highlight_style(styles::SELECTED_STYLE)
+            .highlight_spacing(HighlightSpacing::Always)
+            .highlight_symbol(">");
+
+        StatefulWidget::render(table, area, buf, &mut self.state.clone());
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let count = self.result.as_ref().map_or(0, |result| result.rows.len());
+        if count == 0 {
+            return;
+        }
+        let current = self.state.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, count as isize - 1);
+        self.state.select(Some(next as usize));
+    }
+}
+
+// Sized to the header and the widest sampled value, within bounds
+fn column_widths(result: &QueryResult) -> Vec<u16> {
+    result.columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            let widest_value = result.rows
+                .iter()
+                .take(WIDTH_SAMPLE_ROWS)
+                .map(|row| row.get(index).and_then(|value| value.as_ref()).map_or(4, |value| value.chars().count()))
+                .max()
+                .unwrap_or(0);
+            widest_value.max(column.chars().count()).clamp(4, MAX_COLUMN_WIDTH) as u16
+        })
+        .collect()
+}
+
+// Line breaks would spill into the next row
+fn single_line(value: &str) -> String {
+    value.replace(['\n', '\r'], "↵").replace('\t', " ")
+}
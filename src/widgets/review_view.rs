@@ -0,0 +1,164 @@
+use std::time::Duration;
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Padding, Paragraph, StatefulWidget, Widget, Wrap},
+    Frame
+};
+
+use crate::{
+    core::{
+        access_request::{AccessRequest, RequestState, ReviewDecision},
+        poller::Poller,
+        tsh::Tsh,
+    },
+    widgets::{
+        access_request_view::{clamp_selection, format_request_details, state_style},
+        dialog::get_dialog_layout,
+        styles,
+        text_input::TextInput,
+    },
+};
+
+// How often the reviewable requests are polled while the panel is open
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+pub struct ReviewView {
+    pub requests: Vec<AccessRequest>,
+    pub state: ListState,
+    pub comment_input: TextInput,
+    pub decision: Option<ReviewDecision>,
+
+    poller: Poller<Vec<AccessRequest>>,
+}
+
+impl Widget for &ReviewView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let horizontal = Layout::horizontal([
+            Constraint::Percentage(50),
+            Constraint::Percentage(50),
+        ]);
+        let [left_area, right_area] = horizontal.areas(area);
+
+        self.render_requests(left_area, buf);
+        self.render_request_details(right_area, buf);
+
+        if self.decision.is_some() {
+            self.render_comment(area, buf);
+        }
+    }
+}
+
+impl ReviewView {
+    pub fn new() -> ReviewView {
+        ReviewView {
+            requests: vec![],
+            state: ListState::default(),
+            comment_input: TextInput::new(),
+            decision: None,
+            poller: Poller::new(REFRESH_INTERVAL),
+        }
+    }
+
+    pub fn refresh_now(&mut self) {
+        self.poller.refresh_now();
+    }
+
+    pub fn poll_requests(&mut self) {
+        if let Some(requests) = self.poller.poll(Tsh::read_reviewable_requests) {
+            self.requests = requests;
+            clamp_selection(&mut self.state, self.requests.len());
+        }
+    }
+
+    pub fn selected_request(&self) -> Option<&AccessRequest> {
+        self.state.selected().and_then(|index| self.requests.get(index))
+    }
+
+    // Only pending requests can still receive a review
+    pub fn start_review(&mut self, decision: ReviewDecision) {
+        if let Some(request) = self.selected_request() {
+            if request.spec.state == RequestState::Pending {
+                self.decision = Some(decision);
+            }
+        }
+    }
+
+    pub fn cancel_review(&mut self) {
+        self.comment_input.reset();
+        self.decision = None;
+    }
+
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        if self.decision.is_some() {
+            self.comment_input.set_cursor(frame, get_dialog_layout(40, 10, area));
+        }
+    }
+
+    fn render_requests(&self, area: Rect, buf: &mut Buffer) {
+        let title = format!(" Awaiting Review ({}) ", self.requests.len());
+        let block = Block::new()
+            .title(Line::raw(title).centered())
+            .borders(Borders::ALL)
+            .padding(Padding::new(2, 2, 1, 1));
+
+        let items: Vec<ListItem> = self
+            .requests
+            .iter()
+            .map(|request| {
+                let state = &request.spec.state;
+                ListItem::from(Line::from(vec![
+                    Span::styled(format!("{:<9}", state.to_string()), state_style(state)),
+                    Span::raw(format!(" {} → {}", request.spec.user, request.resource_names().join(", "))),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(styles::SELECTED_STYLE)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(list, area, buf, &mut self.state.clone());
+    }
+
+    fn render_request_details(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .title(Line::raw(" Request Details ").centered())
+            .borders(Borders::ALL)
+            .padding(Padding::new(2, 2, 1, 1));
+
+        let details = match self.selected_request() {
+            Some(request) => format_request_details(request),
+            None => String::new(),
+        };
+
+        Paragraph::new(details)
+            .wrap(Wrap { trim: true })
+            .block(block)
+            .render(area, buf);
+    }
+
+    fn render_comment(&self, area: Rect, buf: &mut Buffer) {
+        let comment_dialog_area = get_dialog_layout(40, 10, area);
+
+        let decision = self.decision
+            .as_ref()
+            .map(|decision| decision.to_string())
+            .unwrap_or_default();
+        let title = format!(" {} Request, Review Comment ", decision);
+
+        let block = Block::new()
+            .title(title)
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        Widget::render(Clear, comment_dialog_area, buf);
+        Widget::render(block, comment_dialog_area, buf);
+
+        self.comment_input.render(comment_dialog_area, buf);
+    }
+}
@@ -0,0 +1,303 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, HighlightSpacing, List, ListItem, ListState, Paragraph, StatefulWidget, Widget, Wrap},
+};
+
+use crate::core::schema::{SchemaKind, SchemaNode, SchemaPath};
+
+// Lines kept for the selected node's details under the tree
+const DETAILS_HEIGHT: u16 = 8;
+
+// Tree of databases, schemas, tables and their columns, fetched a level at a time
+pub struct SchemaBrowser {
+    pub visible: bool,
+    state: ListState,
+    roots: Vec<SchemaNode>,
+    loading: bool,
+    error: Option<String>,
+}
+
+impl SchemaBrowser {
+    pub fn new() -> SchemaBrowser {
+        SchemaBrowser {
+            visible: true,
+            state: ListState::default(),
+            roots: vec![],
+            loading: false,
+            error: None,
+        }
+    }
+
+    // Drops the tree, the caller requests the root level again
+    pub fn reset(&mut self) -> SchemaPath {
+        self.state = ListState::default();
+        self.roots.clear();
+        self.loading = true;
+        self.error = None;
+        SchemaPath::Root
+    }
+
+    pub fn set_children(&mut self, path: SchemaPath, result: Result<Vec<SchemaNode>, String>) {
+        if path == SchemaPath::Root {
+            self.loading = false;
+            match result {
+                Ok(roots) => {
+                    self.roots = roots;
+                    self.state.select(if self.roots.is_empty() { None } else { Some(0) });
+                },
+                Err(err) => self.error = Some(err),
+            }
+            return;
+        }
+
+        let Some(node) = find_node(&mut self.roots, &path) else {
+            return;
+        };
+        node.loading = false;
+        match result {
+            Ok(children) => {
+                node.children = Some(children);
+                node.error = None;
+            },
+            Err(err) => {
+                node.expanded = false;
+                node.error = Some(err);
+            },
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        let count = self.entries().len();
+        if count == 0 {
+            return;
+        }
+        let next = self.state.selected().map_or(0, |index| (index + 1).min(count - 1));
+        self.state.select(Some(next));
+    }
+
+    pub fn select_previous(&mut self) {
+        let previous = self.state.selected().map_or(0, |index| index.saturating_sub(1));
+        self.state.select(Some(previous));
+    }
+
+    // Returns the path to fetch when the node was never loaded
+    pub fn expand_selected(&mut self) -> Option<SchemaPath> {
+        let node = self.selected_mut()?;
+        if !node.is_expandable() || node.loading {
+            return None;
+        }
+        node.expanded = true;
+        node.error = None;
+        if node.children.is_some() {
+            return None;
+        }
+        node.loading = true;
+        node.path.clone()
+    }
+
+    pub fn toggle_selected(&mut self) -> Option<SchemaPath> {
+        match self.selected_mut() {
+            Some(node) if node.expanded => {
+                node.expanded = false;
+                None
+            },
+            _ => self.expand_selected(),
+        }
+    }
+
+    // Collapses the selected node, or moves to its parent when already collapsed
+    pub fn collapse_selected(&mut self) {
+        let entries = self.entries();
+        let Some(index) = self.state.selected().filter(|index| *index < entries.len()) else {
+            return;
+        };
+        if let Some(node) = self.selected_mut().filter(|node| node.expanded) {
+            node.expanded = false;
+            return;
+        }
+        let (address, _) = &entries[index];
+        let parent = &address[..address.len() - 1];
+        if let Some(position) = entries.iter().position(|(other, _)| other == parent) {
+            self.state.select(Some(position));
+        }
+    }
+
+    // Forgets the selected node's children so they are fetched again
+    pub fn refresh_selected(&mut self) -> Option<SchemaPath> {
+        let node = self.selected_mut()?;
+        node.path.as_ref()?;
+        node.children = None;
+        node.expanded = false;
+        self.expand_selected()
+    }
+
+    // Name to insert in the editor, tables are qualified with their schema
+    pub fn selected_name(&self) -> Option<String> {
+        let node = self.selected()?;
+        match (&node.kind, &node.path) {
+            (SchemaKind::Group, _) => None,
+            (SchemaKind::Table | SchemaKind::View, Some(SchemaPath::Table { schema, table, .. })) => {
+                Some(format!("{}.{}", schema, table))
+            },
+            _ => Some(node.name.clone()),
+        }
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer, focused: bool) {
+        let [tree_area, details_area] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(DETAILS_HEIGHT),
+        ]).areas(area);
+
+        let border_style = if focused { Style::new().bold() } else { Style::new().dark_gray() };
+        let block = Block::new()
+            .title(Line::raw(" Schema ").centered())
+            .borders(Borders::ALL)
+            .border_style(border_style);
+
+        if let Some(err) = &self.error {
+            Paragraph::new(err.clone())
+                .block(block)
+                .style(Style::new().fg(Color::Red))
+                .wrap(Wrap { trim: false })
+                .render(area, buf);
+            return;
+        }
+        if self.loading {
+            Paragraph::new("loading...").block(block).render(area, buf);
+            return;
+        }
+
+        let items: Vec<ListItem> = self.entries()
+            .iter()
+            .filter_map(|(address, depth)| node_at(&self.roots, address).map(|node| (node, depth)))
+            .map(|(node, depth)| ListItem::new(tree_line(node, *depth)))
+            .collect();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::new().reversed())
+            .highlight_spacing(HighlightSpacing::Always)
+            .highlight_symbol(">");
+        StatefulWidget::render(list, tree_area, buf, &mut self.state.clone());
+
+        self.render_details(details_area, buf);
+    }
+
+    fn render_details(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .title(Line::raw(" Details ").centered())
+            .borders(Borders::ALL)
+            .border_style(Style::new().dark_gray());
+        let Some(node) = self.selected() else {
+            block.render(area, buf);
+            return;
+        };
+
+        let mut lines: Vec<Line> = node.properties
+            .iter()
+            .map(|(key, value)| Line::from(vec![
+                Span::raw(format!("{}: ", key)).bold(),
+                Span::raw(value.clone()),
+            ]))
+            .collect();
+        if let Some(err) = &node.error {
+            lines.push(Line::raw(err.clone()).fg(Color::Red));
+        }
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false })
+            .render(area, buf);
+    }
+
+    // Visible nodes as their index path in the tree and depth
+    fn entries(&self) -> Vec<(Vec<usize>, usize)> {
+        let mut entries = vec![];
+        flatten(&self.roots, &mut vec![], &mut entries);
+        entries
+    }
+
+    fn selected(&self) -> Option<&SchemaNode> {
+        let entries = self.entries();
+        let (address, _) = entries.get(self.state.selected()?)?;
+        node_at(&self.roots, address)
+    }
+
+    fn selected_mut(&mut self) -> Option<&mut SchemaNode> {
+        let entries = self.entries();
+        let (address, _) = entries.get(self.state.selected()?)?;
+        node_at_mut(&mut self.roots, address)
+    }
+}
+
+fn flatten(nodes: &[SchemaNode], address: &mut Vec<usize>, entries: &mut Vec<(Vec<usize>, usize)>) {
+    for (index, node) in nodes.iter().enumerate() {
+        address.push(index);
+        entries.push((address.clone(), address.len() - 1));
+        if let (true, Some(children)) = (node.expanded, &node.children) {
+            flatten(children, address, entries);
+        }
+        address.pop();
+    }
+}
+
+fn node_at<'a>(nodes: &'a [SchemaNode], address: &[usize]) -> Option<&'a SchemaNode> {
+    let (first, rest) = address.split_first()?;
+    let node = nodes.get(*first)?;
+    if rest.is_empty() {
+        return Some(node);
+    }
+    node_at(node.children.as_deref()?, rest)
+}
+
+fn node_at_mut<'a>(nodes: &'a mut [SchemaNode], address: &[usize]) -> Option<&'a mut SchemaNode> {
+    let (first, rest) = address.split_first()?;
+    let node = nodes.get_mut(*first)?;
+    if rest.is_empty() {
+        return Some(node);
+    }
+    node_at_mut(node.children.as_deref_mut()?, rest)
+}
+
+fn find_node<'a>(nodes: &'a mut [SchemaNode], path: &SchemaPath) -> Option<&'a mut SchemaNode> {
+    for node in nodes {
+        if node.path.as_ref() == Some(path) {
+            return Some(node);
+        }
+        if let Some(found) = node.children.as_deref_mut().and_then(|children| find_node(children, path)) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+fn tree_line(node: &SchemaNode, depth: usize) -> Line<'static> {
+    let marker = match (node.is_expandable(), node.expanded) {
+        (false, _) => "  ",
+        (true, false) => "▸ ",
+        (true, true) => "▾ ",
+    };
+    let name_style = match node.kind {
+        SchemaKind::Database | SchemaKind::Schema => Style::new().bold(),
+        SchemaKind::View => Style::new().italic(),
+        SchemaKind::Group => Style::new().fg(Color::Cyan),
+        _ => Style::new(),
+    };
+
+    let mut spans = vec![
+        Span::raw(format!("{}{}", "  ".repeat(depth), marker)),
+        Span::styled(node.name.clone(), name_style),
+    ];
+    if !node.detail.is_empty() {
+        spans.push(Span::raw(format!(" {}", node.detail)).dark_gray());
+    }
+    if node.loading {
+        spans.push(Span::raw(" loading...").dark_gray());
+    }
+    if node.error.is_some() {
+        spans.push(Span::raw(" !").fg(Color::Red));
+    }
+    Line::from(spans)
+}
@@ -0,0 +1,489 @@
+use std::{
+    io::Write,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Cell, Clear, HighlightSpacing, Padding, Paragraph, Row, StatefulWidget, Table, TableState, Widget, Wrap},
+    Frame
+};
+
+use crate::{
+    core::{
+        guardrail::Guardrail,
+        result_export::{create_file, existing_file},
+        schema_diff::{DiffKind, DiffLine, DiffSide, SchemaCatalog, SchemaDiff, SchemaDiffRun},
+        tsh::ConnectionArgs,
+    },
+    widgets::{dialog::get_dialog_layout, guardrail_confirmation::GuardrailConfirmation, text_input::TextInput},
+};
+
+const PAGE_ROWS: usize = 20;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DiffFocus {
+    LeftUser,
+    LeftName,
+    RightUser,
+    RightName,
+    Reason,
+    Diff,
+}
+
+// A catalog read, handed back to the app for the audit log
+pub struct CatalogRead {
+    pub args: ConnectionArgs,
+    pub succeeded: bool,
+    pub elapsed: Duration,
+}
+
+// Two databases' catalogs side by side, the left one is the reference
+pub struct SchemaDiffView {
+    pub left_user: TextInput,
+    pub left_name: TextInput,
+    pub right_user: TextInput,
+    pub right_name: TextInput,
+    pub reason_input: TextInput,
+    pub path_input: TextInput,
+    pub focus: DiffFocus,
+    // Guarded sides of the comparison, each name is typed before comparing
+    pub guarded: Vec<(String, Guardrail)>,
+    pub confirmation: GuardrailConfirmation,
+    pub require_reason: bool,
+    pub only_differences: bool,
+    // Asking for the report's file
+    pub exporting: bool,
+    // What writing the report would replace, set once the user was warned about it
+    pub overwrite: Option<String>,
+    pub left_instance: String,
+    pub right_instance: String,
+    state: TableState,
+    run: Option<SchemaDiffRun>,
+    started: Option<Instant>,
+    labels: [Option<String>; 2],
+    catalogs: [Option<Result<SchemaCatalog, String>>; 2],
+    diff: Option<SchemaDiff>,
+    generated: String,
+}
+
+impl Widget for &SchemaDiffView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [status_area, inputs_area, diff_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Min(0),
+        ]).areas(area);
+
+        self.render_status(status_area, buf);
+        self.render_inputs(inputs_area, buf);
+        self.render_diff(diff_area, buf);
+        if self.exporting {
+            self.render_path_input(area, buf);
+        }
+        if self.confirmation.is_active() {
+            self.confirmation.render(area, buf);
+        }
+    }
+}
+
+impl SchemaDiffView {
+    pub fn new() -> SchemaDiffView {
+        SchemaDiffView {
+            left_user: TextInput::new(),
+            left_name: TextInput::new(),
+            right_user: TextInput::new(),
+            right_name: TextInput::new(),
+            reason_input: TextInput::new(),
+            path_input: TextInput::new(),
+            focus: DiffFocus::LeftUser,
+            guarded: vec![],
+            confirmation: GuardrailConfirmation::new(),
+            require_reason: false,
+            only_differences: true,
+            exporting: false,
+            overwrite: None,
+            left_instance: String::new(),
+            right_instance: String::new(),
+            state: TableState::default(),
+            run: None,
+            started: None,
+            labels: [None, None],
+            catalogs: [None, None],
+            diff: None,
+            generated: String::new(),
+        }
+    }
+
+    pub fn open(&mut self, left_instance: &str, right_instance: &str, guarded: Vec<(String, Guardrail)>) {
+        self.reset();
+        self.left_instance = left_instance.to_string();
+        self.right_instance = right_instance.to_string();
+        self.require_reason = guarded.iter().any(|(_, guardrail)| guardrail.require_reason);
+        self.guarded = guarded;
+    }
+
+    // Dropping the run discards catalogs still being read
+    pub fn reset(&mut self) {
+        for input in [
+            &mut self.left_user,
+            &mut self.left_name,
+            &mut self.right_user,
+            &mut self.right_name,
+            &mut self.reason_input,
+            &mut self.path_input,
+        ] {
+            input.reset();
+        }
+        self.focus = DiffFocus::LeftUser;
+        self.guarded.clear();
+        self.confirmation.reset();
+        self.require_reason = false;
+        self.exporting = false;
+        self.overwrite = None;
+        self.left_instance.clear();
+        self.right_instance.clear();
+        self.state = TableState::default();
+        self.run = None;
+        self.started = None;
+        self.labels = [None, None];
+        self.catalogs = [None, None];
+        self.diff = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.run.is_some()
+    }
+
+    // Sides that failed to resolve are failed up front, the other one is not read
+    pub fn start(&mut self, run: Option<SchemaDiffRun>, labels: [Option<String>; 2], failures: [Option<String>; 2]) {
+        self.catalogs = failures.map(|failure| failure.map(Err));
+        self.labels = labels;
+        self.diff = None;
+        self.state = TableState::default();
+        self.run = run;
+        self.started = Some(Instant::now());
+        self.focus = DiffFocus::Diff;
+    }
+
+    // Returns the catalogs read so they can be audited
+    pub fn poll(&mut self) -> Vec<CatalogRead> {
+        let Some(run) = &self.run else {
+            return vec![];
+        };
+        let mut reads = vec![];
+        while let Some(outcome) = run.try_event() {
+            reads.push(CatalogRead {
+                args: outcome.args,
+                succeeded: outcome.result.is_ok(),
+                elapsed: outcome.elapsed,
+            });
+            self.catalogs[side_index(outcome.side)] = Some(outcome.result);
+        }
+
+        if let [Some(left), Some(right)] = &self.catalogs {
+            self.run = None;
+            if let (Ok(left), Ok(right)) = (left, right) {
+                self.diff = Some(SchemaDiff::compute(left, right));
+                self.generated = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+                self.select_first();
+            }
+        }
+        reads
+    }
+
+    pub fn next_focus(&mut self) {
+        self.focus = match self.focus {
+            DiffFocus::LeftUser => DiffFocus::LeftName,
+            DiffFocus::LeftName => DiffFocus::RightUser,
+            DiffFocus::RightUser => DiffFocus::RightName,
+            DiffFocus::RightName if self.require_reason => DiffFocus::Reason,
+            DiffFocus::RightName | DiffFocus::Reason => DiffFocus::Diff,
+            DiffFocus::Diff => DiffFocus::LeftUser,
+        };
+    }
+
+    pub fn focused_input(&mut self) -> Option<&mut TextInput> {
+        match self.focus {
+            DiffFocus::LeftUser => Some(&mut self.left_user),
+            DiffFocus::LeftName => Some(&mut self.left_name),
+            DiffFocus::RightUser => Some(&mut self.right_user),
+            DiffFocus::RightName => Some(&mut self.right_name),
+            DiffFocus::Reason => Some(&mut self.reason_input),
+            DiffFocus::Diff => None,
+        }
+    }
+
+    pub fn toggle_only_differences(&mut self) {
+        self.only_differences = !self.only_differences;
+        self.select_first();
+    }
+
+    pub fn select_next(&mut self) {
+        self.move_selection(1);
+    }
+
+    pub fn select_previous(&mut self) {
+        self.move_selection(-1);
+    }
+
+    pub fn page_down(&mut self) {
+        self.move_selection(PAGE_ROWS as isize);
+    }
+
+    pub fn page_up(&mut self) {
+        self.move_selection(-(PAGE_ROWS as isize));
+    }
+
+    pub fn report(&self) -> Option<String> {
+        let diff = self.diff.as_ref()?;
+        let [Some(left), Some(right)] = &self.labels else {
+            return None;
+        };
+        Some(diff.report(left, right, &self.generated))
+    }
+
+    pub fn open_export(&mut self) -> bool {
+        if self.diff.is_none() {
+            return false;
+        }
+        let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        let default_path = format!("schema-diff-{}-{}-{}.txt", self.left_instance, self.right_instance, timestamp);
+        self.path_input.set_value(&default_path);
+        self.overwrite = None;
+        self.exporting = true;
+        true
+    }
+
+    pub fn close_export(&mut self) {
+        self.exporting = false;
+        self.overwrite = None;
+    }
+
+    // Nothing is written the first time an existing file would be replaced, the user is asked instead
+    pub fn write_report(&mut self) -> Result<Option<PathBuf>, String> {
+        let report = self.report().ok_or("no schema diff to export")?;
+        let path = self.path_input.value.trim().to_string();
+        if self.overwrite.is_none() {
+            if let Some(conflict) = existing_file(&path) {
+                self.overwrite = Some(conflict);
+                return Ok(None);
+            }
+        }
+        let (path, mut file) = create_file(&path, self.overwrite.is_some()).map_err(|err| err.to_string())?;
+        file.write_all(report.as_bytes())
+            .and_then(|_| file.flush())
+            .map_err(|err| err.to_string())?;
+        self.close_export();
+        Ok(Some(path))
+    }
+
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        if self.confirmation.is_active() {
+            self.confirmation.set_cursor(frame, area);
+            return;
+        }
+        if self.exporting {
+            self.path_input.set_cursor(frame, get_dialog_layout(60, 10, area));
+            return;
+        }
+        let [_, inputs_area, _] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Min(0),
+        ]).areas(area);
+        let areas = input_areas(inputs_area, self.require_reason);
+        match self.focus {
+            DiffFocus::LeftUser => self.left_user.set_cursor(frame, areas[0]),
+            DiffFocus::LeftName => self.left_name.set_cursor(frame, areas[1]),
+            DiffFocus::RightUser => self.right_user.set_cursor(frame, areas[2]),
+            DiffFocus::RightName => self.right_name.set_cursor(frame, areas[3]),
+            DiffFocus::Reason => self.reason_input.set_cursor(frame, areas[4]),
+            DiffFocus::Diff => {},
+        }
+    }
+
+    fn visible_lines(&self) -> Vec<&DiffLine> {
+        match &self.diff {
+            Some(diff) if self.only_differences => diff.differences().collect(),
+            Some(diff) => diff.lines.iter().collect(),
+            None => vec![],
+        }
+    }
+
+    fn select_first(&mut self) {
+        let empty = self.visible_lines().is_empty();
+        self.state.select(if empty { None } else { Some(0) });
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let count = self.visible_lines().len();
+        if count == 0 {
+            return;
+        }
+        let current = self.state.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, count as isize - 1);
+        self.state.select(Some(next as usize));
+    }
+
+    fn render_status(&self, area: Rect, buf: &mut Buffer) {
+        let sides = format!("{} ↔ {}", self.left_instance, self.right_instance);
+        let (line, style) = match (&self.diff, self.started) {
+            (Some(diff), _) => (format!("{} · {}", sides, diff.summary()), Style::new()),
+            (None, Some(started)) if self.run.is_some() => (
+                format!("{} · reading catalogs for {}s", sides, started.elapsed().as_secs()),
+                Style::new().fg(Color::Yellow),
+            ),
+            (None, Some(_)) => (format!("{} · failed", sides), Style::new().fg(Color::Red)),
+            (None, None) => (format!("Schema diff {} · <F5> to compare", sides), Style::new()),
+        };
+        Paragraph::new(line).style(style).centered().render(area, buf);
+    }
+
+    fn render_inputs(&self, area: Rect, buf: &mut Buffer) {
+        let areas = input_areas(area, self.require_reason);
+        let left_user = format!(" {} User ", self.left_instance);
+        let right_user = format!(" {} User ", self.right_instance);
+        let inputs = [
+            (left_user.as_str(), &self.left_user, DiffFocus::LeftUser, areas[0]),
+            (" Database Name ", &self.left_name, DiffFocus::LeftName, areas[1]),
+            (right_user.as_str(), &self.right_user, DiffFocus::RightUser, areas[2]),
+            (" Database Name ", &self.right_name, DiffFocus::RightName, areas[3]),
+            (" Reason ", &self.reason_input, DiffFocus::Reason, areas[4]),
+        ];
+        for (title, input, focus, input_area) in inputs {
+            if focus == DiffFocus::Reason && !self.require_reason {
+                continue;
+            }
+            Block::new()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(focus_style(self.focus == focus))
+                .render(input_area, buf);
+            input.render(input_area, buf);
+        }
+    }
+
+    fn render_diff(&self, area: Rect, buf: &mut Buffer) {
+        let title = if self.only_differences { " Differences " } else { " All Tables " };
+        let block = Block::new()
+            .title(Line::raw(title).centered())
+            .borders(Borders::ALL)
+            .border_style(focus_style(self.focus == DiffFocus::Diff));
+
+        let errors: Vec<String> = [&self.left_instance, &self.right_instance]
+            .iter()
+            .zip(&self.catalogs)
+            .filter_map(|(instance, catalog)| match catalog {
+                Some(Err(err)) => Some(format!("{}: {}", instance, err)),
+                _ => None,
+            })
+            .collect();
+        if !errors.is_empty() {
+            Paragraph::new(errors.join("\n"))
+                .block(block.title(Line::raw(" Error ").centered()))
+                .style(Style::new().fg(Color::Red))
+                .wrap(Wrap { trim: false })
+                .render(area, buf);
+            return;
+        }
+        let Some(diff) = &self.diff else {
+            block.render(area, buf);
+            return;
+        };
+        if diff.is_identical() && self.only_differences {
+            Paragraph::new("Both schemas are identical")
+                .block(block)
+                .centered()
+                .render(area, buf);
+            return;
+        }
+
+        let header = Row::new([
+            Cell::from(""),
+            Cell::from(self.labels[0].clone().unwrap_or_default()),
+            Cell::from(self.labels[1].clone().unwrap_or_default()),
+        ]).bold();
+        let rows: Vec<Row> = self.visible_lines()
+            .into_iter()
+            .map(|line| {
+                let style = match line.kind {
+                    DiffKind::Same => Style::new(),
+                    DiffKind::Added => Style::new().fg(Color::Green),
+                    DiffKind::Removed => Style::new().fg(Color::Red),
+                    DiffKind::Changed => Style::new().fg(Color::Yellow),
+                };
+                let side = |definition: &Option<String>| match definition {
+                    Some(definition) if line.depth == 0 => format!("{} ({})", line.label, definition),
+                    Some(definition) => format!("  {}: {}", line.label, definition),
+                    None => String::new(),
+                };
+                let row = Row::new([
+                    Cell::from(line.kind.marker()),
+                    Cell::from(side(&line.left)),
+                    Cell::from(side(&line.right)),
+                ]).style(style);
+                if line.depth == 0 { row.bold() } else { row }
+            })
+            .collect();
+        let widths = [
+            Constraint::Length(1),
+            Constraint::Percentage(50),
+            Constraint::Percentage(50),
+        ];
+
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .highlight_style(Style::new().reversed())
+            .highlight_spacing(HighlightSpacing::Always)
+            .highlight_symbol(">");
+        StatefulWidget::render(table, area, buf, &mut self.state.clone());
+    }
+
+    fn render_path_input(&self, area: Rect, buf: &mut Buffer) {
+        let input_dialog_area = get_dialog_layout(60, 10, area);
+
+        let title = match &self.overwrite {
+            Some(conflict) => format!(" {}, <enter> to overwrite ", conflict),
+            None => " Report File ".to_string(),
+        };
+        let block = Block::new()
+            .title(title)
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        Widget::render(Clear, input_dialog_area, buf);
+        Widget::render(block, input_dialog_area, buf);
+
+        self.path_input.render(input_dialog_area, buf);
+    }
+}
+
+fn side_index(side: DiffSide) -> usize {
+    match side {
+        DiffSide::Left => 0,
+        DiffSide::Right => 1,
+    }
+}
+
+// Both sides' user and database name, then the reason when one is required
+fn input_areas(area: Rect, require_reason: bool) -> [Rect; 5] {
+    if require_reason {
+        Layout::horizontal([Constraint::Ratio(1, 5); 5]).areas(area)
+    } else {
+        let [left_user, left_name, right_user, right_name] = Layout::horizontal([Constraint::Ratio(1, 4); 4]).areas(area);
+        [left_user, left_name, right_user, right_name, Rect::default()]
+    }
+}
+
+fn focus_style(focused: bool) -> Style {
+    if focused {
+        Style::new().bold()
+    } else {
+        Style::new().dark_gray()
+    }
+}
@@ -21,7 +21,7 @@ impl Widget for &SearchDialog {
 
 impl SearchDialog {
     pub fn new() -> SearchDialog {
-        return SearchDialog {
+        SearchDialog {
             search: String::new(), 
             cursor_index: 0,
         }
@@ -85,6 +85,6 @@ impl SearchDialog {
 
     fn clamp_index(&self, index: usize) -> usize {
         let char_count = self.search.chars().count();
-        return index.clamp(0, char_count)
+        index.clamp(0, char_count)
     }
 }
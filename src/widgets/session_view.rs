@@ -0,0 +1,248 @@
+use crossterm::event::KeyEvent;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Tabs, Widget},
+    Frame
+};
+
+use crate::{
+    core::{
+        pty_session::{key_to_bytes, PtySession},
+        session_limit::format_duration,
+    },
+    widgets::styles,
+};
+
+// Tabs of embedded terminal sessions, only the active one is rendered
+pub struct SessionView {
+    pub sessions: Vec<PtySession>,
+    pub active: usize,
+}
+
+impl Widget for &SessionView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [tabs_area, pane_area] = session_areas(area);
+
+        self.render_tabs(tabs_area, buf);
+        self.render_pane(pane_area, buf);
+    }
+}
+
+impl SessionView {
+    pub fn new() -> SessionView {
+        SessionView {
+            sessions: vec![],
+            active: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    pub fn add(&mut self, session: PtySession) {
+        self.sessions.push(session);
+        self.active = self.sessions.len() - 1;
+    }
+
+    pub fn next(&mut self) {
+        if !self.sessions.is_empty() {
+            self.active = (self.active + 1) % self.sessions.len();
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.sessions.is_empty() {
+            self.active = (self.active + self.sessions.len() - 1) % self.sessions.len();
+        }
+    }
+
+    pub fn active_session(&mut self) -> Option<&mut PtySession> {
+        self.sessions.get_mut(self.active)
+    }
+
+    // Rows and columns available to a session inside the main area
+    pub fn pane_size(area: Rect) -> (u16, u16) {
+        let [_, pane_area] = session_areas(area);
+        let inner = pane_block().inner(pane_area);
+        (inner.height.max(1), inner.width.max(1))
+    }
+
+    pub fn resize_all(&mut self, area: Rect) {
+        let (rows, cols) = SessionView::pane_size(area);
+        for session in &mut self.sessions {
+            session.resize(rows, cols);
+        }
+    }
+
+    // Removes finished sessions, returning them with their exit codes
+    pub fn take_exited(&mut self) -> Vec<(PtySession, u32)> {
+        let mut exited = vec![];
+        let mut index = 0;
+        while index < self.sessions.len() {
+            match self.sessions[index].try_exit_code() {
+                Some(code) => exited.push((self.sessions.remove(index), code)),
+                None => index += 1,
+            }
+        }
+        self.active = self.active.min(self.sessions.len().saturating_sub(1));
+        exited
+    }
+
+    // Scrolls the active session's history, any other input returns to the live screen
+    pub fn scroll(&mut self, up: bool, area: Rect) {
+        let (rows, _) = SessionView::pane_size(area);
+        let step = (rows / 2).max(1) as usize;
+        if let Some(session) = self.sessions.get(self.active) {
+            if let Ok(mut parser) = session.parser.lock() {
+                let screen = parser.screen_mut();
+                let offset = if up {
+                    screen.scrollback() + step
+                } else {
+                    screen.scrollback().saturating_sub(step)
+                };
+                screen.set_scrollback(offset);
+            }
+        }
+    }
+
+    pub fn send_key(&mut self, key: KeyEvent) {
+        let Some(session) = self.sessions.get_mut(self.active) else {
+            return;
+        };
+        let application_cursor = match session.parser.lock() {
+            Ok(mut parser) => {
+                parser.screen_mut().set_scrollback(0);
+                parser.screen().application_cursor()
+            },
+            Err(_) => false,
+        };
+        let bytes = key_to_bytes(key, application_cursor);
+        if !bytes.is_empty() {
+            session.write_input(&bytes);
+        }
+    }
+
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        let [_, pane_area] = session_areas(area);
+        let inner = pane_block().inner(pane_area);
+        let Some(session) = self.sessions.get(self.active) else {
+            return;
+        };
+        let Ok(parser) = session.parser.lock() else {
+            return;
+        };
+        let screen = parser.screen();
+        if screen.hide_cursor() || screen.scrollback() > 0 {
+            return;
+        }
+        let (row, col) = screen.cursor_position();
+        if row < inner.height && col < inner.width {
+            frame.set_cursor(inner.x + col, inner.y + row);
+        }
+    }
+
+    fn render_tabs(&self, area: Rect, buf: &mut Buffer) {
+        let titles: Vec<Line> = self.sessions
+            .iter()
+            .map(|session| {
+                let mut title = session.title.clone();
+                if session.recording.is_some() {
+                    title.push_str(" [rec]");
+                }
+                match &session.deadline {
+                    Some(deadline) => {
+                        let title = format!("{} {}", title, format_duration(deadline.remaining()));
+                        if deadline.in_warning() {
+                            Line::styled(title, Style::new().fg(Color::Yellow))
+                        } else {
+                            Line::raw(title)
+                        }
+                    },
+                    None => Line::raw(title),
+                }
+            })
+            .collect();
+
+        Tabs::new(titles)
+            .select(self.active)
+            .highlight_style(styles::SELECTED_STYLE)
+            .render(area, buf);
+    }
+
+    fn render_pane(&self, area: Rect, buf: &mut Buffer) {
+        let block = pane_block();
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let Some(session) = self.sessions.get(self.active) else {
+            return;
+        };
+        let Ok(parser) = session.parser.lock() else {
+            return;
+        };
+        render_screen(parser.screen(), inner, buf);
+    }
+}
+
+// Draws a VT100 screen cell by cell, anything past the area is clipped
+pub fn render_screen(screen: &vt100::Screen, area: Rect, buf: &mut Buffer) {
+    for row in 0..area.height {
+        for col in 0..area.width {
+            let Some(cell) = screen.cell(row, col) else {
+                continue;
+            };
+            if cell.is_wide_continuation() {
+                continue;
+            }
+            let symbol = if cell.has_contents() { cell.contents() } else { " " };
+            buf.get_mut(area.x + col, area.y + row)
+                .set_symbol(symbol)
+                .set_style(cell_style(cell));
+        }
+    }
+}
+
+fn session_areas(area: Rect) -> [Rect; 2] {
+    Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+    ]).areas(area)
+}
+
+fn pane_block() -> Block<'static> {
+    Block::new().borders(Borders::TOP)
+}
+
+fn cell_style(cell: &vt100::Cell) -> Style {
+    let mut style = Style::new()
+        .fg(to_color(cell.fgcolor()))
+        .bg(to_color(cell.bgcolor()));
+    if cell.bold() {
+        style = style.add_modifier(Modifier::BOLD);
+    }
+    if cell.dim() {
+        style = style.add_modifier(Modifier::DIM);
+    }
+    if cell.italic() {
+        style = style.add_modifier(Modifier::ITALIC);
+    }
+    if cell.underline() {
+        style = style.add_modifier(Modifier::UNDERLINED);
+    }
+    if cell.inverse() {
+        style = style.add_modifier(Modifier::REVERSED);
+    }
+    style
+}
+
+fn to_color(color: vt100::Color) -> Color {
+    match color {
+        vt100::Color::Default => Color::Reset,
+        vt100::Color::Idx(index) => Color::Indexed(index),
+        vt100::Color::Rgb(red, green, blue) => Color::Rgb(red, green, blue),
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::Stylize,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Padding, Paragraph, StatefulWidget, Widget, Wrap},
+    Frame
+};
+
+use crate::{
+    core::{
+        query::DatabaseKind,
+        snippet::{ParameterType, Snippet, SnippetParameter},
+    },
+    widgets::{dialog::get_dialog_layout, styles, text_input::TextInput},
+};
+
+pub enum SnippetStep {
+    Selection,
+    Parameters,
+}
+
+// Picks a snippet for the console, then asks for its parameters one at a time
+pub struct SnippetDialog {
+    pub state: ListState,
+    pub input: TextInput,
+    pub current_step: SnippetStep,
+    snippets: Vec<Snippet>,
+    // What the values are escaped for
+    kind: DatabaseKind,
+    parameters: Vec<SnippetParameter>,
+    values: HashMap<String, String>,
+}
+
+impl Widget for &SnippetDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        match self.current_step {
+            SnippetStep::Selection => self.render_selection(area, buf),
+            SnippetStep::Parameters => self.render_parameter(area, buf),
+        }
+    }
+}
+
+impl SnippetDialog {
+    pub fn new() -> SnippetDialog {
+        SnippetDialog {
+            state: ListState::default(),
+            input: TextInput::new(),
+            current_step: SnippetStep::Selection,
+            snippets: vec![],
+            kind: DatabaseKind::Postgres,
+            parameters: vec![],
+            values: HashMap::new(),
+        }
+    }
+
+    pub fn open(&mut self, snippets: Vec<Snippet>, kind: DatabaseKind) {
+        self.reset();
+        self.state.select(if snippets.is_empty() { None } else { Some(0) });
+        self.snippets = snippets;
+        self.kind = kind;
+    }
+
+    pub fn reset(&mut self) {
+        self.state = ListState::default();
+        self.input.reset();
+        self.current_step = SnippetStep::Selection;
+        self.snippets.clear();
+        self.parameters.clear();
+        self.values.clear();
+    }
+
+    pub fn select_next(&mut self) {
+        if let SnippetStep::Selection = self.current_step {
+            self.state.select_next();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if let SnippetStep::Selection = self.current_step {
+            self.state.select_previous();
+        }
+    }
+
+    // Returns the rendered SQL once every parameter has a value
+    pub fn next_step(&mut self) -> Option<Result<String, String>> {
+        let snippet = self.selected()?.clone();
+        match self.current_step {
+            SnippetStep::Selection => {
+                self.parameters = snippet.parameters();
+                self.parameters.reverse();
+                self.current_step = SnippetStep::Parameters;
+            },
+            SnippetStep::Parameters => {
+                let parameter = self.parameters.pop()?;
+                self.values.insert(parameter.name, self.input.value.clone());
+            },
+        }
+
+        match self.parameters.last() {
+            Some(parameter) => {
+                self.input.set_value(parameter.default.as_deref().unwrap_or_default());
+                None
+            },
+            None => Some(snippet.render(&self.values, self.kind)),
+        }
+    }
+
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        if let SnippetStep::Parameters = self.current_step {
+            self.input.set_cursor(frame, get_dialog_layout(60, 10, area));
+        }
+    }
+
+    fn selected(&self) -> Option<&Snippet> {
+        self.state.selected().and_then(|index| self.snippets.get(index))
+    }
+
+    fn render_selection(&self, area: Rect, buf: &mut Buffer) {
+        let dialog_area = get_dialog_layout(70, 60, area);
+        let [list_area, preview_area] = Layout::horizontal([
+            Constraint::Percentage(45),
+            Constraint::Min(0),
+        ]).areas(dialog_area);
+
+        let items: Vec<ListItem> = self.snippets
+            .iter()
+            .map(|snippet| ListItem::new(Line::from(vec![
+                Span::raw(snippet.name.clone()),
+                Span::raw(format!(" ({})", snippet.scope())).dark_gray(),
+            ])))
+            .collect();
+        let list = List::new(items)
+            .block(Block::new()
+                .title(" Snippets ")
+                .borders(Borders::ALL)
+                .padding(Padding::new(1, 1, 1, 1)))
+            .highlight_style(styles::SELECTED_STYLE)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        Widget::render(Clear, dialog_area, buf);
+        StatefulWidget::render(list, list_area, buf, &mut self.state.clone());
+
+        let mut lines = vec![];
+        if let Some(snippet) = self.selected() {
+            if let Some(description) = &snippet.description {
+                lines.push(Line::raw(description.clone()).italic());
+                lines.push(Line::raw(""));
+            }
+            lines.extend(snippet.sql.lines().map(|line| Line::raw(line.to_string())));
+        }
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::new()
+                .title(" Preview ")
+                .borders(Borders::ALL)
+                .padding(Padding::new(1, 1, 1, 1)))
+            .render(preview_area, buf);
+    }
+
+    fn render_parameter(&self, area: Rect, buf: &mut Buffer) {
+        let input_dialog_area = get_dialog_layout(60, 10, area);
+        let title = match self.parameters.last() {
+            Some(parameter) if parameter.parameter_type != ParameterType::Text => {
+                format!(" Value for {} ({}) ", parameter.name, parameter.parameter_type.to_string())
+            },
+            Some(parameter) => format!(" Value for {} ", parameter.name),
+            None => " Value ".to_string(),
+        };
+
+        let block = Block::new()
+            .title(title)
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        Widget::render(Clear, input_dialog_area, buf);
+        Widget::render(block, input_dialog_area, buf);
+
+        self.input.render(input_dialog_area, buf);
+    }
+}
@@ -0,0 +1,65 @@
+use ratatui::{buffer::Buffer, layout::Rect, widgets::{Block, Borders, Clear, Padding, Widget}};
+
+use crate::{
+    core::resources::{Node, Resource, SshArgs},
+    widgets::{dialog::get_dialog_layout, user_list::StatefulUserList},
+};
+
+pub struct SshDialog {
+    pub login_list: StatefulUserList,
+    pub selected_node: Option<Node>,
+}
+
+impl Widget for &SshDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let login_dialog_area = get_dialog_layout(30, 30, area);
+
+        let title = match &self.selected_node {
+            Some(node) => format!(" Select Login for {} ", node.name()),
+            None => " Select Login ".to_string(),
+        };
+
+        let block = Block::new()
+            .title(title)
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        Widget::render(Clear, login_dialog_area, buf);
+        Widget::render(block, login_dialog_area, buf);
+
+        self.login_list.render(login_dialog_area, buf);
+    }
+}
+
+impl SshDialog {
+    pub fn new() -> SshDialog {
+        SshDialog {
+            login_list: StatefulUserList::new(),
+            selected_node: None,
+        }
+    }
+
+    pub fn open(&mut self, node: Node, logins: Vec<String>) {
+        self.login_list.reset();
+        self.login_list.with_items(logins);
+        self.login_list.state.select(Some(0));
+        self.selected_node = Some(node);
+    }
+
+    pub fn reset(&mut self) {
+        self.login_list.reset();
+        self.selected_node = None;
+    }
+
+    pub fn to_ssh_args(&self) -> Option<SshArgs> {
+        let node = self.selected_node.as_ref()?;
+        let login = self.login_list.state
+            .selected()
+            .and_then(|index| self.login_list.items.get(index))?;
+
+        Some(SshArgs {
+            login: login.clone(),
+            host: node.spec.hostname.clone(),
+        })
+    }
+}
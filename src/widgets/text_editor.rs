@@ -0,0 +1,184 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::Line,
+    widgets::{Paragraph, Widget},
+    Frame
+};
+
+// Multi-line input, the cursor is a (line, char) position and scrolling follows it
+pub struct TextEditor {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+}
+
+impl TextEditor {
+    pub fn new() -> TextEditor {
+        TextEditor {
+            lines: vec![String::new()],
+            row: 0,
+            col: 0,
+        }
+    }
+
+    pub fn value(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.iter().all(|line| line.trim().is_empty())
+    }
+
+    pub fn set_value(&mut self, value: &str) {
+        self.lines = value.split('\n').map(str::to_string).collect();
+        self.row = self.lines.len() - 1;
+        self.col = self.line_length(self.row);
+    }
+
+    pub fn reset(&mut self) {
+        self.lines = vec![String::new()];
+        self.row = 0;
+        self.col = 0;
+    }
+
+    pub fn enter_char(&mut self, character: char) {
+        let index = self.byte_index();
+        self.lines[self.row].insert(index, character);
+        self.col += 1;
+    }
+
+    // Pastes text at the cursor, line breaks split the current line
+    pub fn insert_text(&mut self, text: &str) {
+        for character in text.chars() {
+            match character {
+                '\n' => self.split_line(false),
+                '\r' => {},
+                character => self.enter_char(character),
+            }
+        }
+    }
+
+    // Keeps the current line's indentation
+    pub fn new_line(&mut self) {
+        self.split_line(true);
+    }
+
+    pub fn delete_char(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let index = self.byte_index();
+            self.lines[self.row].remove(index);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_length(self.row);
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    pub fn delete_forward(&mut self) {
+        if self.col < self.line_length(self.row) {
+            let index = self.byte_index();
+            self.lines[self.row].remove(index);
+        } else if self.row + 1 < self.lines.len() {
+            let line = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    pub fn move_cursor_left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_length(self.row);
+        }
+    }
+
+    pub fn move_cursor_right(&mut self) {
+        if self.col < self.line_length(self.row) {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    pub fn move_cursor_up(&mut self) {
+        if self.row > 0 {
+            self.row -= 1;
+            self.col = self.col.min(self.line_length(self.row));
+        }
+    }
+
+    pub fn move_cursor_down(&mut self) {
+        if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = self.col.min(self.line_length(self.row));
+        }
+    }
+
+    pub fn move_cursor_home(&mut self) {
+        self.col = 0;
+    }
+
+    pub fn move_cursor_end(&mut self) {
+        self.col = self.line_length(self.row);
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let (row_offset, col_offset) = self.offsets(area);
+        let lines: Vec<Line> = self.lines
+            .iter()
+            .skip(row_offset)
+            .take(area.height as usize)
+            .map(|line| Line::raw(line.chars().skip(col_offset).collect::<String>()))
+            .collect();
+
+        Paragraph::new(lines).render(area, buf);
+    }
+
+    // Expects the same area the editor was rendered into
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        let (row_offset, col_offset) = self.offsets(area);
+        let x_position = area.x + (self.col - col_offset) as u16;
+        let y_position = area.y + (self.row - row_offset) as u16;
+        frame.set_cursor(x_position, y_position);
+    }
+
+    // First visible line and column, just enough to keep the cursor in view
+    fn offsets(&self, area: Rect) -> (usize, usize) {
+        let height = area.height.max(1) as usize;
+        let width = area.width.max(1) as usize;
+        (
+            (self.row + 1).saturating_sub(height),
+            (self.col + 1).saturating_sub(width),
+        )
+    }
+
+    fn split_line(&mut self, keep_indent: bool) {
+        let index = self.byte_index();
+        let rest = self.lines[self.row].split_off(index);
+        let indent: String = if keep_indent {
+            self.lines[self.row].chars().take_while(|character| *character == ' ').collect()
+        } else {
+            String::new()
+        };
+        self.col = indent.chars().count();
+        self.row += 1;
+        self.lines.insert(self.row, indent + &rest);
+    }
+
+    fn line_length(&self, row: usize) -> usize {
+        self.lines[row].chars().count()
+    }
+
+    fn byte_index(&self) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices()
+            .map(|(index, _)| index)
+            .nth(self.col)
+            .unwrap_or(line.len())
+    }
+}
@@ -0,0 +1,92 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    widgets::{Block, Padding, Paragraph, Widget},
+    Frame
+};
+
+pub struct TextInput {
+    pub value: String,
+    cursor_index: usize,
+}
+
+impl Widget for &TextInput {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        self.render_text_input(area, buf);
+    }
+}
+
+impl TextInput {
+    pub fn new() -> TextInput {
+        TextInput {
+            value: String::new(),
+            cursor_index: 0,
+        }
+    }
+
+    fn render_text_input(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .padding(Padding::new(2, 1, 2, 1));
+
+        let input = Paragraph::new(self.value.clone())
+            .block(block);
+
+        Widget::render(input, area, buf);
+    }
+
+    // Expects the same area the input was rendered into
+    pub fn set_cursor(&self, frame: &mut Frame, area: Rect) {
+        // Increment positions by two due to padding on the paragraph block
+        let x_position = area.x + self.cursor_index as u16 + 2;
+        let y_position = area.y + 2;
+        frame.set_cursor(x_position, y_position);
+    }
+
+    pub fn move_cursor_right(&mut self) {
+        let moved = self.cursor_index.saturating_add(1);
+        self.cursor_index = self.clamp_index(moved);
+    }
+
+    pub fn move_cursor_left(&mut self) {
+        let moved = self.cursor_index.saturating_sub(1);
+        self.cursor_index = self.clamp_index(moved);
+    }
+
+    pub fn enter_char(&mut self, character: char) {
+        let byte_index = self.value
+            .char_indices()
+            .map(|(index, _)| index)
+            .nth(self.cursor_index)
+            .unwrap_or(self.value.len());
+
+        self.value.insert(byte_index, character);
+        self.move_cursor_right();
+    }
+
+    pub fn delete_char(&mut self) {
+        if self.cursor_index == 0 {
+            return;
+        }
+        let current_index = self.cursor_index;
+        let before_delete = self.value.chars().take(current_index - 1);
+        let after_delete = self.value.chars().skip(current_index);
+
+        self.value = before_delete.chain(after_delete).collect();
+        self.move_cursor_left();
+    }
+
+    pub fn set_value(&mut self, value: &str) {
+        self.value = value.to_string();
+        self.cursor_index = self.value.chars().count();
+    }
+
+    pub fn reset(&mut self) {
+        self.value.clear();
+        self.cursor_index = 0;
+    }
+
+    fn clamp_index(&self, index: usize) -> usize {
+        let char_count = self.value.chars().count();
+        index.clamp(0, char_count)
+    }
+}
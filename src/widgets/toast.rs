@@ -0,0 +1,79 @@
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, Padding, Paragraph, Widget},
+};
+
+// How long a toast stays on screen
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+
+pub enum ToastKind {
+    Info,
+    Error,
+}
+
+struct ToastMessage {
+    text: String,
+    kind: ToastKind,
+    shown_at: Instant,
+}
+
+pub struct Toast {
+    message: Option<ToastMessage>,
+}
+
+impl Widget for &Toast {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let message = match &self.message {
+            Some(message) if message.shown_at.elapsed() < TOAST_DURATION => message,
+            _ => return,
+        };
+
+        let color = match message.kind {
+            ToastKind::Info => Color::Green,
+            ToastKind::Error => Color::Red,
+        };
+
+        // Bottom right corner of the area, sized to the message
+        let width = (message.text.chars().count() as u16 + 4).min(area.width);
+        let vertical = Layout::vertical([Constraint::Length(3)]).flex(Flex::End);
+        let horizontal = Layout::horizontal([Constraint::Length(width)]).flex(Flex::End);
+        let [toast_area] = vertical.areas(area);
+        let [toast_area] = horizontal.areas(toast_area);
+
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .border_style(Style::new().fg(color))
+            .padding(Padding::horizontal(1));
+
+        Widget::render(Clear, toast_area, buf);
+        Paragraph::new(message.text.clone())
+            .block(block)
+            .render(toast_area, buf);
+    }
+}
+
+impl Toast {
+    pub fn new() -> Toast {
+        Toast { message: None }
+    }
+
+    pub fn info(&mut self, text: &str) {
+        self.show(text, ToastKind::Info);
+    }
+
+    pub fn error(&mut self, text: &str) {
+        self.show(text, ToastKind::Error);
+    }
+
+    fn show(&mut self, text: &str, kind: ToastKind) {
+        self.message = Some(ToastMessage {
+            text: text.to_string(),
+            kind,
+            shown_at: Instant::now(),
+        });
+    }
+}
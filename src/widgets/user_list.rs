@@ -12,7 +12,7 @@ pub struct StatefulUserList {
 
 impl StatefulUserList {
     pub fn new() -> StatefulUserList {
-        return StatefulUserList {
+        StatefulUserList {
             state: ListState::default(),
             items: vec![],
         }
@@ -34,8 +34,7 @@ impl StatefulUserList {
         let items: Vec<ListItem> = self
             .items
             .iter()
-            .enumerate()
-            .map(|(_, item)| {
+            .map(|item| {
                 ListItem::from(item.clone())
             })
             .collect();